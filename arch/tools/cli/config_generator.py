@@ -14,8 +14,14 @@ SUPPORTED_PROVIDERS = [
     "mistral",
     "openai",
     "gemini",
+    "bedrock",
+    "azure_openai",
+    "openai_compatible",
 ]
 
+# providers without a well known host, each deployment is served on its own
+PROVIDERS_WITH_BASE_URL = ["azure_openai", "openai_compatible"]
+
 
 def get_endpoint_and_port(endpoint, protocol):
     endpoint_tokens = endpoint.split(":")
@@ -97,6 +103,7 @@ def validate_and_render_schema():
     llm_provider_name_set = set()
     llms_with_usage = []
     model_name_keys = set()
+    weighted_model_names = set()
     model_usage_name_keys = set()
     for llm_provider in config_yaml["llm_providers"]:
         if llm_provider.get("usage", None):
@@ -107,11 +114,19 @@ def validate_and_render_schema():
             )
 
         model_name = llm_provider.get("model")
-        if model_name in model_name_keys:
+        # llm_providers can share a model to spread load across them, each one must then set its own name and weight
+        is_weighted = (
+            llm_provider.get("weight") is not None
+            and llm_provider.get("name") is not None
+        )
+        shares_model = model_name in model_name_keys
+        if shares_model and not (is_weighted and model_name in weighted_model_names):
             raise Exception(
-                f"Duplicate model name {model_name}, please provide unique model name for each llm_provider"
+                f"Duplicate model name {model_name}, please provide unique model name for each llm_provider or set name and weight on every llm_provider that shares it"
             )
         model_name_keys.add(model_name)
+        if is_weighted:
+            weighted_model_names.add(model_name)
         if llm_provider.get("name") is None:
             llm_provider["name"] = model_name
 
@@ -136,7 +151,7 @@ def validate_and_render_schema():
                 f"Please provide provider interface as part of model name {model_name} using the format <provider>/<model_id>. For example, use 'openai/gpt-3.5-turbo' instead of 'gpt-3.5-turbo' "
             )
 
-        if model_id in model_name_keys:
+        if model_id in model_name_keys and not shares_model:
             raise Exception(
                 f"Duplicate model_id {model_id}, please provide unique model_id for each llm_provider"
             )
@@ -163,6 +178,23 @@ def validate_and_render_schema():
             del llm_provider["provider"]
         updated_llm_providers.append(llm_provider)
 
+        provider_interface = llm_provider["provider_interface"]
+        if provider_interface == "bedrock" and not llm_provider.get("base_url"):
+            aws = llm_provider.get("aws", None)
+            if aws is None:
+                raise Exception(
+                    f"Please provide aws region and credentials for bedrock model {model_name}"
+                )
+            llm_provider["base_url"] = (
+                f"https://bedrock-runtime.{aws['region']}.amazonaws.com"
+            )
+        if provider_interface in PROVIDERS_WITH_BASE_URL and not llm_provider.get(
+            "base_url"
+        ):
+            raise Exception(
+                f"Must provide base_url for provider {provider_interface} for model {model_name}"
+            )
+
         if llm_provider.get("base_url", None):
             base_url = llm_provider["base_url"]
             urlparse_result = urlparse(base_url)
@@ -191,6 +223,24 @@ def validate_and_render_schema():
             llm_provider["protocol"] = protocol
             llms_with_endpoint.append(llm_provider)
 
+    for llm_provider in updated_llm_providers:
+        for fallback in llm_provider.get("fallbacks", []):
+            if (
+                fallback not in llm_provider_name_set
+                and fallback not in model_name_keys
+            ):
+                raise Exception(
+                    f"Unknown fallback {fallback} for llm_provider {llm_provider.get('name')}, please add it in llm_providers section in your arch_config.yaml file"
+                )
+
+    for ratelimit in config_yaml.get("ratelimits", []):
+        if not any(
+            ratelimit.get(limit) for limit in ["limit", "input_limit", "output_limit"]
+        ):
+            raise Exception(
+                f"Ratelimit for model {ratelimit.get('model')} must set at least one of limit, input_limit or output_limit"
+            )
+
     if len(model_usage_name_keys) > 0:
         routing_llm_provider = config_yaml.get("routing", {}).get("llm_provider", None)
         if routing_llm_provider and routing_llm_provider not in llm_provider_name_set:
@@ -1,6 +1,7 @@
 import pytest
 from unittest import mock
 import sys
+import yaml
 from cli.config_generator import validate_and_render_schema
 
 # Patch sys.path to allow import from cli/
@@ -224,6 +225,122 @@ llm_providers:
 tracing:
   random_sampling: 100
 
+""",
+    },
+    {
+        "id": "shared_model_without_weight",
+        "expected_error": "Duplicate model name",
+        "arch_config": """
+version: v0.1.0
+
+listeners:
+  egress_traffic:
+    address: 0.0.0.0
+    port: 12000
+    message_format: openai
+    timeout: 30s
+
+llm_providers:
+
+  - name: openai-account-a
+    model: openai/gpt-4o
+    access_key: $OPENAI_API_KEY_A
+    weight: 70
+
+  - name: openai-account-b
+    model: openai/gpt-4o
+    access_key: $OPENAI_API_KEY_B
+
+""",
+    },
+    {
+        "id": "unknown_fallback",
+        "expected_error": "Unknown fallback",
+        "arch_config": """
+version: v0.1.0
+
+listeners:
+  egress_traffic:
+    address: 0.0.0.0
+    port: 12000
+    message_format: openai
+    timeout: 30s
+
+llm_providers:
+
+  - model: openai/gpt-4o
+    access_key: $OPENAI_API_KEY
+    default: true
+    fallbacks:
+      - mistral/ministral-3b
+
+""",
+    },
+    {
+        "id": "azure_openai_without_base_url",
+        "expected_error": "Must provide base_url for provider azure_openai",
+        "arch_config": """
+version: v0.1.0
+
+listeners:
+  egress_traffic:
+    address: 0.0.0.0
+    port: 12000
+    message_format: openai
+    timeout: 30s
+
+llm_providers:
+
+  - model: azure_openai/gpt-4o
+    access_key: $AZURE_API_KEY
+    api_version: "2024-10-21"
+
+""",
+    },
+    {
+        "id": "bedrock_without_aws",
+        "expected_error": "Please provide aws region and credentials",
+        "arch_config": """
+version: v0.1.0
+
+listeners:
+  egress_traffic:
+    address: 0.0.0.0
+    port: 12000
+    message_format: openai
+    timeout: 30s
+
+llm_providers:
+
+  - model: bedrock/anthropic.claude-3-5-sonnet-20240620-v1:0
+
+""",
+    },
+    {
+        "id": "ratelimit_without_limit",
+        "expected_error": "must set at least one of limit, input_limit or output_limit",
+        "arch_config": """
+version: v0.1.0
+
+listeners:
+  egress_traffic:
+    address: 0.0.0.0
+    port: 12000
+    message_format: openai
+    timeout: 30s
+
+llm_providers:
+
+  - model: openai/gpt-4o
+    access_key: $OPENAI_API_KEY
+    default: true
+
+ratelimits:
+  - model: gpt-4o
+    selector:
+      key: x-team
+      value: research
+
 """,
     },
 ]
@@ -270,3 +387,87 @@ def test_validate_and_render_schema_tests(monkeypatch, arch_config_test_case):
             with pytest.raises(Exception) as excinfo:
                 validate_and_render_schema()
             assert expected_error in str(excinfo.value)
+
+
+def test_validate_and_render_provider_clusters(monkeypatch):
+    monkeypatch.setenv("ARCH_CONFIG_FILE", "fake_arch_config.yaml")
+    monkeypatch.setenv("ARCH_CONFIG_SCHEMA_FILE", "../arch_config_schema.yaml")
+    monkeypatch.setenv("ENVOY_CONFIG_TEMPLATE_FILE", "./envoy.template.yaml")
+    monkeypatch.setenv("ARCH_CONFIG_FILE_RENDERED", "fake_arch_config_rendered.yaml")
+    monkeypatch.setenv("ENVOY_CONFIG_FILE_RENDERED", "fake_envoy.yaml")
+    monkeypatch.setenv("TEMPLATE_ROOT", "../")
+
+    arch_config = """
+version: v0.1.0
+
+listeners:
+  egress_traffic:
+    address: 0.0.0.0
+    port: 12000
+    message_format: openai
+    timeout: 30s
+
+llm_providers:
+
+  - name: bedrock-claude
+    model: bedrock/anthropic.claude-3-5-sonnet-20240620-v1:0
+    aws:
+      region: eu-west-1
+      access_key_id: $AWS_ACCESS_KEY_ID
+      secret_access_key: $AWS_SECRET_ACCESS_KEY
+
+  - name: azure-gpt-4o
+    model: azure_openai/gpt-4o
+    access_key: $AZURE_API_KEY
+    base_url: https://example.openai.azure.com
+    deployment: prod-gpt-4o
+    api_version: "2024-10-21"
+
+  - name: together-llama
+    model: openai_compatible/meta-llama/Llama-3.3-70B-Instruct-Turbo
+    access_key: $TOGETHER_API_KEY
+    base_url: https://api.together.xyz
+"""
+
+    # the config is mocked, the schema and the envoy template are read from the repo
+    real_open = open
+    written = {}
+
+    def fake_open(file, mode="r", *args, **kwargs):
+        if file == "fake_arch_config.yaml":
+            return mock.mock_open(read_data=arch_config).return_value
+        if file in ["fake_envoy.yaml", "fake_arch_config_rendered.yaml"]:
+            written[file] = mock.mock_open().return_value
+            return written[file]
+        return real_open(file, mode, *args, **kwargs)
+
+    with mock.patch("builtins.open", fake_open):
+        validate_and_render_schema()
+
+    envoy_config = yaml.safe_load(written["fake_envoy.yaml"].write.call_args[0][0])
+    clusters = {
+        cluster["name"]: cluster
+        for cluster in envoy_config["static_resources"]["clusters"]
+    }
+    for name, address in [
+        ("bedrock-claude", "bedrock-runtime.eu-west-1.amazonaws.com"),
+        ("azure-gpt-4o", "example.openai.azure.com"),
+        ("together-llama", "api.together.xyz"),
+    ]:
+        endpoint = clusters[name]["load_assignment"]["endpoints"][0]["lb_endpoints"][0]
+        socket_address = endpoint["endpoint"]["address"]["socket_address"]
+        assert socket_address["address"] == address
+        assert socket_address["port_value"] == 443
+
+    arch_config_rendered = yaml.safe_load(
+        written["fake_arch_config_rendered.yaml"].write.call_args[0][0]
+    )
+    llm_providers = {
+        llm_provider["name"]: llm_provider
+        for llm_provider in arch_config_rendered["llm_providers"]
+    }
+    assert llm_providers["bedrock-claude"]["provider_interface"] == "bedrock"
+    assert llm_providers["azure-gpt-4o"]["deployment"] == "prod-gpt-4o"
+    assert llm_providers["together-llama"]["model"] == (
+        "meta-llama/Llama-3.3-70B-Instruct-Turbo"
+    )
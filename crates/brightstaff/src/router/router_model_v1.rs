@@ -146,6 +146,7 @@ impl RouterModel for RouterModelV1 {
                     content: Some(ContentType::Text(
                         message.content.as_ref().unwrap().to_string(),
                     )),
+                    ..Default::default()
                 }
             })
             .collect::<Vec<Message>>();
@@ -162,6 +163,7 @@ impl RouterModel for RouterModelV1 {
             messages: vec![Message {
                 content: Some(ContentType::Text(router_message)),
                 role: USER_ROLE.to_string(),
+                ..Default::default()
             }],
             temperature: Some(0.01),
             ..Default::default()
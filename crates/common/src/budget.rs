@@ -0,0 +1,424 @@
+use crate::configuration::{Budget, BudgetPeriod};
+use crate::ratelimit::glob_matches;
+use crate::shared_data::{self, SharedStore};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+// Number of keys the usage of a budget is spread over, whatever the number of selector values.
+const USAGE_SLOTS: u64 = 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("exceeded {period} budget for {selector}, the budget resets at {}", format_utc(*.reset_at))]
+    ExceededBudget {
+        selector: String,
+        period: BudgetPeriod,
+        reset_at: u64,
+    },
+    #[error("no budget usage is free for {selector}")]
+    NoFreeUsage { selector: String },
+}
+
+impl Error {
+    /// Seconds from `now_secs` until the budget resets.
+    pub fn retry_after(&self, now_secs: u64) -> u64 {
+        match self {
+            Error::ExceededBudget { reset_at, .. } => reset_at.saturating_sub(now_secs),
+            // usages are handed over once their period ends
+            Error::NoFreeUsage { .. } => 1,
+        }
+    }
+}
+
+// Usage of a budget within the current period, stored in shared data so that every worker charges
+// the same budget.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BudgetUsage {
+    period_start: u64,
+    tokens: u64,
+    micro_usd: u64,
+}
+
+/// Tokens charged against a budget on ingress, settled with the actual usage on egress.
+#[derive(Debug, Clone)]
+pub struct Reservation {
+    prefix: String,
+    owner: String,
+    period_start: u64,
+    tokens: u64,
+}
+
+/// Charges `tokens` against every budget that applies to the request. `headers` holds the values
+/// of the request headers named by the budget selectors.
+pub fn reserve<S: SharedStore + ?Sized>(
+    store: &S,
+    budgets: &[Budget],
+    model: &str,
+    headers: &HashMap<String, String>,
+    tokens: u64,
+    now_secs: u64,
+) -> Result<Vec<Reservation>, Error> {
+    let mut reservations = Vec::new();
+
+    for budget in budgets {
+        let Some((prefix, value)) = budget_key(budget, model, headers) else {
+            continue;
+        };
+        let (period_start, period_end) = period_bounds(budget.limit.period, now_secs);
+
+        // the usage of a past period can make room for the usage of another selector value
+        let is_idle = |usage: &BudgetUsage| usage.period_start != period_start;
+        let owner = shared_data::encode_key_parts(&[value]);
+        let result = shared_data::update_owned(
+            store,
+            &prefix,
+            USAGE_SLOTS,
+            &owner,
+            is_idle,
+            |usage: &mut BudgetUsage| {
+                if usage.period_start != period_start {
+                    *usage = BudgetUsage {
+                        period_start,
+                        ..Default::default()
+                    };
+                }
+                if is_exhausted(budget, usage, tokens) {
+                    return false;
+                }
+                usage.tokens += tokens;
+                true
+            },
+        );
+
+        match result {
+            Ok((_, true)) => reservations.push(Reservation {
+                prefix,
+                owner,
+                period_start,
+                tokens,
+            }),
+            Ok((_, false)) => {
+                // hand back what the budgets checked so far have already charged
+                settle(store, &reservations, 0, 0.0);
+                return Err(Error::ExceededBudget {
+                    selector: format!("{}={}", budget.selector.key, value),
+                    period: budget.limit.period,
+                    reset_at: period_end,
+                });
+            }
+            // fail closed, the selector value would otherwise be charged to the budget of another
+            Err(shared_data::Error::SlotsExhausted { .. }) => {
+                settle(store, &reservations, 0, 0.0);
+                return Err(Error::NoFreeUsage {
+                    selector: format!("{}={}", budget.selector.key, value),
+                });
+            }
+            Err(e) => warn!("could not charge budget {}: {}", prefix, e),
+        }
+    }
+
+    Ok(reservations)
+}
+
+/// Replaces the tokens reserved on ingress with the tokens the request actually used and adds its
+/// cost. Reservations made in a period that has ended since, or whose usage another selector value
+/// has taken over, are dropped.
+pub fn settle<S: SharedStore + ?Sized>(
+    store: &S,
+    reservations: &[Reservation],
+    tokens: u64,
+    cost_usd: f64,
+) {
+    let micro_usd = (cost_usd * 1_000_000.0).round() as u64;
+    for reservation in reservations {
+        let result = shared_data::update_held(
+            store,
+            &reservation.prefix,
+            USAGE_SLOTS,
+            &reservation.owner,
+            |usage: &mut BudgetUsage| {
+                if usage.period_start == reservation.period_start {
+                    usage.tokens = (usage.tokens + tokens).saturating_sub(reservation.tokens);
+                    usage.micro_usd += micro_usd;
+                }
+            },
+        );
+        if let Err(e) = result {
+            warn!("could not settle budget {}: {}", reservation.prefix, e);
+        }
+    }
+}
+
+// The key prefix of the budget and the selector value of the request, when the budget applies to it.
+fn budget_key<'a>(
+    budget: &Budget,
+    model: &str,
+    headers: &'a HashMap<String, String>,
+) -> Option<(String, &'a str)> {
+    if budget
+        .model
+        .as_deref()
+        .is_some_and(|pattern| !glob_matches(pattern, model))
+    {
+        return None;
+    }
+    let value = headers.get(&budget.selector.key)?;
+    if budget.selector.value.as_ref().is_some_and(|v| v != value) {
+        return None;
+    }
+    debug!(
+        "budget applies to model={}, selector={}={}",
+        model, budget.selector.key, value
+    );
+
+    let period = format!("{:?}", budget.limit.period);
+    let prefix = format!(
+        "arch.budget.{}",
+        shared_data::encode_key_parts(&[
+            budget.model.as_deref().unwrap_or("*"),
+            budget.selector.key.as_str(),
+            period.as_str(),
+        ])
+    );
+    Some((prefix, value.as_str()))
+}
+
+fn is_exhausted(budget: &Budget, usage: &BudgetUsage, tokens: u64) -> bool {
+    let tokens_exhausted = budget
+        .limit
+        .tokens
+        .is_some_and(|limit| usage.tokens + tokens > limit);
+    let usd_exhausted = budget
+        .limit
+        .usd
+        .is_some_and(|limit| usage.micro_usd as f64 >= limit * 1_000_000.0);
+    tokens_exhausted || usd_exhausted
+}
+
+/// Start and end, in seconds since the epoch, of the calendar period that contains `now_secs`.
+pub fn period_bounds(period: BudgetPeriod, now_secs: u64) -> (u64, u64) {
+    let days = now_secs / SECONDS_PER_DAY;
+    match period {
+        BudgetPeriod::Day => (days * SECONDS_PER_DAY, (days + 1) * SECONDS_PER_DAY),
+        BudgetPeriod::Month => {
+            let (year, month, _) = civil_from_days(days as i64);
+            let (next_year, next_month) = if month == 12 {
+                (year + 1, 1)
+            } else {
+                (year, month + 1)
+            };
+            (
+                days_from_civil(year, month, 1) as u64 * SECONDS_PER_DAY,
+                days_from_civil(next_year, next_month, 1) as u64 * SECONDS_PER_DAY,
+            )
+        }
+    }
+}
+
+/// Formats seconds since the epoch as an RFC 3339 UTC timestamp.
+pub fn format_utc(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / SECONDS_PER_DAY) as i64);
+    let secs_of_day = secs % SECONDS_PER_DAY;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+// Conversions between days since the epoch and the proleptic gregorian calendar, see
+// http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_utc, period_bounds, reserve, settle, Error};
+    use crate::configuration::{Budget, BudgetLimit, BudgetPeriod, Header};
+    use crate::shared_data::InMemorySharedStore;
+    use std::collections::HashMap;
+
+    // 2024-02-29T13:00:00Z
+    const LEAP_DAY: u64 = 1_709_211_600;
+
+    fn budget(tokens: Option<u64>, usd: Option<f64>, period: BudgetPeriod) -> Budget {
+        Budget {
+            model: None,
+            selector: Header {
+                key: "x-team".to_string(),
+                value: None,
+            },
+            limit: BudgetLimit {
+                tokens,
+                usd,
+                period,
+            },
+        }
+    }
+
+    fn team(name: &str) -> HashMap<String, String> {
+        HashMap::from([("x-team".to_string(), name.to_string())])
+    }
+
+    #[test]
+    fn calendar_periods() {
+        assert_eq!(format_utc(LEAP_DAY), "2024-02-29T13:00:00Z");
+
+        let (start, end) = period_bounds(BudgetPeriod::Day, LEAP_DAY);
+        assert_eq!(format_utc(start), "2024-02-29T00:00:00Z");
+        assert_eq!(format_utc(end), "2024-03-01T00:00:00Z");
+
+        let (start, end) = period_bounds(BudgetPeriod::Month, LEAP_DAY);
+        assert_eq!(format_utc(start), "2024-02-01T00:00:00Z");
+        assert_eq!(format_utc(end), "2024-03-01T00:00:00Z");
+
+        // 2023-12-31T23:59:59Z rolls over into the next year
+        let (_, end) = period_bounds(BudgetPeriod::Month, 1_704_067_199);
+        assert_eq!(format_utc(end), "2024-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn token_budget_is_enforced_and_settled() {
+        let store = InMemorySharedStore::default();
+        let budgets = vec![budget(Some(1_000), None, BudgetPeriod::Day)];
+
+        let reservations = reserve(&store, &budgets, "gpt-4o", &team("a"), 600, LEAP_DAY).unwrap();
+        // the request used 200 tokens less than what was reserved
+        settle(&store, &reservations, 400, 0.0);
+
+        assert!(reserve(&store, &budgets, "gpt-4o", &team("a"), 600, LEAP_DAY).is_ok());
+        let error = reserve(&store, &budgets, "gpt-4o", &team("a"), 1, LEAP_DAY).unwrap_err();
+        let Error::ExceededBudget { reset_at, .. } = &error else {
+            panic!("expected an exceeded budget, got {:?}", error);
+        };
+        assert_eq!(format_utc(*reset_at), "2024-03-01T00:00:00Z");
+        assert_eq!(error.retry_after(LEAP_DAY), 11 * 3600);
+
+        // other teams have their own budget and the next day starts from zero
+        assert!(reserve(&store, &budgets, "gpt-4o", &team("b"), 1_000, LEAP_DAY).is_ok());
+        assert!(reserve(
+            &store,
+            &budgets,
+            "gpt-4o",
+            &team("a"),
+            1_000,
+            LEAP_DAY + 86_400
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn failed_request_leaves_budget_unchanged() {
+        let store = InMemorySharedStore::default();
+        let budgets = vec![budget(Some(1_000), None, BudgetPeriod::Day)];
+
+        let reservations = reserve(&store, &budgets, "gpt-4o", &team("a"), 100, LEAP_DAY).unwrap();
+        settle(&store, &reservations, 100, 0.0);
+
+        // the request failed before any usage was reported
+        let reservations = reserve(&store, &budgets, "gpt-4o", &team("a"), 600, LEAP_DAY).unwrap();
+        settle(&store, &reservations, 0, 0.0);
+
+        assert!(reserve(&store, &budgets, "gpt-4o", &team("a"), 900, LEAP_DAY).is_ok());
+        assert!(reserve(&store, &budgets, "gpt-4o", &team("a"), 1, LEAP_DAY).is_err());
+    }
+
+    #[test]
+    fn teams_never_share_a_budget() {
+        let store = InMemorySharedStore::default();
+        let budgets = vec![budget(Some(1_000), None, BudgetPeriod::Day)];
+        let reserve_for = |name: &str, tokens: u64, now_secs: u64| {
+            reserve(&store, &budgets, "gpt-4o", &team(name), tokens, now_secs)
+        };
+
+        // teams spend their budget until one finds every usage it may take held by others
+        let mut admitted = Vec::new();
+        let refused = (0..)
+            .map(|i| format!("team-{}", i))
+            .find(|name| match reserve_for(name, 1_000, LEAP_DAY) {
+                Ok(_) => {
+                    admitted.push(name.clone());
+                    false
+                }
+                Err(Error::NoFreeUsage { .. }) => true,
+                Err(e) => panic!("unexpected error {}", e),
+            })
+            .unwrap();
+
+        assert!(matches!(
+            reserve_for(&refused, 1, LEAP_DAY),
+            Err(Error::NoFreeUsage { .. })
+        ));
+        for name in admitted.iter() {
+            assert!(matches!(
+                reserve_for(name, 1, LEAP_DAY),
+                Err(Error::ExceededBudget { .. })
+            ));
+        }
+        // the usages of the day before are handed over the next day, starting from zero
+        assert!(reserve_for(&refused, 1_000, LEAP_DAY + 86_400).is_ok());
+    }
+
+    #[test]
+    fn usd_budget_is_charged_on_settle() {
+        let store = InMemorySharedStore::default();
+        let budgets = vec![budget(None, Some(200.0), BudgetPeriod::Month)];
+
+        let reservations = reserve(&store, &budgets, "gpt-4o", &team("a"), 10, LEAP_DAY).unwrap();
+        settle(&store, &reservations, 10, 199.5);
+        let reservations = reserve(&store, &budgets, "gpt-4o", &team("a"), 10, LEAP_DAY).unwrap();
+        settle(&store, &reservations, 10, 0.5);
+
+        assert!(reserve(&store, &budgets, "gpt-4o", &team("a"), 10, LEAP_DAY).is_err());
+    }
+
+    #[test]
+    fn budgets_apply_only_to_matching_requests() {
+        let store = InMemorySharedStore::default();
+        let mut budgets = vec![budget(Some(10), None, BudgetPeriod::Day)];
+        budgets[0].model = Some("gpt-4o".to_string());
+
+        assert!(reserve(&store, &budgets, "gpt-4o-mini", &team("a"), 100, LEAP_DAY).is_ok());
+        assert!(reserve(&store, &budgets, "gpt-4o", &HashMap::new(), 100, LEAP_DAY).is_ok());
+        assert!(reserve(&store, &budgets, "gpt-4o", &team("a"), 100, LEAP_DAY).is_err());
+
+        // the model is a glob like that of ratelimits
+        budgets[0].model = Some("gpt-4o*".to_string());
+        assert!(reserve(&store, &budgets, "gpt-4o-mini", &team("b"), 100, LEAP_DAY).is_err());
+        assert!(reserve(
+            &store,
+            &budgets,
+            "claude-3-5-sonnet",
+            &team("b"),
+            100,
+            LEAP_DAY
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,330 @@
+use crate::configuration::{CircuitBreaker, LlmProvider};
+use crate::shared_data::{self, SharedStore};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+const DEFAULT_COOLDOWN_SECONDS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Circuit {
+    /// Requests flow to the provider.
+    Closed,
+    /// The provider failed too often, requests are routed elsewhere until the cooldown elapses.
+    Open,
+    /// The cooldown elapsed, a single trial request is let through and its outcome decides whether
+    /// the circuit closes or opens for another cooldown.
+    HalfOpen,
+}
+
+/// Health of a provider as seen by all workers, stored in shared data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitState {
+    pub consecutive_failures: u32,
+    pub opened_at_ms: Option<u64>,
+    /// When the trial request of a half-open circuit was let through.
+    #[serde(default)]
+    pub probe_started_at_ms: Option<u64>,
+}
+
+impl CircuitState {
+    pub fn circuit(&self, config: &CircuitBreaker, now_ms: u64) -> Circuit {
+        match self.opened_at_ms {
+            None => Circuit::Closed,
+            Some(opened_at_ms) if now_ms < opened_at_ms + cooldown_ms(config) => Circuit::Open,
+            Some(_) => Circuit::HalfOpen,
+        }
+    }
+
+    /// Whether a request may be sent to the provider. A half-open circuit admits requests until one
+    /// of them claims the trial, a trial that never reports back is given up after a cooldown.
+    pub fn admits(&self, config: &CircuitBreaker, now_ms: u64) -> bool {
+        match self.circuit(config, now_ms) {
+            Circuit::Closed => true,
+            Circuit::Open => false,
+            Circuit::HalfOpen => self
+                .probe_started_at_ms
+                .is_none_or(|started_at_ms| now_ms >= started_at_ms + cooldown_ms(config)),
+        }
+    }
+
+    /// Admits a request, a request admitted by a half-open circuit becomes its trial.
+    pub fn claim(&mut self, config: &CircuitBreaker, now_ms: u64) -> bool {
+        if !self.admits(config, now_ms) {
+            return false;
+        }
+        if self.circuit(config, now_ms) == Circuit::HalfOpen {
+            self.probe_started_at_ms = Some(now_ms);
+        }
+        true
+    }
+
+    pub fn record_failure(&mut self, config: &CircuitBreaker, now_ms: u64) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        match self.circuit(config, now_ms) {
+            Circuit::Closed => {
+                if self.consecutive_failures >= failure_threshold(config) {
+                    self.opened_at_ms = Some(now_ms);
+                }
+            }
+            // the trial request failed, keep the provider out for another cooldown
+            Circuit::HalfOpen => {
+                self.opened_at_ms = Some(now_ms);
+                self.probe_started_at_ms = None;
+            }
+            Circuit::Open => {}
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        *self = CircuitState::default();
+    }
+}
+
+fn failure_threshold(config: &CircuitBreaker) -> u32 {
+    config
+        .failure_threshold
+        .unwrap_or(DEFAULT_FAILURE_THRESHOLD)
+        .max(1)
+}
+
+fn cooldown_ms(config: &CircuitBreaker) -> u64 {
+    config.cooldown_seconds.unwrap_or(DEFAULT_COOLDOWN_SECONDS) * 1000
+}
+
+fn key(llm_provider: &LlmProvider) -> String {
+    format!("arch.circuit_breaker.{}", llm_provider.name)
+}
+
+/// Returns false while the circuit of the provider is open or its trial request is in flight.
+/// Providers without a circuit breaker are always available.
+pub fn is_available<S: SharedStore + ?Sized>(
+    store: &S,
+    llm_provider: &LlmProvider,
+    now_ms: u64,
+) -> bool {
+    let config = match llm_provider.circuit_breaker.as_ref() {
+        Some(config) => config,
+        None => return true,
+    };
+
+    match shared_data::read::<_, CircuitState>(store, &key(llm_provider)) {
+        Ok(state) => state.unwrap_or_default().admits(config, now_ms),
+        Err(e) => {
+            warn!("could not read circuit state of {}: {}", llm_provider, e);
+            true
+        }
+    }
+}
+
+/// Same as `is_available` for the provider a request is about to be sent to. When the circuit is
+/// half-open the request claims the trial with compare-and-swap, so only one worker sends it and
+/// every other request keeps away from the provider until the trial's outcome is recorded.
+pub fn try_acquire<S: SharedStore + ?Sized>(
+    store: &S,
+    llm_provider: &LlmProvider,
+    now_ms: u64,
+) -> bool {
+    let config = match llm_provider.circuit_breaker.as_ref() {
+        Some(config) => config,
+        None => return true,
+    };
+
+    let key = key(llm_provider);
+    match shared_data::read::<_, CircuitState>(store, &key) {
+        // avoid a write for every request while the provider is healthy
+        Ok(state) if state.unwrap_or_default().circuit(config, now_ms) == Circuit::Closed => {
+            return true
+        }
+        Ok(_) => {}
+        Err(e) => {
+            warn!("could not read circuit state of {}: {}", llm_provider, e);
+            return true;
+        }
+    }
+
+    match shared_data::update(store, &key, |state: &mut CircuitState| {
+        let half_open = state.circuit(config, now_ms) == Circuit::HalfOpen;
+        (state.claim(config, now_ms), half_open)
+    }) {
+        Ok((true, true)) => {
+            info!("sending trial request to llm provider {}", llm_provider);
+            true
+        }
+        Ok((admitted, _)) => admitted,
+        Err(e) => {
+            warn!("could not claim trial request of {}: {}", llm_provider, e);
+            false
+        }
+    }
+}
+
+pub fn record_failure<S: SharedStore + ?Sized>(store: &S, llm_provider: &LlmProvider, now_ms: u64) {
+    let config = match llm_provider.circuit_breaker.as_ref() {
+        Some(config) => config,
+        None => return,
+    };
+
+    let result = shared_data::update(store, &key(llm_provider), |state: &mut CircuitState| {
+        let was_open = state.circuit(config, now_ms) == Circuit::Open;
+        state.record_failure(config, now_ms);
+        !was_open && state.circuit(config, now_ms) == Circuit::Open
+    });
+    match result {
+        Ok(true) => info!(
+            "circuit of llm provider {} opened after {} consecutive failures",
+            llm_provider,
+            failure_threshold(config)
+        ),
+        Ok(false) => {}
+        Err(e) => warn!("could not record failure of {}: {}", llm_provider, e),
+    }
+}
+
+pub fn record_success<S: SharedStore + ?Sized>(store: &S, llm_provider: &LlmProvider) {
+    if llm_provider.circuit_breaker.is_none() {
+        return;
+    }
+
+    // avoid a write for every successful request while the provider is healthy
+    let key = key(llm_provider);
+    if let Ok(None)
+    | Ok(Some(CircuitState {
+        consecutive_failures: 0,
+        opened_at_ms: None,
+        ..
+    })) = shared_data::read::<_, CircuitState>(store, &key)
+    {
+        return;
+    }
+
+    if let Err(e) = shared_data::update(store, &key, |state: &mut CircuitState| {
+        state.record_success()
+    }) {
+        warn!("could not record success of {}: {}", llm_provider, e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_available, record_failure, record_success, try_acquire, Circuit, CircuitState};
+    use crate::configuration::{CircuitBreaker, LlmProvider};
+    use crate::shared_data::InMemorySharedStore;
+
+    fn config() -> CircuitBreaker {
+        CircuitBreaker {
+            failure_threshold: Some(3),
+            cooldown_seconds: Some(10),
+        }
+    }
+
+    #[test]
+    fn opens_after_threshold_and_half_opens_after_cooldown() {
+        let config = config();
+        let mut state = CircuitState::default();
+
+        state.record_failure(&config, 1_000);
+        state.record_failure(&config, 2_000);
+        assert_eq!(state.circuit(&config, 2_000), Circuit::Closed);
+
+        state.record_failure(&config, 3_000);
+        assert_eq!(state.circuit(&config, 3_000), Circuit::Open);
+        assert_eq!(state.circuit(&config, 12_999), Circuit::Open);
+        assert_eq!(state.circuit(&config, 13_000), Circuit::HalfOpen);
+
+        // a failed trial opens the circuit for another cooldown
+        state.record_failure(&config, 14_000);
+        assert_eq!(state.circuit(&config, 14_000), Circuit::Open);
+        assert_eq!(state.circuit(&config, 24_000), Circuit::HalfOpen);
+
+        state.record_success();
+        assert_eq!(state.circuit(&config, 24_000), Circuit::Closed);
+        assert_eq!(state.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn success_resets_consecutive_failures() {
+        let config = config();
+        let mut state = CircuitState::default();
+
+        state.record_failure(&config, 1_000);
+        state.record_failure(&config, 2_000);
+        state.record_success();
+        state.record_failure(&config, 3_000);
+        assert_eq!(state.circuit(&config, 3_000), Circuit::Closed);
+    }
+
+    #[test]
+    fn availability_is_shared_through_the_store() {
+        let store = InMemorySharedStore::default();
+        let llm_provider = LlmProvider {
+            name: "openai".to_string(),
+            circuit_breaker: Some(config()),
+            ..Default::default()
+        };
+
+        for now_ms in [1_000, 2_000, 3_000] {
+            assert!(is_available(&store, &llm_provider, now_ms));
+            record_failure(&store, &llm_provider, now_ms);
+        }
+        assert!(!is_available(&store, &llm_provider, 4_000));
+        assert!(is_available(&store, &llm_provider, 13_000));
+
+        record_success(&store, &llm_provider);
+        assert!(is_available(&store, &llm_provider, 13_000));
+    }
+
+    #[test]
+    fn half_open_circuit_lets_one_trial_through() {
+        let store = InMemorySharedStore::default();
+        let llm_provider = LlmProvider {
+            name: "openai".to_string(),
+            circuit_breaker: Some(config()),
+            ..Default::default()
+        };
+        for now_ms in [1_000, 2_000, 3_000] {
+            record_failure(&store, &llm_provider, now_ms);
+        }
+
+        // concurrent requests once the cooldown elapsed, only the first is sent to the provider
+        assert!(try_acquire(&store, &llm_provider, 13_000));
+        assert!(!try_acquire(&store, &llm_provider, 13_001));
+        assert!(!is_available(&store, &llm_provider, 13_002));
+
+        // the trial failed, the provider is out for another cooldown
+        record_failure(&store, &llm_provider, 14_000);
+        assert!(!try_acquire(&store, &llm_provider, 23_999));
+        assert!(try_acquire(&store, &llm_provider, 24_000));
+        assert!(!try_acquire(&store, &llm_provider, 24_000));
+
+        // the trial succeeded, the circuit closes for everyone
+        record_success(&store, &llm_provider);
+        assert!(try_acquire(&store, &llm_provider, 25_000));
+        assert!(try_acquire(&store, &llm_provider, 25_000));
+    }
+
+    #[test]
+    fn trial_that_never_reports_back_is_given_up() {
+        let config = config();
+        let mut state = CircuitState {
+            consecutive_failures: 3,
+            opened_at_ms: Some(3_000),
+            probe_started_at_ms: None,
+        };
+
+        assert!(state.claim(&config, 13_000));
+        assert!(!state.admits(&config, 22_999));
+        assert!(state.claim(&config, 23_000));
+    }
+
+    #[test]
+    fn provider_without_circuit_breaker_is_always_available() {
+        let store = InMemorySharedStore::default();
+        let llm_provider = LlmProvider::default();
+
+        for now_ms in 0..10 {
+            record_failure(&store, &llm_provider, now_ms);
+        }
+        assert!(is_available(&store, &llm_provider, 10));
+    }
+}
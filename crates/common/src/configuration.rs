@@ -1,4 +1,6 @@
+use hermesllm::clients::AwsCredentials;
 use hermesllm::providers::openai::types::{ModelDetail, ModelObject, Models};
+use hermesllm::Provider;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Display;
@@ -11,6 +13,16 @@ use crate::api::open_ai::{
 pub struct Routing {
     pub llm_provider: Option<String>,
     pub model: Option<String>,
+    pub load_balancing: Option<LoadBalancing>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum LoadBalancing {
+    #[default]
+    #[serde(rename = "weighted_random")]
+    WeightedRandom,
+    #[serde(rename = "round_robin")]
+    RoundRobin,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +36,7 @@ pub struct Configuration {
     pub prompt_targets: Option<Vec<PromptTarget>>,
     pub error_target: Option<ErrorTargetDetail>,
     pub ratelimits: Option<Vec<Ratelimit>>,
+    pub budgets: Option<Vec<Budget>>,
     pub tracing: Option<Tracing>,
     pub mode: Option<GatewayMode>,
     pub routing: Option<Routing>,
@@ -39,9 +52,22 @@ pub struct Overrides {
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Tracing {
     pub sampling_rate: Option<f64>,
+    /// Percentage of requests envoy samples, used when `sampling_rate` is not set
+    pub random_sampling: Option<u32>,
     pub trace_arch_internal: Option<bool>,
 }
 
+impl Tracing {
+    /// The fraction of the traces started by the gateways that are sampled
+    pub fn head_sampling_rate(&self) -> f64 {
+        self.sampling_rate
+            .or(self
+                .random_sampling
+                .map(|random_sampling| random_sampling as f64 / 100.0))
+            .unwrap_or(1.0)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
 pub enum GatewayMode {
     #[serde(rename = "llm")]
@@ -109,11 +135,19 @@ pub struct Header {
     pub value: Option<String>,
 }
 
+/// Token limits of the requests matching every given dimension: the `model` and `path` globs (`*`
+/// matches any sequence), the header named by `x-arch-ratelimit-selector` and a claim of the bearer
+/// JWT. `input_limit` and `output_limit` apply to the prompt and completion tokens respectively,
+/// `limit` to their sum.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ratelimit {
     pub model: String,
-    pub selector: Header,
-    pub limit: Limit,
+    pub selector: Option<Header>,
+    pub path: Option<String>,
+    pub jwt_claim: Option<Header>,
+    pub limit: Option<Limit>,
+    pub input_limit: Option<Limit>,
+    pub output_limit: Option<Limit>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -132,12 +166,104 @@ pub enum TimeUnit {
     Hour,
 }
 
+/// Hard usage budget that resets on calendar boundaries (UTC), as opposed to the continuously
+/// refilling `Ratelimit`. `model` is a glob like that of `Ratelimit`, a missing one applies the
+/// budget across all models.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Budget {
+    pub model: Option<String>,
+    pub selector: Header,
+    pub limit: BudgetLimit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetLimit {
+    pub tokens: Option<u64>,
+    pub usd: Option<f64>,
+    pub period: BudgetPeriod,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum BudgetPeriod {
+    #[serde(rename = "day")]
+    Day,
+    #[serde(rename = "month")]
+    Month,
+}
+
+impl Display for BudgetPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetPeriod::Day => write!(f, "daily"),
+            BudgetPeriod::Month => write!(f, "monthly"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct RatelimitHeader {
     pub name: String,
     pub value: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreaker {
+    pub failure_threshold: Option<u32>,
+    pub cooldown_seconds: Option<u64>,
+}
+
+/// Prices in USD per million tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pricing {
+    pub input: f64,
+    pub output: f64,
+    pub cached_input: Option<f64>,
+}
+
+impl Pricing {
+    /// Cost of a request in USD, `cached_prompt_tokens` are the part of `prompt_tokens` that was
+    /// served from the provider's prompt cache.
+    pub fn cost_usd(
+        &self,
+        prompt_tokens: usize,
+        cached_prompt_tokens: usize,
+        completion_tokens: usize,
+    ) -> f64 {
+        let cached_prompt_tokens = cached_prompt_tokens.min(prompt_tokens);
+        let uncached_prompt_tokens = prompt_tokens - cached_prompt_tokens;
+        let cached_input = self.cached_input.unwrap_or(self.input);
+
+        (uncached_prompt_tokens as f64 * self.input
+            + cached_prompt_tokens as f64 * cached_input
+            + completion_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
+
+/// Region and credentials that requests to AWS are signed with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwsConfig {
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsConfig {
+    pub fn credentials(&self) -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: self.access_key_id.clone(),
+            secret_access_key: self.secret_access_key.clone(),
+            session_token: self.session_token.clone(),
+        }
+    }
+
+    /// Host of the bedrock runtime API in the region.
+    pub fn bedrock_host(&self) -> String {
+        format!("bedrock-runtime.{}.amazonaws.com", self.region)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 //TODO: use enum for model, but if there is a new model, we need to update the code
 pub struct EmbeddingProviver {
@@ -161,6 +287,12 @@ pub enum LlmProviderType {
     OpenAI,
     #[serde(rename = "gemini")]
     Gemini,
+    #[serde(rename = "bedrock")]
+    Bedrock,
+    #[serde(rename = "azure_openai")]
+    AzureOpenAI,
+    #[serde(rename = "openai_compatible")]
+    OpenAICompatible,
 }
 
 impl Display for LlmProviderType {
@@ -173,6 +305,26 @@ impl Display for LlmProviderType {
             LlmProviderType::Gemini => write!(f, "gemini"),
             LlmProviderType::Mistral => write!(f, "mistral"),
             LlmProviderType::OpenAI => write!(f, "openai"),
+            LlmProviderType::Bedrock => write!(f, "bedrock"),
+            LlmProviderType::AzureOpenAI => write!(f, "azure_openai"),
+            LlmProviderType::OpenAICompatible => write!(f, "openai_compatible"),
+        }
+    }
+}
+
+impl From<&LlmProviderType> for Provider {
+    fn from(value: &LlmProviderType) -> Self {
+        match value {
+            LlmProviderType::Arch => Provider::Arch,
+            LlmProviderType::Claude => Provider::Claude,
+            LlmProviderType::Deepseek => Provider::Deepseek,
+            LlmProviderType::Groq => Provider::Groq,
+            LlmProviderType::Mistral => Provider::Mistral,
+            LlmProviderType::OpenAI => Provider::OpenAI,
+            LlmProviderType::Gemini => Provider::Gemini,
+            LlmProviderType::Bedrock => Provider::Bedrock,
+            LlmProviderType::AzureOpenAI => Provider::AzureOpenAI,
+            LlmProviderType::OpenAICompatible => Provider::OpenAICompatible,
         }
     }
 }
@@ -203,6 +355,28 @@ pub struct LlmProvider {
     pub rate_limits: Option<LlmRatelimit>,
     pub usage: Option<String>,
     pub routing_preferences: Option<Vec<RoutingPreference>>,
+    /// Names of the providers to retry against, in order, when this provider responds with 429 or 5xx.
+    /// Only chat completions fail over, embeddings requests get the failed response. A streamed
+    /// request fails over without streaming, the fallback's events reach the client in one block.
+    pub fallbacks: Option<Vec<String>>,
+    /// Share of traffic this provider receives among the providers configured with the same model.
+    pub weight: Option<u32>,
+    pub circuit_breaker: Option<CircuitBreaker>,
+    pub pricing: Option<Pricing>,
+    /// Signs requests to bedrock with AWS credentials rather than sending the access key.
+    pub aws: Option<AwsConfig>,
+    /// Azure OpenAI deployment that serves the model, defaults to the model name.
+    pub deployment: Option<String>,
+    /// Azure OpenAI api-version the requests are sent with.
+    pub api_version: Option<String>,
+    /// Path prefix the provider serves the openai api under, in place of `/v1`.
+    pub base_path: Option<String>,
+    /// Header the access key is sent in, defaults to `Authorization`.
+    pub auth_header: Option<String>,
+    /// Scheme the access key is prefixed with, defaults to `Bearer` in the `Authorization` header.
+    pub auth_prefix: Option<String>,
+    /// Static headers added to every request sent to the provider.
+    pub headers: Option<HashMap<String, String>>,
 }
 
 pub trait IntoModels {
@@ -242,6 +416,42 @@ impl Default for LlmProvider {
             rate_limits: None,
             usage: None,
             routing_preferences: None,
+            fallbacks: None,
+            weight: None,
+            circuit_breaker: None,
+            pricing: None,
+            aws: None,
+            deployment: None,
+            api_version: None,
+            base_path: None,
+            auth_header: None,
+            auth_prefix: None,
+            headers: None,
+        }
+    }
+}
+
+impl LlmProvider {
+    /// Name of the envoy cluster that serves this provider.
+    pub fn cluster_name(&self) -> String {
+        if self.endpoint.is_some() {
+            self.name.clone()
+        } else {
+            self.provider_interface.to_string()
+        }
+    }
+
+    /// Azure OpenAI deployment requests are sent to, deployments are commonly named after their model.
+    pub fn azure_deployment(&self) -> Option<&str> {
+        self.deployment.as_deref().or(self.model.as_deref())
+    }
+
+    /// Path prefix openai compatible requests are sent under in place of `/v1`, groq serves the
+    /// openai api under `/openai/v1`.
+    pub fn openai_base_path(&self) -> Option<&str> {
+        match self.provider_interface {
+            LlmProviderType::Groq => Some(self.base_path.as_deref().unwrap_or("/openai/v1")),
+            _ => self.base_path.as_deref(),
         }
     }
 }
@@ -407,6 +617,7 @@ mod test {
 
         let tracing = config.tracing.as_ref().unwrap();
         assert_eq!(tracing.sampling_rate.unwrap(), 0.1);
+        assert_eq!(tracing.head_sampling_rate(), 0.1);
 
         let mode = config.mode.as_ref().unwrap_or(&super::GatewayMode::Prompt);
         assert_eq!(*mode, super::GatewayMode::Prompt);
@@ -483,4 +694,98 @@ mod test {
             crate::api::open_ai::ParameterType::Bool
         );
     }
+
+    #[test]
+    fn test_pricing_cost() {
+        let pricing = super::Pricing {
+            input: 2.5,
+            output: 10.0,
+            cached_input: Some(1.25),
+        };
+        // 600k uncached + 400k cached prompt tokens, 100k completion tokens
+        let cost = pricing.cost_usd(1_000_000, 400_000, 100_000);
+        assert!((cost - (1.5 + 0.5 + 1.0)).abs() < 1e-9);
+
+        let pricing = super::Pricing {
+            cached_input: None,
+            ..pricing
+        };
+        let cost = pricing.cost_usd(1_000_000, 400_000, 0);
+        assert!((cost - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_azure_deployment() {
+        let llm_provider: super::LlmProvider = serde_yaml::from_str(
+            r#"
+name: azure-gpt-4o
+provider_interface: azure_openai
+model: gpt-4o
+api_version: 2024-10-21
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            llm_provider.provider_interface,
+            super::LlmProviderType::AzureOpenAI
+        );
+        assert_eq!(llm_provider.azure_deployment(), Some("gpt-4o"));
+
+        let llm_provider = super::LlmProvider {
+            deployment: Some("prod-gpt-4o".to_string()),
+            ..llm_provider
+        };
+        assert_eq!(llm_provider.azure_deployment(), Some("prod-gpt-4o"));
+    }
+
+    #[test]
+    fn test_openai_compatible_provider() {
+        let llm_provider: super::LlmProvider = serde_yaml::from_str(
+            r#"
+name: fireworks
+provider_interface: openai_compatible
+endpoint: api.fireworks.ai
+base_path: /inference/v1
+auth_header: x-api-key
+auth_prefix: ""
+headers:
+  x-team: search
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            llm_provider.provider_interface,
+            super::LlmProviderType::OpenAICompatible
+        );
+        assert_eq!(llm_provider.openai_base_path(), Some("/inference/v1"));
+        assert_eq!(llm_provider.auth_header.as_deref(), Some("x-api-key"));
+        assert_eq!(llm_provider.auth_prefix.as_deref(), Some(""));
+        assert_eq!(
+            llm_provider
+                .headers
+                .unwrap()
+                .get("x-team")
+                .map(String::as_str),
+            Some("search")
+        );
+
+        let groq = super::LlmProvider {
+            provider_interface: super::LlmProviderType::Groq,
+            ..Default::default()
+        };
+        assert_eq!(groq.openai_base_path(), Some("/openai/v1"));
+        assert_eq!(super::LlmProvider::default().openai_base_path(), None);
+    }
+
+    #[test]
+    fn test_tracing_head_sampling_rate() {
+        let tracing: super::Tracing = serde_yaml::from_str("random_sampling: 25").unwrap();
+        assert_eq!(tracing.head_sampling_rate(), 0.25);
+
+        let tracing: super::Tracing =
+            serde_yaml::from_str("random_sampling: 25\nsampling_rate: 0.5").unwrap();
+        assert_eq!(tracing.head_sampling_rate(), 0.5);
+
+        assert_eq!(super::Tracing::default().head_sampling_rate(), 1.0);
+    }
 }
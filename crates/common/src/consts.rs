@@ -7,11 +7,14 @@ pub const ARCH_FC_REQUEST_TIMEOUT_MS: u64 = 30000; // 30 seconds
 pub const DEFAULT_TARGET_REQUEST_TIMEOUT_MS: u64 = 30000; // 30 seconds
 pub const API_REQUEST_TIMEOUT_MS: u64 = 30000; // 30 seconds
 pub const MODEL_SERVER_REQUEST_TIMEOUT_MS: u64 = 30000; // 30 seconds
+pub const LLM_FAILOVER_REQUEST_TIMEOUT_MS: u64 = 60000; // 60 seconds
 pub const MODEL_SERVER_NAME: &str = "model_server";
 pub const ARCH_ROUTING_HEADER: &str = "x-arch-llm-provider";
 pub const MESSAGES_KEY: &str = "messages";
 pub const ARCH_PROVIDER_HINT_HEADER: &str = "x-arch-llm-provider-hint";
 pub const CHAT_COMPLETIONS_PATH: &str = "/v1/chat/completions";
+pub const EMBEDDINGS_PATH: &str = "/v1/embeddings";
+pub const RESPONSES_PATH: &str = "/v1/responses";
 pub const HEALTHZ_PATH: &str = "/healthz";
 pub const X_ARCH_STATE_HEADER: &str = "x-arch-state";
 pub const X_ARCH_API_RESPONSE: &str = "x-arch-api-response-message";
@@ -28,3 +31,11 @@ pub const HALLUCINATION_TEMPLATE: &str =
 pub const OTEL_COLLECTOR_HTTP: &str = "opentelemetry_collector_http";
 pub const OTEL_POST_PATH: &str = "/v1/traces";
 pub const LLM_ROUTE_HEADER: &str = "x-arch-llm-route";
+pub const ARCH_COST_HEADER: &str = "x-arch-cost-usd";
+pub const ANTHROPIC_MESSAGES_PATH: &str = "/v1/messages";
+pub const ANTHROPIC_API_KEY_HEADER: &str = "x-api-key";
+pub const ANTHROPIC_VERSION_HEADER: &str = "anthropic-version";
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+pub const GEMINI_API_KEY_HEADER: &str = "x-goog-api-key";
+pub const AZURE_API_KEY_HEADER: &str = "api-key";
+pub const AZURE_OPENAI_API_VERSION: &str = "2024-10-21";
@@ -1,6 +1,7 @@
 use proxy_wasm::types::Status;
 
-use crate::{api::open_ai::ChatCompletionChunkResponseError, ratelimit};
+use crate::{api::open_ai::ChatCompletionChunkResponseError, budget, ratelimit};
+use hermesllm::clients::TransformError;
 use hermesllm::providers::openai::types::OpenAIError;
 
 #[derive(thiserror::Error, Debug)]
@@ -36,10 +37,42 @@ pub enum ServerError {
     NoMessagesFound { why: String },
     #[error(transparent)]
     ExceededRatelimit(ratelimit::Error),
+    #[error(transparent)]
+    ExceededBudget(budget::Error),
     #[error("{why}")]
     BadRequest { why: String },
+    #[error("{why}")]
+    MissingAccessKey { why: String },
     #[error("error in streaming response")]
     Streaming(#[from] ChatCompletionChunkResponseError),
     #[error("error parsing openai message: {0}")]
     OpenAIPError(#[from] OpenAIError),
+    #[error("error parsing upstream response: {0}")]
+    UpstreamResponse(OpenAIError),
+    #[error("error translating upstream response: {0}")]
+    ResponseTranslation(TransformError),
+}
+
+impl ServerError {
+    /// The kind of error, as a metric tag value.
+    pub fn class(&self) -> &'static str {
+        match self {
+            ServerError::HttpDispatch(_) => "http_dispatch",
+            ServerError::Deserialization(_) | ServerError::Serialization(_) => "serialization",
+            ServerError::LogicError(_) => "internal",
+            ServerError::Upstream { .. } => "upstream",
+            ServerError::Jailbreak(_) => "jailbreak",
+            ServerError::NoMessagesFound { .. }
+            | ServerError::BadRequest { .. }
+            | ServerError::OpenAIPError(_) => "bad_request",
+            ServerError::ExceededRatelimit(ratelimit::Error::NoFreeBucket { .. })
+            | ServerError::ExceededBudget(budget::Error::NoFreeUsage { .. }) => "slots_exhausted",
+            ServerError::ExceededRatelimit(_) => "ratelimited",
+            ServerError::ExceededBudget(_) => "budget_exceeded",
+            ServerError::MissingAccessKey { .. } => "missing_access_key",
+            ServerError::Streaming(_)
+            | ServerError::UpstreamResponse(_)
+            | ServerError::ResponseTranslation(_) => "response_parse",
+        }
+    }
 }
@@ -1,4 +1,6 @@
 pub mod api;
+pub mod budget;
+pub mod circuit_breaker;
 pub mod configuration;
 pub mod consts;
 pub mod errors;
@@ -8,7 +10,9 @@ pub mod path;
 pub mod pii;
 pub mod ratelimit;
 pub mod routing;
+pub mod shared_data;
 pub mod stats;
 pub mod tokenizer;
+pub mod trace_export;
 pub mod tracing;
 pub mod utils;
@@ -1,11 +1,66 @@
-use crate::configuration::LlmProvider;
+use crate::configuration::{LlmProvider, LoadBalancing};
+use crate::routing;
+use rand::thread_rng;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
 #[derive(Debug)]
 pub struct LlmProviders {
     providers: HashMap<String, Rc<LlmProvider>>,
+    model_groups: HashMap<String, ModelGroup>,
     default: Option<Rc<LlmProvider>>,
+    load_balancing: LoadBalancing,
+}
+
+// Providers that are configured with the same model, traffic for the model is spread across them by weight.
+#[derive(Debug)]
+struct ModelGroup {
+    providers: Vec<Rc<LlmProvider>>,
+    current_weights: RefCell<Vec<i64>>,
+}
+
+impl ModelGroup {
+    fn new(providers: Vec<Rc<LlmProvider>>) -> Self {
+        ModelGroup {
+            providers,
+            current_weights: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn select(
+        &self,
+        load_balancing: LoadBalancing,
+        is_available: impl Fn(&LlmProvider) -> bool,
+    ) -> Rc<LlmProvider> {
+        let configured_weights = self
+            .providers
+            .iter()
+            .map(|provider| provider.weight.unwrap_or(1));
+        // unavailable providers get no traffic unless none of the providers is available
+        let available_weights: Vec<u32> = configured_weights
+            .clone()
+            .zip(self.providers.iter())
+            .map(|(weight, provider)| if is_available(provider) { weight } else { 0 })
+            .collect();
+        let weights: Vec<u32> = if available_weights.iter().any(|weight| *weight > 0) {
+            available_weights
+        } else {
+            configured_weights.collect()
+        };
+
+        let index = match load_balancing {
+            LoadBalancing::WeightedRandom => {
+                routing::weighted_random_index(&weights, &mut thread_rng())
+            }
+            LoadBalancing::RoundRobin => routing::weighted_round_robin_index(
+                &weights,
+                &mut self.current_weights.borrow_mut(),
+            ),
+        };
+
+        Rc::clone(&self.providers[index])
+    }
 }
 
 impl LlmProviders {
@@ -17,8 +72,43 @@ impl LlmProviders {
         self.default.as_ref().map(|rc| rc.clone())
     }
 
+    /// Looks up a provider by name or model. When several providers share the model, one of them is
+    /// picked according to the load balancing policy.
     pub fn get(&self, name: &str) -> Option<Rc<LlmProvider>> {
-        self.providers.get(name).cloned()
+        self.get_available(name, |_| true)
+    }
+
+    /// Same as `get`, but prefers the providers of a shared model that `is_available` accepts.
+    pub fn get_available(
+        &self,
+        name: &str,
+        is_available: impl Fn(&LlmProvider) -> bool,
+    ) -> Option<Rc<LlmProvider>> {
+        if let Some(provider) = self.providers.get(name) {
+            return Some(Rc::clone(provider));
+        }
+        self.model_groups
+            .get(name)
+            .map(|group| group.select(self.load_balancing, is_available))
+    }
+
+    pub fn with_load_balancing(mut self, load_balancing: LoadBalancing) -> Self {
+        self.load_balancing = load_balancing;
+        self
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.providers.contains_key(name) || self.model_groups.contains_key(name)
+    }
+
+    /// Returns the providers to fail over to, in configured order, when `llm_provider` fails.
+    pub fn fallbacks(&self, llm_provider: &LlmProvider) -> Vec<Rc<LlmProvider>> {
+        llm_provider
+            .fallbacks
+            .iter()
+            .flatten()
+            .filter_map(|name| self.get(name))
+            .collect()
     }
 }
 
@@ -30,6 +120,12 @@ pub enum LlmProvidersNewError {
     MoreThanOneDefault,
     #[error("\'{0}\' is not a unique name")]
     DuplicateName(String),
+    #[error("\'{0}\' must set a model")]
+    MissingModel(String),
+    #[error("\'{1}\' is listed as a fallback of \'{0}\' but is not a known LLM Provider")]
+    UnknownFallback(String, String),
+    #[error("model \'{0}\' is shared by more than one LLM Provider, \'{1}\' must set a weight")]
+    MissingWeight(String, String),
 }
 
 impl TryFrom<Vec<LlmProvider>> for LlmProviders {
@@ -42,8 +138,11 @@ impl TryFrom<Vec<LlmProvider>> for LlmProviders {
 
         let mut llm_providers = LlmProviders {
             providers: HashMap::new(),
+            model_groups: HashMap::new(),
             default: None,
+            load_balancing: LoadBalancing::default(),
         };
+        let mut providers_by_model: HashMap<String, Vec<Rc<LlmProvider>>> = HashMap::new();
 
         for llm_provider in llm_providers_config {
             let llm_provider: Rc<LlmProvider> = Rc::new(llm_provider);
@@ -64,15 +163,186 @@ impl TryFrom<Vec<LlmProvider>> for LlmProviders {
                 return Err(LlmProvidersNewError::DuplicateName(name));
             }
 
-            // also add model_id as key for provider lookup
-            if llm_providers
-                .providers
-                .insert(llm_provider.model.clone().unwrap(), llm_provider)
-                .is_some()
-            {
-                return Err(LlmProvidersNewError::DuplicateName(name));
+            let Some(model) = llm_provider.model.clone() else {
+                return Err(LlmProvidersNewError::MissingModel(name));
+            };
+            providers_by_model
+                .entry(model)
+                .or_default()
+                .push(llm_provider);
+        }
+
+        for (model, mut providers) in providers_by_model {
+            if providers.len() == 1 {
+                // also add model_id as key for provider lookup
+                let llm_provider = providers.pop().unwrap();
+                let name = llm_provider.name.clone();
+                if llm_providers
+                    .providers
+                    .insert(model, llm_provider)
+                    .is_some()
+                {
+                    return Err(LlmProvidersNewError::DuplicateName(name));
+                }
+                continue;
+            }
+
+            if let Some(llm_provider) = providers.iter().find(|p| p.weight.is_none()) {
+                return Err(LlmProvidersNewError::MissingWeight(
+                    model,
+                    llm_provider.name.clone(),
+                ));
+            }
+            if llm_providers.providers.contains_key(&model) {
+                return Err(LlmProvidersNewError::DuplicateName(model));
+            }
+            llm_providers
+                .model_groups
+                .insert(model, ModelGroup::new(providers));
+        }
+
+        for llm_provider in llm_providers.providers.values() {
+            for fallback in llm_provider.fallbacks.iter().flatten() {
+                if !llm_providers.contains(fallback) {
+                    return Err(LlmProvidersNewError::UnknownFallback(
+                        llm_provider.name.clone(),
+                        fallback.clone(),
+                    ));
+                }
             }
         }
+
         Ok(llm_providers)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{LlmProviders, LlmProvidersNewError};
+    use crate::configuration::{LlmProvider, LlmProviderType, LoadBalancing};
+
+    fn provider(name: &str, model: &str, fallbacks: Option<Vec<&str>>) -> LlmProvider {
+        LlmProvider {
+            name: name.to_string(),
+            provider_interface: LlmProviderType::OpenAI,
+            model: Some(model.to_string()),
+            default: None,
+            fallbacks: fallbacks.map(|f| f.into_iter().map(String::from).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fallbacks_in_configured_order() {
+        let llm_providers = LlmProviders::try_from(vec![
+            provider(
+                "openai/gpt-4o",
+                "gpt-4o",
+                Some(vec!["mistral", "gpt-4o-mini"]),
+            ),
+            provider("mistral", "ministral-3b", None),
+            provider("openai/gpt-4o-mini", "gpt-4o-mini", None),
+        ])
+        .unwrap();
+
+        let primary = llm_providers.get("openai/gpt-4o").unwrap();
+        let fallbacks: Vec<String> = llm_providers
+            .fallbacks(&primary)
+            .iter()
+            .map(|p| p.name.clone())
+            .collect();
+        assert_eq!(fallbacks, vec!["mistral", "openai/gpt-4o-mini"]);
+
+        let mistral = llm_providers.get("mistral").unwrap();
+        assert!(llm_providers.fallbacks(&mistral).is_empty());
+    }
+
+    fn weighted(name: &str, model: &str, weight: Option<u32>) -> LlmProvider {
+        LlmProvider {
+            weight,
+            ..provider(name, model, None)
+        }
+    }
+
+    #[test]
+    fn providers_sharing_a_model_are_balanced_by_weight() {
+        let llm_providers = LlmProviders::try_from(vec![
+            weighted("openai-account-a", "gpt-4o", Some(3)),
+            weighted("openai-account-b", "gpt-4o", Some(1)),
+        ])
+        .unwrap()
+        .with_load_balancing(LoadBalancing::RoundRobin);
+
+        let picks: Vec<String> = (0..8)
+            .map(|_| llm_providers.get("gpt-4o").unwrap().name.clone())
+            .collect();
+        assert_eq!(
+            picks
+                .iter()
+                .filter(|name| name.as_str() == "openai-account-a")
+                .count(),
+            6
+        );
+        assert_eq!(
+            picks
+                .iter()
+                .filter(|name| name.as_str() == "openai-account-b")
+                .count(),
+            2
+        );
+
+        // members are still addressable by their own name
+        assert_eq!(
+            llm_providers.get("openai-account-b").unwrap().name,
+            "openai-account-b"
+        );
+    }
+
+    #[test]
+    fn shared_model_requires_weights() {
+        let result = LlmProviders::try_from(vec![
+            weighted("openai-account-a", "gpt-4o", Some(3)),
+            weighted("openai-account-b", "gpt-4o", None),
+        ]);
+
+        match result {
+            Err(LlmProvidersNewError::MissingWeight(model, provider)) => {
+                assert_eq!(model, "gpt-4o");
+                assert_eq!(provider, "openai-account-b");
+            }
+            other => panic!("expected MissingWeight, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_fallback_is_rejected() {
+        let result = LlmProviders::try_from(vec![provider(
+            "openai/gpt-4o",
+            "gpt-4o",
+            Some(vec!["does-not-exist"]),
+        )]);
+
+        match result {
+            Err(LlmProvidersNewError::UnknownFallback(provider, fallback)) => {
+                assert_eq!(provider, "openai/gpt-4o");
+                assert_eq!(fallback, "does-not-exist");
+            }
+            other => panic!("expected UnknownFallback, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fallback_without_model_is_rejected() {
+        let mut fallback = provider("mistral", "ministral-3b", None);
+        fallback.model = None;
+        let result = LlmProviders::try_from(vec![
+            provider("openai/gpt-4o", "gpt-4o", Some(vec!["mistral"])),
+            fallback,
+        ]);
+
+        match result {
+            Err(LlmProvidersNewError::MissingModel(provider)) => assert_eq!(provider, "mistral"),
+            other => panic!("expected MissingModel, got {:?}", other),
+        }
+    }
+}
@@ -1,11 +1,16 @@
 use crate::configuration;
+use crate::shared_data::{self, SharedStore};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use configuration::{Limit, Ratelimit, TimeUnit};
-use governor::{DefaultKeyedRateLimiter, InsufficientCapacity, Quota};
-use log::debug;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::Display;
-use std::num::{NonZero, NonZeroU32};
+use std::num::NonZeroU32;
+use std::sync::OnceLock;
 use std::sync::RwLock;
-use std::{collections::HashMap, sync::OnceLock};
+use std::time::Duration;
 
 pub type RatelimitData = RwLock<RatelimitMap>;
 
@@ -18,16 +23,56 @@ pub fn ratelimits(ratelimits_config: Option<Vec<Ratelimit>>) -> &'static Ratelim
     })
 }
 
-// The Data Structure is laid out in the following way:
-// Provider -> Hash { Header -> Limit }.
-// If the Header used to configure the given Limit:
-//   a) Has None value, then there will be N Limit keyed by the Header value.
-//   b) Has Some() value, then there will be 1 Limit keyed by the empty string.
-// It would have been nicer to use a non-keyed limit for b). However, the type system made that option a nightmare.
+// The rules are kept in configuration order, a request is limited by the most specific rule that
+// matches it, see `Rule::specificity`.
+// If a dimension of a rule (the selector header or the jwt claim):
+//   a) Has None value, then every value of that dimension has its own bucket.
+//   b) Has Some() value, then there is a single bucket for that value.
+// The buckets themselves live in proxy-wasm shared data so that every worker thread draws from the same
+// bucket, see `check_limit`.
 pub struct RatelimitMap {
-    datastore: HashMap<String, HashMap<configuration::Header, DefaultKeyedRateLimiter<String>>>,
+    rules: Vec<Rule>,
 }
 
+#[derive(Debug)]
+struct Rule {
+    model: String,
+    selector: Option<configuration::Header>,
+    path: Option<String>,
+    jwt_claim: Option<configuration::Header>,
+    limits: Limits,
+}
+
+// Input tokens are charged on ingress, output tokens once the response ended. The total limit is
+// charged with both.
+#[derive(Debug)]
+struct Limits {
+    input: Option<Limit>,
+    output: Option<Limit>,
+    total: Option<Limit>,
+}
+
+// Theoretical arrival time of the next token, in nanoseconds, of every limit of a selector. They are
+// kept under a single key so that they are updated together.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+struct Buckets {
+    input: u64,
+    output: u64,
+    total: u64,
+}
+
+impl Buckets {
+    // Full buckets hold no state, they can make room for the buckets of another selector value.
+    fn is_full(&self, now_ns: u64) -> bool {
+        [self.input, self.output, self.total]
+            .iter()
+            .all(|theoretical_arrival_ns| *theoretical_arrival_ns <= now_ns)
+    }
+}
+
+// Number of keys the buckets of a rule are spread over, whatever the number of selector values.
+const BUCKET_SLOTS: u64 = 1024;
+
 // This version of Header demands that the user passes a header value to match on.
 #[derive(Debug, Clone)]
 pub struct Header {
@@ -41,13 +86,76 @@ impl Display for Header {
     }
 }
 
-impl From<Header> for configuration::Header {
-    fn from(header: Header) -> Self {
-        Self {
-            key: header.key,
-            value: Some(header.value),
+/// Attributes of a request that the ratelimit rules are matched against.
+#[derive(Debug, Clone, Default)]
+pub struct Descriptor {
+    pub model: String,
+    /// Header named by the `x-arch-ratelimit-selector` request header.
+    pub selector: Option<Header>,
+    pub path: Option<String>,
+    pub jwt_claims: BTreeMap<String, String>,
+}
+
+impl Descriptor {
+    pub fn new(model: &str, selector: Option<Header>) -> Self {
+        Descriptor {
+            model: model.to_string(),
+            selector,
+            ..Default::default()
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.selector.is_none() && self.jwt_claims.is_empty()
+    }
+}
+
+impl Display for Descriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts: Vec<String> = self.selector.iter().map(Header::to_string).collect();
+        parts.extend(
+            self.jwt_claims
+                .iter()
+                .map(|(claim, value)| format!("jwt_claim {}={}", claim, value)),
+        );
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// State of the most constrained limit of a selector, reported to clients with the same
+/// `x-ratelimit-*` headers OpenAI uses so that their SDKs back off on their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub limit_tokens: u32,
+    pub remaining_tokens: u32,
+    /// Time until the bucket is full again.
+    pub reset: Duration,
+}
+
+impl Quota {
+    fn new(theoretical_arrival_ns: u64, limit: &Limit, now_ns: u64) -> Self {
+        let period_ns = period(&limit.unit).as_nanos() as u64;
+        let reset_ns = theoretical_arrival_ns.saturating_sub(now_ns);
+        let remaining_tokens = ((period_ns - reset_ns.min(period_ns)) as u128
+            * limit.tokens as u128
+            / period_ns as u128) as u32;
+        Quota {
+            limit_tokens: limit.tokens,
+            remaining_tokens,
+            reset: Duration::from_nanos(reset_ns),
         }
     }
+
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("x-ratelimit-limit-tokens", self.limit_tokens.to_string()),
+            (
+                "x-ratelimit-remaining-tokens",
+                self.remaining_tokens.to_string(),
+            ),
+            ("x-ratelimit-reset-tokens", format_duration(self.reset)),
+        ]
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -55,130 +163,467 @@ pub enum Error {
     #[error("exceeded limit provider={provider}, selector={selector}, tokens_used={tokens_used}")]
     ExceededLimit {
         provider: String,
-        selector: Header,
+        selector: String,
         tokens_used: NonZeroU32,
+        quota: Quota,
+        retry_after: Duration,
     },
+    #[error("no ratelimit bucket is free for provider={provider}, selector={selector}")]
+    NoFreeBucket { provider: String, selector: String },
+}
+
+impl Error {
+    /// Headers of the 429 response, the quota of the exceeded limit and when to retry.
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Error::ExceededLimit {
+                quota, retry_after, ..
+            } => {
+                let mut headers = quota.headers();
+                // Retry-After only takes whole seconds
+                let retry_after_secs =
+                    retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                headers.push(("retry-after", retry_after_secs.to_string()));
+                headers
+            }
+            // buckets are handed over once the selector values holding them stop sending requests
+            Error::NoFreeBucket { .. } => vec![("retry-after", "1".to_string())],
+        }
+    }
+}
+
+impl Rule {
+    // Values of the request that pick the bucket of the rule, None when the rule does not match it.
+    fn matches<'a>(&self, descriptor: &'a Descriptor) -> Option<Vec<&'a str>> {
+        if !glob_matches(&self.model, &descriptor.model) {
+            return None;
+        }
+        if let Some(path) = self.path.as_ref() {
+            if !glob_matches(path, descriptor.path.as_deref()?) {
+                return None;
+            }
+        }
+
+        let mut values = Vec::new();
+        if let Some(selector) = self.selector.as_ref() {
+            let header = descriptor
+                .selector
+                .as_ref()
+                .filter(|header| header.key == selector.key)?;
+            if selector.value.as_ref().is_some_and(|v| *v != header.value) {
+                return None;
+            }
+            values.push(header.value.as_str());
+        }
+        if let Some(jwt_claim) = self.jwt_claim.as_ref() {
+            let value = descriptor.jwt_claims.get(&jwt_claim.key)?;
+            if jwt_claim.value.as_ref().is_some_and(|v| v != value) {
+                return None;
+            }
+            values.push(value.as_str());
+        }
+        Some(values)
+    }
+
+    // Dimensions matched on an exact value count more than the ones matched on any value or on a
+    // glob, ties are broken by the length of the literal part of the globs.
+    fn specificity(&self) -> (usize, usize) {
+        let mut score = 0;
+        let mut literal_len = 0;
+        for glob in std::iter::once(&self.model).chain(self.path.as_ref()) {
+            score += if glob.contains('*') { 1 } else { 2 };
+            literal_len += glob.replace('*', "").len();
+        }
+        for header in self.selector.iter().chain(self.jwt_claim.as_ref()) {
+            score += if header.value.is_some() { 2 } else { 1 };
+        }
+        (score, literal_len)
+    }
+
+    fn is_same_selector(&self, other: &Rule) -> bool {
+        self.model == other.model
+            && self.selector == other.selector
+            && self.path == other.path
+            && self.jwt_claim == other.jwt_claim
+    }
 }
 
 impl RatelimitMap {
     // n.b new is private so that the only access to the Ratelimits can be done via the static
     // reference inside a RwLock via ratelimit::ratelimits().
     fn new(ratelimits_config: Vec<Ratelimit>) -> Self {
-        let mut new_ratelimit_map = RatelimitMap {
-            datastore: HashMap::new(),
-        };
+        let mut rules: Vec<Rule> = Vec::new();
         for ratelimit_config in ratelimits_config {
-            let limit = DefaultKeyedRateLimiter::keyed(get_quota(ratelimit_config.limit));
+            let limits = Limits {
+                input: ratelimit_config.input_limit,
+                output: ratelimit_config.output_limit,
+                total: ratelimit_config.limit,
+            };
+            assert!(
+                limits.input.is_some() || limits.output.is_some() || limits.total.is_some(),
+                "Ratelimit rule must set limit, input_limit or output_limit"
+            );
+            for limit in [&limits.input, &limits.output, &limits.total]
+                .into_iter()
+                .flatten()
+            {
+                assert!(limit.tokens > 0, "Limit's tokens must be positive");
+            }
 
-            match new_ratelimit_map.datastore.get_mut(&ratelimit_config.model) {
-                Some(limits) => match limits.get_mut(&ratelimit_config.selector) {
-                    Some(_) => {
-                        panic!("repeated selector. Selectors per provider must be unique")
-                    }
-                    None => {
-                        limits.insert(ratelimit_config.selector, limit);
-                    }
-                },
-                None => {
-                    // The provider has not been seen before.
-                    // Insert the provider and a new HashMap with the specified limit
-                    let new_hash_map = HashMap::from([(ratelimit_config.selector, limit)]);
-                    new_ratelimit_map
-                        .datastore
-                        .insert(ratelimit_config.model, new_hash_map);
-                }
+            let rule = Rule {
+                model: ratelimit_config.model,
+                selector: ratelimit_config.selector,
+                path: ratelimit_config.path,
+                jwt_claim: ratelimit_config.jwt_claim,
+                limits,
+            };
+            if rules.iter().any(|other| other.is_same_selector(&rule)) {
+                panic!("repeated selector. Selectors per provider must be unique")
             }
+            rules.push(rule);
         }
-        new_ratelimit_map
+        RatelimitMap { rules }
+    }
+
+    /// Names of the jwt claims the rules select on, requests are only decoded when there are any.
+    pub fn jwt_claims(&self) -> HashSet<&str> {
+        self.rules
+            .iter()
+            .filter_map(|rule| rule.jwt_claim.as_ref())
+            .map(|jwt_claim| jwt_claim.key.as_str())
+            .collect()
     }
 
-    #[allow(unused)]
-    pub fn check_limit(
+    /// Whether a request with `descriptor` has to be checked against the rules at all. Requests that
+    /// carry neither a selector header nor a jwt claim only match rules that select on neither.
+    pub fn applies_to(&self, descriptor: &Descriptor) -> bool {
+        !descriptor.is_empty()
+            || self
+                .rules
+                .iter()
+                .any(|rule| rule.selector.is_none() && rule.jwt_claim.is_none())
+    }
+
+    /// Takes the input tokens `tokens_used` out of the input and total buckets of the most specific
+    /// rule that matches the request, which is also held back while its output bucket is in debt.
+    /// Buckets are encoded with the generic cell rate algorithm (GCRA) as the theoretical arrival time
+    /// of the next token, which is a single value that can be updated with compare-and-swap. Returns
+    /// the quota left of the most constrained limit when a rule matches.
+    pub fn check_limit<S: SharedStore + ?Sized>(
         &self,
-        provider: String,
-        selector: Header,
+        store: &S,
+        descriptor: &Descriptor,
         tokens_used: NonZeroU32,
-    ) -> Result<(), Error> {
+        now_ns: u64,
+    ) -> Result<Option<Quota>, Error> {
         debug!(
-            "Checking limit for provider={}, with selector={:?}, consuming tokens={:?}",
-            provider, selector, tokens_used
+            "Checking limit for provider={}, with selector={}, consuming tokens={:?}",
+            descriptor.model, descriptor, tokens_used
         );
 
-        let provider_limits = match self.datastore.get(&provider) {
-            None => {
-                // No limit configured for this provider, hence ok.
-                return Ok(());
-            }
-            Some(limit) => limit,
+        let (prefix, owner, limits) = match self.most_specific_rule(descriptor) {
+            Some(rule) => rule,
+            // No rule matches the request, hence ok.
+            None => return Ok(None),
         };
 
-        let mut config_selector = configuration::Header::from(selector.clone());
-
-        let (limit, limit_key) = match provider_limits.get(&config_selector) {
-            // This is a specific limit, i.e one that was configured with both key, and value.
-            // Therefore, the key for the internal limit does not matter, and hence the empty string is always returned.
-            Some(limit) => (limit, String::from("")),
-            None => {
-                // Unwrap is ok here because we _know_ the value exists.
-                let header_key = config_selector.value.take().unwrap();
-                // Search for less specific limit, i.e, one that was configured without a value, therefore every Header
-                // value has its own key in the internal limit.
-                match provider_limits.get(&config_selector) {
-                    Some(limit) => (limit, header_key),
-                    // No limit for that header key, value pair exists within that provider limits.
-                    None => {
-                        return Ok(());
+        let is_idle = |buckets: &Buckets| buckets.is_full(now_ns);
+        let result = shared_data::update_owned(
+            store,
+            &prefix,
+            BUCKET_SLOTS,
+            &owner,
+            is_idle,
+            |buckets: &mut Buckets| {
+                let mut updated = *buckets;
+                let mut quota: Option<Quota> = None;
+                // the output tokens of the request are only known once its response ended
+                for (limit, theoretical_arrival_ns, tokens) in [
+                    (&limits.input, &mut updated.input, tokens_used.get()),
+                    (&limits.output, &mut updated.output, 0),
+                    (&limits.total, &mut updated.total, tokens_used.get()),
+                ] {
+                    let Some(limit) = limit else {
+                        continue;
+                    };
+                    if let Err(retry_after) = gcra(theoretical_arrival_ns, limit, tokens, now_ns) {
+                        return Err((
+                            Quota::new(*theoretical_arrival_ns, limit, now_ns),
+                            retry_after,
+                        ));
+                    }
+                    let limit_quota = Quota::new(*theoretical_arrival_ns, limit, now_ns);
+                    if quota
+                        .is_none_or(|quota| limit_quota.remaining_tokens < quota.remaining_tokens)
+                    {
+                        quota = Some(limit_quota);
                     }
                 }
-            }
-        };
+                *buckets = updated;
+                Ok(quota)
+            },
+        );
 
-        match limit.check_key_n(&limit_key, tokens_used) {
-            Ok(Ok(())) => Ok(()),
-            Ok(Err(_)) | Err(InsufficientCapacity(_)) => Err(Error::ExceededLimit {
-                provider,
-                selector,
+        match result {
+            Ok((_, Ok(quota))) => Ok(quota),
+            Ok((_, Err((quota, retry_after)))) => Err(Error::ExceededLimit {
+                provider: descriptor.model.clone(),
+                selector: descriptor.to_string(),
                 tokens_used,
+                quota,
+                retry_after,
+            }),
+            // fail closed, the selector value would otherwise draw from the bucket of another
+            Err(shared_data::Error::SlotsExhausted { .. }) => Err(Error::NoFreeBucket {
+                provider: descriptor.model.clone(),
+                selector: descriptor.to_string(),
             }),
+            Err(e) => {
+                // fail open, an unavailable store should not take the gateway down
+                warn!("could not check ratelimit {}: {}", prefix, e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Takes the output tokens of a finished response out of the output and total buckets of the rule
+    /// the request was checked against. The response was already served, so the buckets may go into
+    /// debt, which delays the requests that follow until it is paid off.
+    pub fn debit_output<S: SharedStore + ?Sized>(
+        &self,
+        store: &S,
+        descriptor: &Descriptor,
+        tokens_used: u32,
+        now_ns: u64,
+    ) {
+        let (prefix, owner, limits) = match self.most_specific_rule(descriptor) {
+            Some(rule) if tokens_used > 0 => rule,
+            _ => return,
+        };
+        if limits.output.is_none() && limits.total.is_none() {
+            return;
+        }
+        debug!(
+            "Debiting output tokens for provider={}, with selector={}, tokens={}",
+            descriptor.model, descriptor, tokens_used
+        );
+
+        // buckets that filled up and were handed to another selector value owe nothing anymore
+        let result = shared_data::update_held(
+            store,
+            &prefix,
+            BUCKET_SLOTS,
+            &owner,
+            |buckets: &mut Buckets| {
+                for (limit, theoretical_arrival_ns) in [
+                    (&limits.output, &mut buckets.output),
+                    (&limits.total, &mut buckets.total),
+                ] {
+                    if let Some(limit) = limit {
+                        *theoretical_arrival_ns = theoretical_arrival_after(
+                            *theoretical_arrival_ns,
+                            limit,
+                            tokens_used,
+                            now_ns,
+                        );
+                    }
+                }
+            },
+        );
+        if let Err(e) = result {
+            warn!("could not debit ratelimit {}: {}", prefix, e);
+        }
+    }
+
+    // The key prefix, bucket owner and limits of the most specific matching rule, the first one
+    // configured wins a tie. The selector values of the request own the buckets.
+    fn most_specific_rule(&self, descriptor: &Descriptor) -> Option<(String, String, &Limits)> {
+        let mut most_specific: Option<(usize, &Rule, Vec<&str>)> = None;
+        for (index, rule) in self.rules.iter().enumerate() {
+            let Some(values) = rule.matches(descriptor) else {
+                continue;
+            };
+            if most_specific
+                .as_ref()
+                .is_none_or(|(_, current, _)| rule.specificity() > current.specificity())
+            {
+                most_specific = Some((index, rule, values));
+            }
+        }
+
+        most_specific.map(|(index, rule, values)| {
+            let mut parts = vec![rule.model.as_str()];
+            parts.extend(values);
+            (
+                format!("arch.ratelimit.{}", index),
+                shared_data::encode_key_parts(&parts),
+                &rule.limits,
+            )
+        })
+    }
+}
+
+/// Claims of the bearer JWT in an `Authorization` header. The signature is not verified, which is
+/// left to whatever authenticates the request in front of the gateway.
+pub fn jwt_claims(authorization: &str, claims: &HashSet<&str>) -> BTreeMap<String, String> {
+    // the auth scheme is case insensitive, RFC 7235 section 2.1
+    let payload = authorization
+        .split_once(' ')
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Bearer"))
+        .and_then(|(_, token)| token.trim_start().split('.').nth(1))
+        .and_then(|payload| URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok())
+        .and_then(|payload| {
+            serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&payload).ok()
+        })
+        .unwrap_or_default();
+
+    payload
+        .into_iter()
+        .filter(|(claim, _)| claims.contains(claim.as_str()))
+        .map(|(claim, value)| match value {
+            serde_json::Value::String(value) => (claim, value),
+            value => (claim, value.to_string()),
+        })
+        .collect()
+}
+
+// Matches `value` against a pattern in which `*` stands for any sequence of characters.
+pub(crate) fn glob_matches(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // split always yields at least one part
+    let first = parts.next().unwrap();
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // no wildcard
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
         }
     }
+    rest.len() >= last.len() && rest.ends_with(last)
 }
 
-fn get_quota(limit: Limit) -> Quota {
-    let tokens = NonZero::new(limit.tokens).expect("Limit's tokens must be positive");
-    match limit.unit {
-        TimeUnit::Second => Quota::per_second(tokens),
-        TimeUnit::Minute => Quota::per_minute(tokens),
-        TimeUnit::Hour => Quota::per_hour(tokens),
+fn period(unit: &TimeUnit) -> Duration {
+    match unit {
+        TimeUnit::Second => Duration::from_secs(1),
+        TimeUnit::Minute => Duration::from_secs(60),
+        TimeUnit::Hour => Duration::from_secs(60 * 60),
+    }
+}
+
+// A bucket of `limit.tokens` that refills continuously over the period of the limit. Each token moves
+// the theoretical arrival time forward by period / tokens, the request fits when that does not push it
+// more than one period ahead of now. Otherwise returns how long until it would fit.
+fn gcra(
+    theoretical_arrival_ns: &mut u64,
+    limit: &Limit,
+    tokens: u32,
+    now_ns: u64,
+) -> Result<(), Duration> {
+    let period_ns = period(&limit.unit).as_nanos() as u64;
+    let new_theoretical_arrival_ns =
+        theoretical_arrival_after(*theoretical_arrival_ns, limit, tokens, now_ns);
+
+    let ahead_ns = new_theoretical_arrival_ns - now_ns;
+    if ahead_ns > period_ns {
+        return Err(Duration::from_nanos(ahead_ns - period_ns));
+    }
+    *theoretical_arrival_ns = new_theoretical_arrival_ns;
+    Ok(())
+}
+
+fn theoretical_arrival_after(
+    theoretical_arrival_ns: u64,
+    limit: &Limit,
+    tokens: u32,
+    now_ns: u64,
+) -> u64 {
+    let period_ns = period(&limit.unit).as_nanos() as u64;
+    let increment_ns = (period_ns as u128 * tokens as u128 / limit.tokens as u128) as u64;
+    theoretical_arrival_ns
+        .max(now_ns)
+        .saturating_add(increment_ns)
+}
+
+// Formats durations like OpenAI does in `x-ratelimit-reset-*` e.g. 20ms, 1.5s or 6m0s.
+fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_nanos().div_ceil(1_000_000) as u64;
+    if millis < 1000 {
+        return format!("{}ms", millis);
+    }
+
+    let (hours, minutes) = (millis / 3_600_000, millis / 60_000 % 60);
+    let seconds = match millis % 1000 {
+        0 => format!("{}s", millis / 1000 % 60),
+        fraction => {
+            format!("{}.{:03}", millis / 1000 % 60, fraction)
+                .trim_end_matches('0')
+                .to_string()
+                + "s"
+        }
+    };
+    if hours > 0 {
+        format!("{}h{}m{}", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m{}", minutes, seconds)
+    } else {
+        seconds
     }
 }
 
 // The following tests are inside the ratelimit module in order to access RatelimitMap::new() in order to provide
 // different configuration values per test.
+#[cfg(test)]
+use crate::shared_data::InMemorySharedStore;
+#[cfg(test)]
+use std::num::NonZero;
+#[cfg(test)]
+const NOW_NS: u64 = 1_700_000_000_000_000_000;
+
+#[cfg(test)]
+fn descriptor(model: &str, key: &str, value: &str) -> Descriptor {
+    Descriptor::new(
+        model,
+        Some(Header {
+            key: key.to_string(),
+            value: value.to_string(),
+        }),
+    )
+}
+
 #[test]
 fn non_existent_provider_is_ok() {
     let ratelimits_config = vec![Ratelimit {
         model: String::from("provider"),
-        selector: configuration::Header {
+        selector: Some(configuration::Header {
             key: String::from("only-key"),
             value: None,
-        },
-        limit: Limit {
+        }),
+        path: None,
+        jwt_claim: None,
+        limit: Some(Limit {
             tokens: 100,
             unit: TimeUnit::Minute,
-        },
+        }),
+        input_limit: None,
+        output_limit: None,
     }];
 
     let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
 
     assert!(ratelimits
         .check_limit(
-            String::from("non-existent-provider"),
-            Header {
-                key: String::from("key"),
-                value: String::from("value"),
-            },
+            &store,
+            &descriptor("non-existent-provider", "key", "value"),
             NonZero::new(5000).unwrap(),
+            NOW_NS,
         )
         .is_ok())
 }
@@ -187,26 +632,29 @@ fn non_existent_provider_is_ok() {
 fn non_existent_key_is_ok() {
     let ratelimits_config = vec![Ratelimit {
         model: String::from("provider"),
-        selector: configuration::Header {
+        selector: Some(configuration::Header {
             key: String::from("only-key"),
             value: None,
-        },
-        limit: Limit {
+        }),
+        path: None,
+        jwt_claim: None,
+        limit: Some(Limit {
             tokens: 100,
             unit: TimeUnit::Minute,
-        },
+        }),
+        input_limit: None,
+        output_limit: None,
     }];
 
     let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
 
     assert!(ratelimits
         .check_limit(
-            String::from("provider"),
-            Header {
-                key: String::from("key"),
-                value: String::from("value"),
-            },
+            &store,
+            &descriptor("provider", "key", "value"),
             NonZero::new(5000).unwrap(),
+            NOW_NS,
         )
         .is_ok())
 }
@@ -215,26 +663,29 @@ fn non_existent_key_is_ok() {
 fn specific_limit_does_not_catch_non_specific_value() {
     let ratelimits_config = vec![Ratelimit {
         model: String::from("provider"),
-        selector: configuration::Header {
+        selector: Some(configuration::Header {
             key: String::from("key"),
             value: Some(String::from("value")),
-        },
-        limit: Limit {
+        }),
+        path: None,
+        jwt_claim: None,
+        limit: Some(Limit {
             tokens: 200,
             unit: TimeUnit::Second,
-        },
+        }),
+        input_limit: None,
+        output_limit: None,
     }];
 
     let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
 
     assert!(ratelimits
         .check_limit(
-            String::from("provider"),
-            Header {
-                key: String::from("key"),
-                value: String::from("not-the-correct-value"),
-            },
+            &store,
+            &descriptor("provider", "key", "not-the-correct-value"),
             NonZero::new(5000).unwrap(),
+            NOW_NS,
         )
         .is_ok())
 }
@@ -243,26 +694,29 @@ fn specific_limit_does_not_catch_non_specific_value() {
 fn specific_limit_is_hit() {
     let ratelimits_config = vec![Ratelimit {
         model: String::from("provider"),
-        selector: configuration::Header {
+        selector: Some(configuration::Header {
             key: String::from("key"),
             value: Some(String::from("value")),
-        },
-        limit: Limit {
+        }),
+        path: None,
+        jwt_claim: None,
+        limit: Some(Limit {
             tokens: 200,
             unit: TimeUnit::Hour,
-        },
+        }),
+        input_limit: None,
+        output_limit: None,
     }];
 
     let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
 
     assert!(ratelimits
         .check_limit(
-            String::from("provider"),
-            Header {
-                key: String::from("key"),
-                value: String::from("value"),
-            },
+            &store,
+            &descriptor("provider", "key", "value"),
             NonZero::new(5000).unwrap(),
+            NOW_NS,
         )
         .is_err())
 }
@@ -271,51 +725,50 @@ fn specific_limit_is_hit() {
 fn non_specific_key_has_different_limits_for_different_values() {
     let ratelimits_config = vec![Ratelimit {
         model: String::from("provider"),
-        selector: configuration::Header {
+        selector: Some(configuration::Header {
             key: String::from("only-key"),
             value: None,
-        },
-        limit: Limit {
+        }),
+        path: None,
+        jwt_claim: None,
+        limit: Some(Limit {
             tokens: 100,
             unit: TimeUnit::Hour,
-        },
+        }),
+        input_limit: None,
+        output_limit: None,
     }];
 
     let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
 
     // Value1 takes 50.
     assert!(ratelimits
         .check_limit(
-            String::from("provider"),
-            Header {
-                key: String::from("only-key"),
-                value: String::from("value1"),
-            },
+            &store,
+            &descriptor("provider", "only-key", "value1"),
             NonZero::new(50).unwrap(),
+            NOW_NS,
         )
         .is_ok());
 
     // value2 takes 60 because it has its own 100 limit
     assert!(ratelimits
         .check_limit(
-            String::from("provider"),
-            Header {
-                key: String::from("only-key"),
-                value: String::from("value2"),
-            },
+            &store,
+            &descriptor("provider", "only-key", "value2"),
             NonZero::new(60).unwrap(),
+            NOW_NS,
         )
         .is_ok());
 
     // However value1 cannot take more than 100 per hour which 50+70 = 120
     assert!(ratelimits
         .check_limit(
-            String::from("provider"),
-            Header {
-                key: String::from("only-key"),
-                value: String::from("value1"),
-            },
+            &store,
+            &descriptor("provider", "only-key", "value1"),
             NonZero::new(70).unwrap(),
+            NOW_NS,
         )
         .is_err())
 }
@@ -325,82 +778,517 @@ fn different_provider_can_have_different_limits_with_the_same_keys() {
     let ratelimits_config = vec![
         Ratelimit {
             model: String::from("first_provider"),
-            selector: configuration::Header {
+            selector: Some(configuration::Header {
                 key: String::from("key"),
                 value: Some(String::from("value")),
-            },
-            limit: Limit {
+            }),
+            path: None,
+            jwt_claim: None,
+            limit: Some(Limit {
                 tokens: 100,
                 unit: TimeUnit::Hour,
-            },
+            }),
+            input_limit: None,
+            output_limit: None,
         },
         Ratelimit {
             model: String::from("second_provider"),
-            selector: configuration::Header {
+            selector: Some(configuration::Header {
                 key: String::from("key"),
                 value: Some(String::from("value")),
-            },
-            limit: Limit {
+            }),
+            path: None,
+            jwt_claim: None,
+            limit: Some(Limit {
                 tokens: 200,
                 unit: TimeUnit::Hour,
-            },
+            }),
+            input_limit: None,
+            output_limit: None,
         },
     ];
 
     let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
 
     assert!(ratelimits
         .check_limit(
-            String::from("first_provider"),
-            Header {
-                key: String::from("key"),
-                value: String::from("value"),
-            },
+            &store,
+            &descriptor("first_provider", "key", "value"),
             NonZero::new(100).unwrap(),
+            NOW_NS,
         )
         .is_ok());
 
     assert!(ratelimits
         .check_limit(
-            String::from("second_provider"),
-            Header {
-                key: String::from("key"),
-                value: String::from("value"),
-            },
+            &store,
+            &descriptor("second_provider", "key", "value"),
             NonZero::new(200).unwrap(),
+            NOW_NS,
         )
         .is_ok());
 
     assert!(ratelimits
         .check_limit(
-            String::from("first_provider"),
-            Header {
-                key: String::from("key"),
-                value: String::from("value"),
-            },
+            &store,
+            &descriptor("first_provider", "key", "value"),
             NonZero::new(1).unwrap(),
+            NOW_NS,
         )
         .is_err());
 
     assert!(ratelimits
         .check_limit(
-            String::from("second_provider"),
-            Header {
-                key: String::from("key"),
-                value: String::from("value"),
-            },
+            &store,
+            &descriptor("second_provider", "key", "value"),
             NonZero::new(1).unwrap(),
+            NOW_NS,
         )
         .is_err());
 }
 
+#[test]
+fn workers_share_the_bucket_and_it_refills_over_time() {
+    let ratelimits_config = vec![Ratelimit {
+        model: String::from("provider"),
+        selector: Some(configuration::Header {
+            key: String::from("key"),
+            value: None,
+        }),
+        path: None,
+        jwt_claim: None,
+        limit: Some(Limit {
+            tokens: 60,
+            unit: TimeUnit::Minute,
+        }),
+        input_limit: None,
+        output_limit: None,
+    }];
+
+    // Every worker builds its own map from the configuration, the bucket lives in the shared store.
+    let first_worker = RatelimitMap::new(ratelimits_config.clone());
+    let second_worker = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
+    let check = |ratelimits: &RatelimitMap, tokens: u32, now_ns: u64| {
+        ratelimits.check_limit(
+            &store,
+            &descriptor("provider", "key", "value"),
+            NonZero::new(tokens).unwrap(),
+            now_ns,
+        )
+    };
+
+    assert!(check(&first_worker, 40, NOW_NS).is_ok());
+    assert!(check(&second_worker, 20, NOW_NS).is_ok());
+    assert!(check(&first_worker, 1, NOW_NS).is_err());
+
+    // One token per second comes back.
+    assert!(check(&second_worker, 10, NOW_NS + 9_000_000_000).is_err());
+    assert!(check(&second_worker, 10, NOW_NS + 10_000_000_000).is_ok());
+    assert!(check(&first_worker, 60, NOW_NS + 70_000_000_000).is_ok());
+}
+
+#[test]
+fn output_tokens_are_debited_from_output_and_total_limits() {
+    let ratelimits_config = vec![Ratelimit {
+        model: String::from("provider"),
+        selector: Some(configuration::Header {
+            key: String::from("key"),
+            value: None,
+        }),
+        path: None,
+        jwt_claim: None,
+        limit: Some(Limit {
+            tokens: 100,
+            unit: TimeUnit::Minute,
+        }),
+        input_limit: Some(Limit {
+            tokens: 80,
+            unit: TimeUnit::Minute,
+        }),
+        output_limit: Some(Limit {
+            tokens: 50,
+            unit: TimeUnit::Minute,
+        }),
+    }];
+
+    let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
+    let check = |value: &str, tokens: u32| {
+        ratelimits.check_limit(
+            &store,
+            &descriptor("provider", "key", value),
+            NonZero::new(tokens).unwrap(),
+            NOW_NS,
+        )
+    };
+
+    // the input limit is tighter than the total one
+    assert!(check("first", 81).is_err());
+    assert!(check("first", 80).is_ok());
+
+    // 80 input + 30 output tokens overdraw the total limit
+    ratelimits.debit_output(&store, &descriptor("provider", "key", "first"), 30, NOW_NS);
+    assert!(check("second", 1).is_ok());
+    assert!(check("first", 1).is_err());
+
+    // output tokens only count against the output and total limits
+    ratelimits.debit_output(&store, &descriptor("provider", "key", "second"), 50, NOW_NS);
+    assert!(check("second", 49).is_ok());
+    assert!(check("second", 1).is_err());
+}
+
+#[test]
+fn output_limit_holds_requests_back_while_in_debt() {
+    let ratelimits_config = vec![Ratelimit {
+        model: String::from("provider"),
+        selector: Some(configuration::Header {
+            key: String::from("key"),
+            value: None,
+        }),
+        path: None,
+        jwt_claim: None,
+        limit: None,
+        input_limit: None,
+        output_limit: Some(Limit {
+            tokens: 60,
+            unit: TimeUnit::Minute,
+        }),
+    }];
+
+    let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
+    let check = |now_ns: u64| {
+        ratelimits.check_limit(
+            &store,
+            &descriptor("provider", "key", "value"),
+            NonZero::new(1000).unwrap(),
+            now_ns,
+        )
+    };
+
+    // input tokens are not charged to the output limit, its quota is reported all the same
+    let quota = check(NOW_NS).unwrap().unwrap();
+    assert_eq!(quota.limit_tokens, 60);
+    assert_eq!(quota.remaining_tokens, 60);
+
+    ratelimits.debit_output(&store, &descriptor("provider", "key", "value"), 45, NOW_NS);
+    assert_eq!(check(NOW_NS).unwrap().unwrap().remaining_tokens, 15);
+
+    // 90 output tokens in a minute put the bucket 30 seconds in debt
+    ratelimits.debit_output(&store, &descriptor("provider", "key", "value"), 45, NOW_NS);
+    let Err(Error::ExceededLimit { retry_after, .. }) = check(NOW_NS) else {
+        panic!("expected ExceededLimit");
+    };
+    assert_eq!(retry_after, Duration::from_secs(30));
+    assert!(check(NOW_NS + 30_000_000_000).is_ok());
+}
+
+#[test]
+#[should_panic(expected = "Ratelimit rule must set limit, input_limit or output_limit")]
+fn rule_without_limits_is_rejected() {
+    RatelimitMap::new(vec![Ratelimit {
+        model: String::from("*"),
+        selector: None,
+        path: None,
+        jwt_claim: None,
+        limit: None,
+        input_limit: None,
+        output_limit: None,
+    }]);
+}
+
+#[test]
+fn quota_and_retry_after_are_reported() {
+    let ratelimits_config = vec![Ratelimit {
+        model: String::from("provider"),
+        selector: Some(configuration::Header {
+            key: String::from("key"),
+            value: None,
+        }),
+        path: None,
+        jwt_claim: None,
+        limit: Some(Limit {
+            tokens: 60,
+            unit: TimeUnit::Minute,
+        }),
+        input_limit: None,
+        output_limit: None,
+    }];
+
+    let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
+    let check = |tokens: u32| {
+        ratelimits.check_limit(
+            &store,
+            &descriptor("provider", "key", "value"),
+            NonZero::new(tokens).unwrap(),
+            NOW_NS,
+        )
+    };
+
+    let quota = check(45).unwrap().unwrap();
+    assert_eq!(
+        quota.headers(),
+        vec![
+            ("x-ratelimit-limit-tokens", String::from("60")),
+            ("x-ratelimit-remaining-tokens", String::from("15")),
+            ("x-ratelimit-reset-tokens", String::from("45s")),
+        ]
+    );
+
+    let error = check(20).unwrap_err();
+    assert_eq!(
+        error.headers(),
+        vec![
+            ("x-ratelimit-limit-tokens", String::from("60")),
+            ("x-ratelimit-remaining-tokens", String::from("15")),
+            ("x-ratelimit-reset-tokens", String::from("45s")),
+            ("retry-after", String::from("5")),
+        ]
+    );
+}
+
+#[test]
+fn durations_are_formatted_like_openai() {
+    assert_eq!(format_duration(Duration::from_nanos(1)), "1ms");
+    assert_eq!(format_duration(Duration::from_millis(20)), "20ms");
+    assert_eq!(format_duration(Duration::from_millis(1500)), "1.5s");
+    assert_eq!(format_duration(Duration::from_secs(360)), "6m0s");
+    assert_eq!(
+        format_duration(Duration::from_millis(3_723_250)),
+        "1h2m3.25s"
+    );
+}
+
+#[test]
+fn globs_match_any_sequence() {
+    assert!(glob_matches("gpt-4", "gpt-4"));
+    assert!(!glob_matches("gpt-4", "gpt-4o"));
+    assert!(glob_matches("gpt-4*", "gpt-4o-mini"));
+    assert!(glob_matches("*", "claude-3-5-sonnet"));
+    assert!(glob_matches("/v1/*/completions", "/v1/chat/completions"));
+    assert!(!glob_matches("gpt-4*-mini", "gpt-4"));
+    assert!(!glob_matches("gpt-*o", "claude-o"));
+}
+
+#[test]
+fn most_specific_rule_is_picked() {
+    let rule = |model: &str, value: Option<&str>, path: Option<&str>, tokens: u32| Ratelimit {
+        model: String::from(model),
+        selector: Some(configuration::Header {
+            key: String::from("key"),
+            value: value.map(String::from),
+        }),
+        path: path.map(String::from),
+        jwt_claim: None,
+        limit: Some(Limit {
+            tokens,
+            unit: TimeUnit::Hour,
+        }),
+        input_limit: None,
+        output_limit: None,
+    };
+    let ratelimits_config = vec![
+        rule("*", None, None, 10),
+        rule("gpt-4*", None, None, 20),
+        rule("gpt-4o", None, None, 30),
+        rule("gpt-4o", Some("value"), None, 40),
+        rule("gpt-4o", Some("value"), Some("/v1/chat/completions"), 50),
+    ];
+
+    let ratelimits = RatelimitMap::new(ratelimits_config);
+    let limit_tokens = |model: &str, value: &str, path: &str| {
+        let mut descriptor = descriptor(model, "key", value);
+        descriptor.path = Some(String::from(path));
+        ratelimits
+            .check_limit(
+                &InMemorySharedStore::default(),
+                &descriptor,
+                NonZero::new(1).unwrap(),
+                NOW_NS,
+            )
+            .unwrap()
+            .unwrap()
+            .limit_tokens
+    };
+
+    assert_eq!(limit_tokens("claude", "value", "/v1/chat/completions"), 10);
+    assert_eq!(
+        limit_tokens("gpt-4-turbo", "value", "/v1/chat/completions"),
+        20
+    );
+    assert_eq!(limit_tokens("gpt-4o", "other", "/v1/chat/completions"), 30);
+    assert_eq!(limit_tokens("gpt-4o", "value", "/v1/embeddings"), 40);
+    assert_eq!(limit_tokens("gpt-4o", "value", "/v1/chat/completions"), 50);
+}
+
+#[test]
+fn jwt_claims_select_buckets() {
+    let ratelimits_config = vec![Ratelimit {
+        model: String::from("*"),
+        selector: None,
+        path: None,
+        jwt_claim: Some(configuration::Header {
+            key: String::from("org_id"),
+            value: None,
+        }),
+        limit: Some(Limit {
+            tokens: 100,
+            unit: TimeUnit::Hour,
+        }),
+        input_limit: None,
+        output_limit: None,
+    }];
+
+    let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
+    // {"sub":"1234567890","org_id":"acme","admin":true}
+    let token = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.\
+        eyJzdWIiOiIxMjM0NTY3ODkwIiwib3JnX2lkIjoiYWNtZSIsImFkbWluIjp0cnVlfQ.\
+        c2lnbmF0dXJl";
+    let claims = jwt_claims(&format!("Bearer {token}"), &ratelimits.jwt_claims());
+    assert_eq!(
+        claims,
+        BTreeMap::from([(String::from("org_id"), String::from("acme"))])
+    );
+    assert_eq!(
+        jwt_claims(&format!("bearer {token}"), &ratelimits.jwt_claims()),
+        claims
+    );
+    assert!(jwt_claims(&format!("Basic {token}"), &ratelimits.jwt_claims()).is_empty());
+
+    let mut acme = Descriptor::new("gpt-4o", None);
+    acme.jwt_claims = claims;
+    let mut other = acme.clone();
+    other
+        .jwt_claims
+        .insert(String::from("org_id"), String::from("other"));
+
+    assert!(ratelimits.applies_to(&acme));
+    assert!(!ratelimits.applies_to(&Descriptor::new("gpt-4o", None)));
+    let check = |descriptor: &Descriptor, tokens: u32| {
+        ratelimits.check_limit(&store, descriptor, NonZero::new(tokens).unwrap(), NOW_NS)
+    };
+    assert!(check(&acme, 100).is_ok());
+    let error = check(&acme, 1).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "exceeded limit provider=gpt-4o, selector=jwt_claim org_id=acme, tokens_used=1"
+    );
+    assert!(check(&other, 100).is_ok());
+}
+
+#[test]
+fn values_with_dots_do_not_share_buckets() {
+    let ratelimits_config = vec![Ratelimit {
+        model: String::from("*"),
+        selector: Some(configuration::Header {
+            key: String::from("key"),
+            value: None,
+        }),
+        path: None,
+        jwt_claim: Some(configuration::Header {
+            key: String::from("sub"),
+            value: None,
+        }),
+        limit: Some(Limit {
+            tokens: 100,
+            unit: TimeUnit::Hour,
+        }),
+        input_limit: None,
+        output_limit: None,
+    }];
+
+    let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
+    let mut first = descriptor("gpt-4o", "key", "a.b");
+    first
+        .jwt_claims
+        .insert(String::from("sub"), String::from("c"));
+    let mut second = descriptor("gpt-4o", "key", "a");
+    second
+        .jwt_claims
+        .insert(String::from("sub"), String::from("b.c"));
+
+    let check = |descriptor: &Descriptor, tokens: u32| {
+        ratelimits.check_limit(&store, descriptor, NonZero::new(tokens).unwrap(), NOW_NS)
+    };
+    assert!(check(&first, 100).is_ok());
+    assert!(check(&first, 1).is_err());
+    assert!(check(&second, 100).is_ok());
+}
+
+#[test]
+fn values_never_share_buckets() {
+    let ratelimits_config = vec![Ratelimit {
+        model: String::from("*"),
+        selector: Some(configuration::Header {
+            key: String::from("key"),
+            value: None,
+        }),
+        path: None,
+        jwt_claim: None,
+        limit: Some(Limit {
+            tokens: 100,
+            unit: TimeUnit::Minute,
+        }),
+        input_limit: None,
+        output_limit: None,
+    }];
+
+    let ratelimits = RatelimitMap::new(ratelimits_config);
+    let store = InMemorySharedStore::default();
+    let check = |value: &str, tokens: u32, now_ns: u64| {
+        ratelimits.check_limit(
+            &store,
+            &descriptor("gpt-4o", "key", value),
+            NonZero::new(tokens).unwrap(),
+            now_ns,
+        )
+    };
+
+    // values drain their buckets until one finds every bucket it may take held by others
+    let mut admitted = Vec::new();
+    let refused = (0..)
+        .map(|i| format!("value-{}", i))
+        .find(|value| match check(value, 100, NOW_NS) {
+            Ok(_) => {
+                admitted.push(value.clone());
+                false
+            }
+            Err(Error::NoFreeBucket { .. }) => true,
+            Err(e) => panic!("unexpected error {}", e),
+        })
+        .unwrap();
+
+    // the refused value did not drain the bucket of another, nor another the bucket it gets later
+    assert!(matches!(
+        check(&refused, 1, NOW_NS),
+        Err(Error::NoFreeBucket { .. })
+    ));
+    for value in admitted.iter() {
+        assert!(matches!(
+            check(value, 1, NOW_NS),
+            Err(Error::ExceededLimit { .. })
+        ));
+    }
+    // a minute later the buckets are full again and can be handed over
+    let quota = check(&refused, 1, NOW_NS + 60_000_000_000)
+        .unwrap()
+        .unwrap();
+    assert_eq!(quota.remaining_tokens, 99);
+}
+
 // These tests use the publicly exposed static singleton, thus the same configuration is used in every test.
 // If more tests are written here, move the initial call out of the test.
 #[cfg(test)]
 mod test {
     use crate::configuration;
 
-    use super::ratelimits;
+    use super::{ratelimits, NOW_NS};
+    use crate::shared_data::InMemorySharedStore;
     use configuration::{Limit, Ratelimit, TimeUnit};
     use std::num::NonZero;
     use std::thread;
@@ -417,14 +1305,18 @@ mod test {
     fn different_threads_have_same_ratelimit_data_structure() {
         let ratelimits_config = Some(vec![Ratelimit {
             model: String::from("provider"),
-            selector: configuration::Header {
+            selector: Some(configuration::Header {
                 key: String::from("key"),
                 value: Some(String::from("value")),
-            },
-            limit: Limit {
+            }),
+            path: None,
+            jwt_claim: None,
+            limit: Some(Limit {
                 tokens: 200,
                 unit: TimeUnit::Hour,
-            },
+            }),
+            input_limit: None,
+            output_limit: None,
         }]);
 
         // Initialize in the main thread.
@@ -433,17 +1325,16 @@ mod test {
         // Use the singleton in a different thread.
         thread::spawn(|| {
             let ratelimits = ratelimits(None);
+            let store = InMemorySharedStore::default();
 
             assert!(ratelimits
                 .read()
                 .unwrap()
                 .check_limit(
-                    String::from("provider"),
-                    super::Header {
-                        key: String::from("key"),
-                        value: String::from("value"),
-                    },
+                    &store,
+                    &super::descriptor("provider", "key", "value"),
                     NonZero::new(5000).unwrap(),
+                    NOW_NS,
                 )
                 .is_err())
         });
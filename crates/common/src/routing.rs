@@ -2,9 +2,14 @@ use std::rc::Rc;
 
 use crate::{configuration, llm_providers::LlmProviders};
 use configuration::LlmProvider;
-use rand::{seq::IteratorRandom, thread_rng};
+use log::debug;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::IteratorRandom,
+    thread_rng, Rng,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ProviderHint {
     Default,
     Name(String),
@@ -19,29 +24,154 @@ impl From<String> for ProviderHint {
     }
 }
 
+/// Picks the hinted provider, then the default one, then a random one, skipping the providers that
+/// `is_available` rejects e.g. because their circuit is open. When no provider is available the
+/// selection falls back to ignoring availability.
 pub fn get_llm_provider(
     llm_providers: &LlmProviders,
     provider_hint: Option<ProviderHint>,
+    is_available: impl Fn(&LlmProvider) -> bool,
 ) -> Rc<LlmProvider> {
     let maybe_provider = provider_hint.and_then(|hint| match hint {
         ProviderHint::Default => llm_providers.default(),
         // FIXME: should a non-existent name in the hint be more explicit? i.e, return a BAD_REQUEST?
-        ProviderHint::Name(name) => llm_providers.get(&name),
+        ProviderHint::Name(name) => llm_providers.get_available(&name, &is_available),
     });
 
-    if let Some(provider) = maybe_provider {
-        return provider;
+    if let Some(provider) = maybe_provider.as_ref() {
+        if is_available(provider) {
+            return Rc::clone(provider);
+        }
+        debug!("llm provider {} is unavailable", provider);
     }
 
-    if llm_providers.default().is_some() {
-        return llm_providers.default().unwrap();
+    if let Some(provider) = llm_providers.default() {
+        if is_available(&provider) {
+            return provider;
+        }
     }
 
     let mut rng = thread_rng();
     llm_providers
         .iter()
+        .map(|(_, provider)| provider)
+        .filter(|provider| is_available(provider))
         .choose(&mut rng)
-        .expect("There should always be at least one llm provider")
-        .1
-        .clone()
+        .cloned()
+        .or(maybe_provider)
+        .or_else(|| llm_providers.default())
+        .unwrap_or_else(|| {
+            llm_providers
+                .iter()
+                .choose(&mut rng)
+                .expect("There should always be at least one llm provider")
+                .1
+                .clone()
+        })
+}
+
+/// Picks an index with a probability proportional to its weight.
+pub fn weighted_random_index<R: Rng>(weights: &[u32], rng: &mut R) -> usize {
+    match WeightedIndex::new(weights) {
+        Ok(distribution) => distribution.sample(rng),
+        // all weights are zero, treat them as equal
+        Err(_) => rng.gen_range(0..weights.len()),
+    }
+}
+
+/// Smooth weighted round-robin, weights 5, 1, 1 yield the sequence a, a, b, a, c, a, a.
+/// `current_weights` carries the state between picks.
+pub fn weighted_round_robin_index(weights: &[u32], current_weights: &mut Vec<i64>) -> usize {
+    let weights: Vec<i64> = if weights.iter().all(|weight| *weight == 0) {
+        vec![1; weights.len()]
+    } else {
+        weights.iter().map(|weight| *weight as i64).collect()
+    };
+    let total: i64 = weights.iter().sum();
+    current_weights.resize(weights.len(), 0);
+
+    let mut selected = 0;
+    for (index, weight) in weights.iter().enumerate() {
+        current_weights[index] += weight;
+        if current_weights[index] > current_weights[selected] {
+            selected = index;
+        }
+    }
+    current_weights[selected] -= total;
+
+    selected
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        get_llm_provider, weighted_random_index, weighted_round_robin_index, ProviderHint,
+    };
+    use crate::configuration::LlmProvider;
+    use crate::llm_providers::LlmProviders;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn llm_providers() -> LlmProviders {
+        LlmProviders::try_from(vec![
+            LlmProvider {
+                name: "openai/gpt-4o".to_string(),
+                model: Some("gpt-4o".to_string()),
+                default: Some(true),
+                ..Default::default()
+            },
+            LlmProvider {
+                name: "mistral/ministral-3b".to_string(),
+                model: Some("ministral-3b".to_string()),
+                default: None,
+                ..Default::default()
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn unavailable_providers_are_skipped() {
+        let llm_providers = llm_providers();
+        let hint = || Some(ProviderHint::Name("openai/gpt-4o".to_string()));
+
+        let provider = get_llm_provider(&llm_providers, hint(), |_| true);
+        assert_eq!(provider.name, "openai/gpt-4o");
+
+        let provider = get_llm_provider(&llm_providers, hint(), |p| p.name != "openai/gpt-4o");
+        assert_eq!(provider.name, "mistral/ministral-3b");
+
+        // with nothing available the hint still wins
+        let provider = get_llm_provider(&llm_providers, hint(), |_| false);
+        assert_eq!(provider.name, "openai/gpt-4o");
+    }
+
+    #[test]
+    fn smooth_weighted_round_robin() {
+        let mut current_weights = Vec::new();
+        let picks: Vec<usize> = (0..7)
+            .map(|_| weighted_round_robin_index(&[5, 1, 1], &mut current_weights))
+            .collect();
+        assert_eq!(picks, vec![0, 0, 1, 0, 2, 0, 0]);
+    }
+
+    #[test]
+    fn round_robin_with_zero_weights() {
+        let mut current_weights = Vec::new();
+        let picks: Vec<usize> = (0..4)
+            .map(|_| weighted_round_robin_index(&[0, 0], &mut current_weights))
+            .collect();
+        assert_eq!(picks, vec![0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn weighted_random_follows_weights() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut counts = [0; 2];
+        for _ in 0..10_000 {
+            counts[weighted_random_index(&[70, 30], &mut rng)] += 1;
+        }
+        assert!((6_500..7_500).contains(&counts[0]), "{:?}", counts);
+
+        assert_eq!(weighted_random_index(&[0, 5], &mut rng), 1);
+    }
 }
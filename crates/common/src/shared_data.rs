@@ -0,0 +1,406 @@
+use log::warn;
+use proxy_wasm::hostcalls;
+use proxy_wasm::types::Status;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(test)]
+use std::cell::RefCell;
+#[cfg(test)]
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+// Number of times an update is retried when another worker wrote the same key in between.
+const MAX_CAS_RETRIES: usize = 8;
+// Number of slots of a bounded keyspace an owner may take, starting at the slot it hashes to.
+const MAX_SLOT_PROBES: u64 = 4;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("shared data for key `{key}` could not be accessed, status: {status:?}")]
+    Host { key: String, status: Status },
+    #[error("shared data for key `{key}` kept changing, gave up after {MAX_CAS_RETRIES} attempts")]
+    CasRetriesExhausted { key: String },
+    #[error("every slot of `{prefix}` that `{owner}` may take is held by another owner")]
+    SlotsExhausted { prefix: String, owner: String },
+    #[error("shared data for key `{key}` could not be serialized: {source}")]
+    Serialization {
+        key: String,
+        source: serde_json::Error,
+    },
+}
+
+/// Key/value store that is visible to every worker thread of the VM. Values carry a compare-and-swap
+/// token so concurrent writers can detect that they raced each other.
+pub trait SharedStore {
+    fn get(&self, key: &str) -> Result<(Option<Vec<u8>>, Option<u32>), Status>;
+
+    fn set(&self, key: &str, value: &[u8], cas: Option<u32>) -> Result<(), Status>;
+}
+
+/// Store backed by the proxy-wasm shared data of the host.
+pub struct HostSharedStore;
+
+impl SharedStore for HostSharedStore {
+    fn get(&self, key: &str) -> Result<(Option<Vec<u8>>, Option<u32>), Status> {
+        hostcalls::get_shared_data(key)
+    }
+
+    fn set(&self, key: &str, value: &[u8], cas: Option<u32>) -> Result<(), Status> {
+        hostcalls::set_shared_data(key, Some(value), cas)
+    }
+}
+
+/// Store that follows the host's compare-and-swap semantics without a host, used in tests.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct InMemorySharedStore {
+    entries: RefCell<HashMap<String, (Vec<u8>, u32)>>,
+}
+
+#[cfg(test)]
+impl SharedStore for InMemorySharedStore {
+    fn get(&self, key: &str) -> Result<(Option<Vec<u8>>, Option<u32>), Status> {
+        Ok(match self.entries.borrow().get(key) {
+            Some((value, cas)) => (Some(value.clone()), Some(*cas)),
+            None => (None, None),
+        })
+    }
+
+    fn set(&self, key: &str, value: &[u8], cas: Option<u32>) -> Result<(), Status> {
+        let mut entries = self.entries.borrow_mut();
+        let current_cas = entries.get(key).map(|(_, cas)| *cas).unwrap_or(0);
+        if let Some(cas) = cas {
+            if cas != 0 && cas != current_cas {
+                return Err(Status::CasMismatch);
+            }
+        }
+        entries.insert(key.to_string(), (value.to_vec(), current_cas + 1));
+        Ok(())
+    }
+}
+
+/// Reads the JSON encoded value stored at `key`. Values that fail to decode are treated as missing.
+pub fn read<S, T>(store: &S, key: &str) -> Result<Option<T>, Error>
+where
+    S: SharedStore + ?Sized,
+    T: DeserializeOwned,
+{
+    let (bytes, _) = store.get(key).map_err(|status| Error::Host {
+        key: key.to_string(),
+        status,
+    })?;
+    Ok(bytes.and_then(|bytes| decode(key, &bytes)))
+}
+
+/// Applies `update` to the value stored at `key` and writes it back with compare-and-swap. The update
+/// is re-applied on a fresh read whenever another worker wrote the key in between.
+pub fn update<S, T, R, F>(store: &S, key: &str, mut update: F) -> Result<R, Error>
+where
+    S: SharedStore + ?Sized,
+    T: Serialize + DeserializeOwned + Default,
+    F: FnMut(&mut T) -> R,
+{
+    for _ in 0..MAX_CAS_RETRIES {
+        let (bytes, cas) = store.get(key).map_err(|status| Error::Host {
+            key: key.to_string(),
+            status,
+        })?;
+        let mut value: T = bytes
+            .and_then(|bytes| decode(key, &bytes))
+            .unwrap_or_default();
+
+        let result = update(&mut value);
+
+        let bytes = serde_json::to_vec(&value).map_err(|source| Error::Serialization {
+            key: key.to_string(),
+            source,
+        })?;
+        match store.set(key, &bytes, cas) {
+            Ok(()) => return Ok(result),
+            Err(Status::CasMismatch) => continue,
+            Err(status) => {
+                return Err(Error::Host {
+                    key: key.to_string(),
+                    status,
+                })
+            }
+        }
+    }
+
+    Err(Error::CasRetriesExhausted {
+        key: key.to_string(),
+    })
+}
+
+/// Encodes the parts of a key so that different parts never encode alike, whatever characters
+/// they contain, e.g. `["a.b", "c"]` and `["a", "b.c"]`.
+pub fn encode_key_parts<T: AsRef<str>>(parts: &[T]) -> String {
+    parts
+        .iter()
+        .map(|part| format!("{}:{}", part.as_ref().len(), part.as_ref()))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// A value of a bounded keyspace along with the owner it currently belongs to.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Slot<T> {
+    pub owner: String,
+    pub value: T,
+}
+
+/// Keys of a bounded keyspace `owner` may take, they are the same on every worker.
+pub fn slot_keys(prefix: &str, slots: u64, owner: &str) -> Vec<String> {
+    let mut hasher = DefaultHasher::new();
+    owner.hash(&mut hasher);
+    let first = hasher.finish();
+    (0..MAX_SLOT_PROBES.min(slots))
+        .map(|probe| format!("{}.{}", prefix, first.wrapping_add(probe) % slots))
+        .collect()
+}
+
+/// Applies `update` to the value of `owner` in a keyspace of `slots` keys under `prefix`, e.g. to
+/// keep state per request header value without every value the clients send adding a key to the
+/// shared data of the host, which is never evicted. The owner takes the first of its slots that it
+/// already holds, that is free or whose value `is_idle` i.e. equal to a default one. It never shares
+/// a slot, when others hold all of them the update fails with `Error::SlotsExhausted`. Returns the
+/// key the value is stored at.
+pub fn update_owned<S, T, R, F>(
+    store: &S,
+    prefix: &str,
+    slots: u64,
+    owner: &str,
+    is_idle: impl Fn(&T) -> bool,
+    mut update: F,
+) -> Result<(String, R), Error>
+where
+    S: SharedStore + ?Sized,
+    T: Serialize + DeserializeOwned + Default,
+    F: FnMut(&mut T) -> R,
+{
+    for key in slot_keys(prefix, slots, owner) {
+        let result = self::update(store, &key, |slot: &mut Slot<T>| {
+            if slot.owner != owner {
+                if !slot.owner.is_empty() && !is_idle(&slot.value) {
+                    return None;
+                }
+                *slot = Slot {
+                    owner: owner.to_string(),
+                    value: T::default(),
+                };
+            }
+            Some(update(&mut slot.value))
+        })?;
+        if let Some(result) = result {
+            return Ok((key, result));
+        }
+    }
+
+    warn!(
+        "every slot of {} that {} may take is held by another owner",
+        prefix, owner
+    );
+    Err(Error::SlotsExhausted {
+        prefix: prefix.to_string(),
+        owner: owner.to_string(),
+    })
+}
+
+/// Applies `update` to the value of `owner` in a keyspace of `slots` keys under `prefix` when it
+/// holds one of them, see `update_owned`.
+pub fn update_held<S, T, R, F>(
+    store: &S,
+    prefix: &str,
+    slots: u64,
+    owner: &str,
+    mut update: F,
+) -> Result<Option<R>, Error>
+where
+    S: SharedStore + ?Sized,
+    T: Serialize + DeserializeOwned + Default,
+    F: FnMut(&mut T) -> R,
+{
+    for key in slot_keys(prefix, slots, owner) {
+        if read::<_, Slot<T>>(store, &key)?.is_none_or(|slot| slot.owner != owner) {
+            continue;
+        }
+        let result = self::update(store, &key, |slot: &mut Slot<T>| {
+            (slot.owner == owner).then(|| update(&mut slot.value))
+        })?;
+        if result.is_some() {
+            return Ok(result);
+        }
+    }
+    Ok(None)
+}
+
+fn decode<T: DeserializeOwned>(key: &str, bytes: &[u8]) -> Option<T> {
+    match serde_json::from_slice(bytes) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("discarding undecodable shared data for key {}: {}", key, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        encode_key_parts, read, slot_keys, update, update_held, update_owned, Error,
+        InMemorySharedStore, SharedStore,
+    };
+    use proxy_wasm::types::Status;
+
+    #[test]
+    fn stale_cas_is_rejected() {
+        let store = InMemorySharedStore::default();
+        store.set("key", b"1", None).unwrap();
+        let (_, cas) = store.get("key").unwrap();
+        store.set("key", b"2", cas).unwrap();
+
+        assert!(matches!(
+            store.set("key", b"3", cas),
+            Err(Status::CasMismatch)
+        ));
+    }
+
+    #[test]
+    fn update_reads_modifies_and_writes() {
+        let store = InMemorySharedStore::default();
+        assert_eq!(read::<_, u64>(&store, "counter").unwrap(), None);
+
+        for _ in 0..3 {
+            update(&store, "counter", |counter: &mut u64| *counter += 1).unwrap();
+        }
+        assert_eq!(read::<_, u64>(&store, "counter").unwrap(), Some(3));
+    }
+
+    #[test]
+    fn key_parts_are_encoded_unambiguously() {
+        assert_eq!(encode_key_parts(&["a.b", "c"]), "3:a.b.1:c");
+        assert_ne!(
+            encode_key_parts(&["a.b", "c"]),
+            encode_key_parts(&["a", "b.c"])
+        );
+    }
+
+    #[test]
+    fn owners_share_a_bounded_keyspace() {
+        let store = InMemorySharedStore::default();
+        // counters that are idle once they drop back to zero
+        let is_idle = |counter: &u64| *counter == 0;
+
+        let owners: Vec<String> = (0..100).map(|i| format!("owner-{}", i)).collect();
+        for owner in owners.iter() {
+            let result = update_owned(
+                &store,
+                "counters",
+                8,
+                owner,
+                is_idle,
+                |counter: &mut u64| *counter += 1,
+            );
+            // owners that find all of their slots held by others are refused
+            assert!(matches!(result, Ok(_) | Err(Error::SlotsExhausted { .. })));
+        }
+        // a hundred owners, no more keys than slots
+        assert_eq!(store.entries.borrow().len(), 8);
+
+        // an owner keeps its slot while it is in use
+        let (key, _) = update_owned(&store, "other", 1024, "a", is_idle, |counter: &mut u64| {
+            *counter += 1
+        })
+        .unwrap();
+        assert!(slot_keys("other", 1024, "a").contains(&key));
+        let (same_key, count) =
+            update_owned(&store, "other", 1024, "a", is_idle, |counter: &mut u64| {
+                *counter += 1;
+                *counter
+            })
+            .unwrap();
+        assert_eq!((same_key.as_str(), count), (key.as_str(), 2));
+        assert_eq!(
+            update_held(&store, "other", 1024, "a", |counter: &mut u64| *counter).unwrap(),
+            Some(2)
+        );
+        assert_eq!(
+            update_held(&store, "other", 1024, "b", |counter: &mut u64| *counter).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn owners_never_share_a_slot() {
+        let store = InMemorySharedStore::default();
+        let is_idle = |counter: &u64| *counter == 0;
+
+        // as many slots as probes, the first owners to come take all of them
+        let slots = slot_keys("counters", 4, "a").len() as u64;
+        assert_eq!(slots, 4);
+        let owners: Vec<String> = (0..slots).map(|i| format!("owner-{}", i)).collect();
+        for owner in owners.iter() {
+            update_owned(
+                &store,
+                "counters",
+                slots,
+                owner,
+                is_idle,
+                |counter: &mut u64| *counter += 1,
+            )
+            .unwrap();
+        }
+
+        let result = update_owned(
+            &store,
+            "counters",
+            slots,
+            "a",
+            is_idle,
+            |counter: &mut u64| *counter += 1,
+        );
+        assert!(matches!(result, Err(Error::SlotsExhausted { .. })));
+        for owner in owners.iter() {
+            assert_eq!(
+                update_held(&store, "counters", slots, owner, |counter: &mut u64| {
+                    *counter
+                })
+                .unwrap(),
+                Some(1)
+            );
+        }
+        assert_eq!(
+            update_held(&store, "counters", slots, "a", |counter: &mut u64| *counter).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn idle_slots_are_taken_over() {
+        let store = InMemorySharedStore::default();
+        let is_idle = |counter: &u64| *counter == 0;
+
+        update_owned(&store, "counters", 1, "a", is_idle, |counter: &mut u64| {
+            *counter += 1
+        })
+        .unwrap();
+        // the slot is busy, b can not take it
+        assert!(
+            update_owned(&store, "counters", 1, "b", is_idle, |counter: &mut u64| {
+                *counter += 1
+            })
+            .is_err()
+        );
+
+        update_held(&store, "counters", 1, "a", |counter: &mut u64| *counter = 0).unwrap();
+        let (_, count) = update_owned(&store, "counters", 1, "b", is_idle, |counter: &mut u64| {
+            *counter += 1;
+            *counter
+        })
+        .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            update_held(&store, "counters", 1, "a", |counter: &mut u64| *counter).unwrap(),
+            None
+        );
+    }
+}
@@ -1,6 +1,13 @@
 use log::error;
 use proxy_wasm::hostcalls;
 use proxy_wasm::types::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Most tag combinations a tagged metric defines, any further combination is recorded with all
+/// of its tag values set to [`OTHER_TAG_VALUE`].
+pub const MAX_TAG_COMBINATIONS: usize = 256;
+pub const OTHER_TAG_VALUE: &str = "other";
 
 pub trait Metric {
     fn id(&self) -> u32;
@@ -96,3 +103,110 @@ impl Metric for Histogram {
 }
 
 impl RecordingMetric for Histogram {}
+
+/// The name of a metric with its tags embedded as `name;key=value;...`, for the `stats_tags`
+/// extractors of the envoy config to turn back into tags.
+pub fn tagged_name<V: AsRef<str>>(name: &str, tags: &[(&str, V)]) -> String {
+    let mut tagged_name = name.to_string();
+    for (key, value) in tags {
+        tagged_name.push(';');
+        tagged_name.push_str(key);
+        tagged_name.push('=');
+        tagged_name.extend(value.as_ref().chars().map(|c| match c {
+            ';' | '=' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        }));
+    }
+    tagged_name
+}
+
+/// A metric split by tags. Envoy metrics have no dimensions, so one metric is defined for every
+/// combination of tag values, the first time it is used.
+#[derive(Debug)]
+pub struct Tagged<M> {
+    name: String,
+    define: fn(String) -> M,
+    metrics: RefCell<HashMap<String, M>>,
+}
+
+impl<M: Clone> Tagged<M> {
+    pub fn new(name: &str, define: fn(String) -> M) -> Self {
+        Tagged {
+            name: name.to_string(),
+            define,
+            metrics: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_tags<V: AsRef<str>>(&self, tags: &[(&str, V)]) -> M {
+        let mut metrics = self.metrics.borrow_mut();
+        let mut name = tagged_name(&self.name, tags);
+        if !metrics.contains_key(&name) && metrics.len() >= MAX_TAG_COMBINATIONS {
+            let other_tags: Vec<(&str, &str)> = tags
+                .iter()
+                .map(|(key, _)| (*key, OTHER_TAG_VALUE))
+                .collect();
+            name = tagged_name(&self.name, &other_tags);
+        }
+        metrics
+            .entry(name)
+            .or_insert_with_key(|name| (self.define)(name.clone()))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_tagged_name() {
+        assert_eq!(
+            tagged_name(
+                "request_latency",
+                &[
+                    ("provider", "open-ai"),
+                    ("model", "gpt-4.1"),
+                    ("status", "200")
+                ]
+            ),
+            "request_latency;provider=open-ai;model=gpt-4.1;status=200"
+        );
+        assert_eq!(
+            tagged_name("cost", &[("model", "a;b=c d")]),
+            "cost;model=a_b_c_d"
+        );
+    }
+
+    #[test]
+    fn test_tagged_metrics_are_defined_once_per_combination() {
+        static DEFINED: AtomicUsize = AtomicUsize::new(0);
+        let metric = Tagged::new("ratelimited_rq", |name| {
+            DEFINED.fetch_add(1, Ordering::SeqCst);
+            name
+        });
+
+        assert_eq!(
+            metric.with_tags(&[("status", "200")]),
+            "ratelimited_rq;status=200"
+        );
+        metric.with_tags(&[("status", "200")]);
+        metric.with_tags(&[("status", String::from("429"))]);
+        assert_eq!(DEFINED.load(Ordering::SeqCst), 2);
+
+        for i in 2..MAX_TAG_COMBINATIONS {
+            metric.with_tags(&[("status", format!("status_{}", i))]);
+        }
+        assert_eq!(
+            metric.with_tags(&[("status", "503")]),
+            "ratelimited_rq;status=other"
+        );
+        // combinations defined before the limit was reached are still recorded as they are
+        assert_eq!(
+            metric.with_tags(&[("status", "429")]),
+            "ratelimited_rq;status=429"
+        );
+    }
+}
@@ -0,0 +1,316 @@
+//! Export of the spans the filters record to the OpenTelemetry collector.
+//!
+//! Http contexts push their spans to a bounded [`SpanQueue`] shared with the root context, which
+//! sends them on every tick as a single OTLP payload. One export is in flight at a time and a
+//! payload the collector failed to take is retried with exponential backoff.
+
+use crate::stats::{Counter, IncrementingMetric};
+use crate::tracing::{Span, TraceData};
+use log::warn;
+use std::collections::VecDeque;
+
+pub const MAX_QUEUED_SPANS: usize = 2048;
+pub const MAX_EXPORT_BATCH_SPANS: usize = 512;
+pub const MAX_EXPORT_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF_MS: u64 = 1000;
+const MAX_BACKOFF_MS: u64 = 30_000;
+
+#[derive(Debug)]
+pub struct SpanQueue {
+    spans: VecDeque<Span>,
+    capacity: usize,
+}
+
+impl Default for SpanQueue {
+    fn default() -> Self {
+        Self::new(MAX_QUEUED_SPANS)
+    }
+}
+
+impl SpanQueue {
+    pub fn new(capacity: usize) -> Self {
+        SpanQueue {
+            spans: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Queues the spans that fit, returns the number of spans dropped because the queue is full
+    pub fn push(&mut self, spans: impl IntoIterator<Item = Span>) -> usize {
+        let mut dropped = 0;
+        for span in spans {
+            if self.spans.len() < self.capacity {
+                self.spans.push_back(span);
+            } else {
+                dropped += 1;
+            }
+        }
+        dropped
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    fn take_batch(&mut self, max_spans: usize) -> Vec<Span> {
+        let count = max_spans.min(self.spans.len());
+        self.spans.drain(..count).collect()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ExportOutcome {
+    Exported,
+    /// The payload is sent again once the backoff has passed
+    Retrying {
+        attempts: u32,
+    },
+    /// The payload was given up on, its spans are lost
+    Dropped {
+        spans: usize,
+    },
+}
+
+#[derive(Debug)]
+struct PendingExport {
+    payload: String,
+    spans: usize,
+    attempts: u32,
+    retry_at_ms: u64,
+}
+
+#[derive(Debug)]
+pub struct TraceExporter {
+    service_name: String,
+    pending: Option<PendingExport>,
+    in_flight: bool,
+}
+
+impl TraceExporter {
+    pub fn new(service_name: &str) -> Self {
+        TraceExporter {
+            service_name: service_name.to_string(),
+            pending: None,
+            in_flight: false,
+        }
+    }
+
+    /// The payload to send now, if any. A payload that failed is retried once its backoff has
+    /// passed, before any new spans are taken from the queue.
+    pub fn next_export(&mut self, queue: &mut SpanQueue, now_ms: u64) -> Option<String> {
+        if self.in_flight {
+            return None;
+        }
+
+        if let Some(pending) = self.pending.as_ref() {
+            if now_ms < pending.retry_at_ms {
+                return None;
+            }
+        } else {
+            if queue.is_empty() {
+                return None;
+            }
+            let spans = queue.take_batch(MAX_EXPORT_BATCH_SPANS);
+            let span_count = spans.len();
+            let mut trace_data = TraceData::for_service(&self.service_name);
+            for span in spans {
+                trace_data.add_span(span);
+            }
+            self.pending = Some(PendingExport {
+                payload: serde_json::to_string(&trace_data).unwrap(),
+                spans: span_count,
+                attempts: 0,
+                retry_at_ms: now_ms,
+            });
+        }
+
+        self.in_flight = true;
+        self.pending.as_ref().map(|pending| pending.payload.clone())
+    }
+
+    /// Settles the export in flight with the status the collector responded with, `None` when
+    /// the call could not be made or got no response.
+    pub fn export_finished(&mut self, status: Option<u16>, now_ms: u64) -> ExportOutcome {
+        self.in_flight = false;
+        let mut pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return ExportOutcome::Exported,
+        };
+
+        match status {
+            Some(status) if (200..300).contains(&status) => ExportOutcome::Exported,
+            // the collector rejected the payload, sending it again would not change that
+            Some(status) if (400..500).contains(&status) && status != 408 && status != 429 => {
+                ExportOutcome::Dropped {
+                    spans: pending.spans,
+                }
+            }
+            _ => {
+                pending.attempts += 1;
+                if pending.attempts >= MAX_EXPORT_ATTEMPTS {
+                    return ExportOutcome::Dropped {
+                        spans: pending.spans,
+                    };
+                }
+                let backoff_ms = (INITIAL_BACKOFF_MS << (pending.attempts - 1)).min(MAX_BACKOFF_MS);
+                pending.retry_at_ms = now_ms + backoff_ms;
+                let attempts = pending.attempts;
+                self.pending = Some(pending);
+                ExportOutcome::Retrying { attempts }
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TraceExportMetrics {
+    pub exports_succeeded: Counter,
+    pub exports_failed: Counter,
+    pub dropped_spans: Counter,
+}
+
+impl TraceExportMetrics {
+    pub fn new() -> Self {
+        TraceExportMetrics {
+            exports_succeeded: Counter::new(String::from("trace_exports_succeeded")),
+            exports_failed: Counter::new(String::from("trace_exports_failed")),
+            dropped_spans: Counter::new(String::from("trace_spans_dropped")),
+        }
+    }
+
+    pub fn record(&self, outcome: &ExportOutcome) {
+        match outcome {
+            ExportOutcome::Exported => self.exports_succeeded.increment(1),
+            ExportOutcome::Retrying { attempts } => {
+                warn!("trace export failed, attempt {} will be retried", attempts);
+                self.exports_failed.increment(1);
+            }
+            ExportOutcome::Dropped { spans } => {
+                warn!("trace export failed, dropping {} spans", spans);
+                self.exports_failed.increment(1);
+                self.dropped_spans.increment(*spans as i64);
+            }
+        }
+    }
+}
+
+impl Default for TraceExportMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(count: usize) -> Vec<Span> {
+        (0..count)
+            .map(|i| Span::new(format!("span_{}", i), None, None, 0, 1))
+            .collect()
+    }
+
+    fn exported_spans(payload: &str) -> usize {
+        let payload: serde_json::Value = serde_json::from_str(payload).unwrap();
+        let resource_spans = payload["resourceSpans"].as_array().unwrap();
+        assert_eq!(resource_spans.len(), 1);
+        resource_spans[0]["scopeSpans"][0]["spans"]
+            .as_array()
+            .unwrap()
+            .len()
+    }
+
+    #[test]
+    fn test_queue_is_bounded() {
+        let mut queue = SpanQueue::new(3);
+        assert_eq!(queue.push(spans(2)), 0);
+        assert_eq!(queue.push(spans(2)), 1);
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn test_spans_are_batched_into_one_payload() {
+        let mut queue = SpanQueue::default();
+        queue.push(spans(MAX_EXPORT_BATCH_SPANS + 10));
+        let mut exporter = TraceExporter::new("egress_llm_traffic");
+
+        let payload = exporter.next_export(&mut queue, 0).unwrap();
+        assert_eq!(exported_spans(&payload), MAX_EXPORT_BATCH_SPANS);
+        assert!(payload.contains("egress_llm_traffic"));
+        // one export at a time
+        assert_eq!(exporter.next_export(&mut queue, 0), None);
+
+        assert_eq!(
+            exporter.export_finished(Some(200), 0),
+            ExportOutcome::Exported
+        );
+        let payload = exporter.next_export(&mut queue, 0).unwrap();
+        assert_eq!(exported_spans(&payload), 10);
+        exporter.export_finished(Some(200), 0);
+        assert_eq!(exporter.next_export(&mut queue, 0), None);
+    }
+
+    #[test]
+    fn test_failed_exports_are_retried_with_backoff() {
+        let mut queue = SpanQueue::default();
+        queue.push(spans(1));
+        let mut exporter = TraceExporter::new("egress_llm_traffic");
+
+        let payload = exporter.next_export(&mut queue, 0).unwrap();
+        assert_eq!(
+            exporter.export_finished(Some(503), 0),
+            ExportOutcome::Retrying { attempts: 1 }
+        );
+        queue.push(spans(1));
+        assert_eq!(exporter.next_export(&mut queue, 999), None);
+        assert_eq!(
+            exporter.next_export(&mut queue, 1000),
+            Some(payload.clone())
+        );
+
+        assert_eq!(
+            exporter.export_finished(None, 1000),
+            ExportOutcome::Retrying { attempts: 2 }
+        );
+        assert_eq!(exporter.next_export(&mut queue, 2999), None);
+        assert_eq!(exporter.next_export(&mut queue, 3000), Some(payload));
+        exporter.export_finished(Some(200), 3000);
+
+        // the span queued meanwhile goes out next
+        assert!(exporter.next_export(&mut queue, 3000).is_some());
+    }
+
+    #[test]
+    fn test_exports_are_given_up_on() {
+        let mut queue = SpanQueue::default();
+        let mut exporter = TraceExporter::new("egress_llm_traffic");
+
+        queue.push(spans(2));
+        exporter.next_export(&mut queue, 0).unwrap();
+        assert_eq!(
+            exporter.export_finished(Some(400), 0),
+            ExportOutcome::Dropped { spans: 2 }
+        );
+
+        queue.push(spans(1));
+        let mut now_ms = 0;
+        for attempts in 1..MAX_EXPORT_ATTEMPTS {
+            exporter.next_export(&mut queue, now_ms).unwrap();
+            assert_eq!(
+                exporter.export_finished(Some(500), now_ms),
+                ExportOutcome::Retrying { attempts }
+            );
+            now_ms += MAX_BACKOFF_MS;
+        }
+        exporter.next_export(&mut queue, now_ms).unwrap();
+        assert_eq!(
+            exporter.export_finished(Some(500), now_ms),
+            ExportOutcome::Dropped { spans: 1 }
+        );
+        assert_eq!(exporter.next_export(&mut queue, now_ms), None);
+    }
+}
@@ -1,6 +1,15 @@
-use rand::RngCore;
+use crate::configuration::Tracing;
+use rand::{Rng, RngCore};
 use serde::{Deserialize, Serialize};
 
+// https://opentelemetry.io/docs/specs/otel/trace/api/#spankind
+pub const SPAN_KIND_SERVER: u32 = 2;
+pub const SPAN_KIND_CLIENT: u32 = 3;
+
+// https://opentelemetry.io/docs/specs/otel/trace/api/#set-status
+pub const STATUS_CODE_OK: u32 = 1;
+pub const STATUS_CODE_ERROR: u32 = 2;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ResourceSpan {
     pub resource: Resource,
@@ -42,6 +51,15 @@ pub struct Span {
     pub kind: u32,
     pub attributes: Vec<Attribute>,
     pub events: Option<Vec<Event>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Status {
+    pub code: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
 }
 
 impl Span {
@@ -66,18 +84,54 @@ impl Span {
             kind: 0,
             attributes: Vec::new(),
             events: None,
+            status: None,
         }
     }
 
     pub fn add_attribute(&mut self, key: String, value: String) {
+        self.attributes.push(Attribute {
+            key,
+            value: AttributeValue::string(value),
+        });
+    }
+
+    pub fn add_int_attribute(&mut self, key: String, value: i64) {
         self.attributes.push(Attribute {
             key,
             value: AttributeValue {
-                string_value: Some(value),
+                // 64 bit integers are strings in the JSON encoding of OTLP
+                int_value: Some(value.to_string()),
+                ..Default::default()
             },
         });
     }
 
+    pub fn add_double_attribute(&mut self, key: String, value: f64) {
+        self.attributes.push(Attribute {
+            key,
+            value: AttributeValue {
+                double_value: Some(value),
+                ..Default::default()
+            },
+        });
+    }
+
+    pub fn add_string_array_attribute(&mut self, key: String, values: Vec<String>) {
+        self.attributes.push(Attribute {
+            key,
+            value: AttributeValue {
+                array_value: Some(ArrayValue {
+                    values: values.into_iter().map(AttributeValue::string).collect(),
+                }),
+                ..Default::default()
+            },
+        });
+    }
+
+    pub fn set_status(&mut self, code: u32, message: Option<String>) {
+        self.status = Some(Status { code, message });
+    }
+
     pub fn add_event(&mut self, event: Event) {
         if self.events.is_none() {
             self.events = Some(Vec::new());
@@ -85,7 +139,7 @@ impl Span {
         self.events.as_mut().unwrap().push(event);
     }
 
-    fn get_random_span_id() -> String {
+    pub fn get_random_span_id() -> String {
         let mut rng = rand::thread_rng();
         let mut random_bytes = [0u8; 8];
         rng.fill_bytes(&mut random_bytes);
@@ -93,7 +147,7 @@ impl Span {
         hex::encode(random_bytes)
     }
 
-    fn get_random_trace_id() -> String {
+    pub fn get_random_trace_id() -> String {
         let mut rng = rand::thread_rng();
         let mut random_bytes = [0u8; 16];
         rng.fill_bytes(&mut random_bytes);
@@ -122,9 +176,7 @@ impl Event {
     pub fn add_attribute(&mut self, key: String, value: String) {
         self.attributes.push(Attribute {
             key,
-            value: AttributeValue {
-                string_value: Some(value),
-            },
+            value: AttributeValue::string(value),
         });
     }
 }
@@ -135,16 +187,38 @@ pub struct Attribute {
     value: AttributeValue,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 struct AttributeValue {
-    #[serde(rename = "stringValue")]
+    #[serde(rename = "stringValue", skip_serializing_if = "Option::is_none")]
     string_value: Option<String>, // Use Option to handle different value types
+    #[serde(rename = "intValue", skip_serializing_if = "Option::is_none")]
+    int_value: Option<String>,
+    #[serde(rename = "doubleValue", skip_serializing_if = "Option::is_none")]
+    double_value: Option<f64>,
+    #[serde(rename = "arrayValue", skip_serializing_if = "Option::is_none")]
+    array_value: Option<ArrayValue>,
+}
+
+impl AttributeValue {
+    fn string(value: String) -> Self {
+        AttributeValue {
+            string_value: Some(value),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ArrayValue {
+    values: Vec<AttributeValue>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TraceData {
     #[serde(rename = "resourceSpans")]
     resource_spans: Vec<ResourceSpan>,
+    #[serde(skip)]
+    service_name: String,
 }
 
 impl Default for TraceData {
@@ -155,8 +229,13 @@ impl Default for TraceData {
 
 impl TraceData {
     pub fn new() -> Self {
+        Self::for_service("egress_llm_traffic")
+    }
+
+    pub fn for_service(service_name: &str) -> Self {
         TraceData {
             resource_spans: Vec::new(),
+            service_name: service_name.to_string(),
         }
     }
 
@@ -165,9 +244,7 @@ impl TraceData {
             let resource = Resource {
                 attributes: vec![Attribute {
                     key: "service.name".to_string(),
-                    value: AttributeValue {
-                        string_value: Some("egress_llm_traffic".to_string()),
-                    },
+                    value: AttributeValue::string(self.service_name.clone()),
                 }],
             };
             let scope_span = ScopeSpan {
@@ -195,6 +272,12 @@ pub struct Traceparent {
     pub flags: String,
 }
 
+impl Traceparent {
+    pub fn is_sampled(&self) -> bool {
+        u8::from_str_radix(&self.flags, 16).is_ok_and(|flags| flags & 0x01 == 0x01)
+    }
+}
+
 impl std::fmt::Display for Traceparent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -216,7 +299,12 @@ impl TryFrom<String> for Traceparent {
 
     fn try_from(traceparent: String) -> Result<Self, Self::Error> {
         let traceparent_tokens: Vec<&str> = traceparent.split("-").collect::<Vec<&str>>();
-        if traceparent_tokens.len() != 4 {
+        if traceparent_tokens.len() != 4
+            || !is_hex_id(traceparent_tokens[0], 2)
+            || !is_hex_id(traceparent_tokens[1], 32)
+            || !is_hex_id(traceparent_tokens[2], 16)
+            || !is_hex_id(traceparent_tokens[3], 2)
+        {
             return Err(TraceparentNewError::InvalidTraceparent(traceparent));
         }
         Ok(Traceparent {
@@ -227,3 +315,182 @@ impl TryFrom<String> for Traceparent {
         })
     }
 }
+
+// all zero trace and span ids are invalid, https://www.w3.org/TR/trace-context/#trace-id
+fn is_hex_id(id: &str, len: usize) -> bool {
+    id.len() == len
+        && id.bytes().all(|b| b.is_ascii_hexdigit())
+        && (len == 2 || id.bytes().any(|b| b != b'0'))
+}
+
+/// The place of a gateway's span in a trace. The trace of an incoming traceparent is continued
+/// with its sampling decision, without one a new trace is started when tracing is configured,
+/// sampled at `sampling_rate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub parent_span_id: Option<String>,
+    pub span_id: String,
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    pub fn new(traceparent: Option<&str>, tracing: Option<&Tracing>) -> Option<Self> {
+        let incoming = traceparent.and_then(|traceparent| {
+            Traceparent::try_from(traceparent.to_string())
+                .map_err(|e| log::warn!("starting a new trace, {}", e))
+                .ok()
+        });
+        match incoming {
+            Some(incoming) => Some(TraceContext {
+                sampled: incoming.is_sampled(),
+                trace_id: incoming.trace_id,
+                parent_span_id: Some(incoming.parent_id),
+                span_id: Span::get_random_span_id(),
+            }),
+            None => tracing.map(|tracing| TraceContext {
+                trace_id: Span::get_random_trace_id(),
+                parent_span_id: None,
+                span_id: Span::get_random_span_id(),
+                sampled: rand::thread_rng().gen::<f64>() < tracing.head_sampling_rate(),
+            }),
+        }
+    }
+
+    /// The traceparent to send upstream, calls made by the gateway are children of its span
+    pub fn traceparent(&self) -> Traceparent {
+        Traceparent {
+            version: "00".to_string(),
+            trace_id: self.trace_id.clone(),
+            parent_id: self.span_id.clone(),
+            flags: if self.sampled { "01" } else { "00" }.to_string(),
+        }
+    }
+
+    /// The gateway's own span
+    pub fn span(&self, name: String, start_time_unix_nano: u128, end_time_unix_nano: u128) -> Span {
+        let mut span = Span::new(
+            name,
+            Some(self.trace_id.clone()),
+            self.parent_span_id.clone(),
+            start_time_unix_nano,
+            end_time_unix_nano,
+        );
+        span.span_id = self.span_id.clone();
+        span
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    const TRACEPARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    #[test]
+    fn test_span_attributes_and_status_serialize_to_otlp_json() {
+        let mut span = Span::new("egress_traffic".to_string(), None, None, 1, 2);
+        span.add_attribute("gen_ai.system".to_string(), "openai".to_string());
+        span.add_int_attribute("gen_ai.usage.input_tokens".to_string(), 42);
+        span.add_double_attribute("gen_ai.request.temperature".to_string(), 0.5);
+        span.add_string_array_attribute(
+            "gen_ai.response.finish_reasons".to_string(),
+            vec!["stop".to_string()],
+        );
+
+        let value = serde_json::to_value(&span).unwrap();
+        assert_eq!(
+            value["attributes"],
+            json!([
+                {"key": "gen_ai.system", "value": {"stringValue": "openai"}},
+                {"key": "gen_ai.usage.input_tokens", "value": {"intValue": "42"}},
+                {"key": "gen_ai.request.temperature", "value": {"doubleValue": 0.5}},
+                {
+                    "key": "gen_ai.response.finish_reasons",
+                    "value": {"arrayValue": {"values": [{"stringValue": "stop"}]}}
+                },
+            ])
+        );
+        assert!(value.get("status").is_none());
+
+        span.set_status(STATUS_CODE_ERROR, Some("upstream error".to_string()));
+        let value = serde_json::to_value(&span).unwrap();
+        assert_eq!(
+            value["status"],
+            json!({"code": STATUS_CODE_ERROR, "message": "upstream error"})
+        );
+    }
+
+    #[test]
+    fn test_traceparent_validation() {
+        let traceparent = Traceparent::try_from(TRACEPARENT.to_string()).unwrap();
+        assert!(traceparent.is_sampled());
+        assert_eq!(traceparent.to_string(), TRACEPARENT);
+
+        for invalid in [
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b716920333-01",
+            "00-0af7651916cd43dd8448eb211c80319z-b7ad6b7169203331-01",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+        ] {
+            assert!(
+                Traceparent::try_from(invalid.to_string()).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_trace_context_continues_incoming_trace() {
+        let tracing = Tracing {
+            sampling_rate: Some(0.0),
+            random_sampling: None,
+            trace_arch_internal: None,
+        };
+        let context = TraceContext::new(Some(TRACEPARENT), Some(&tracing)).unwrap();
+        assert_eq!(context.trace_id, "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(context.parent_span_id.as_deref(), Some("b7ad6b7169203331"));
+        // the incoming sampling decision wins over the sampling rate
+        assert!(context.sampled);
+
+        let traceparent = context.traceparent();
+        assert_eq!(traceparent.trace_id, context.trace_id);
+        assert_eq!(traceparent.parent_id, context.span_id);
+        assert_eq!(traceparent.flags, "01");
+
+        let span = context.span("egress_traffic".to_string(), 1, 2);
+        assert_eq!(span.trace_id, context.trace_id);
+        assert_eq!(span.span_id, context.span_id);
+        assert_eq!(span.parent_span_id, context.parent_span_id);
+
+        let unsampled = TRACEPARENT.replace("-01", "-00");
+        assert!(!TraceContext::new(Some(&unsampled), None).unwrap().sampled);
+    }
+
+    #[test]
+    fn test_trace_context_starts_root_trace() {
+        assert_eq!(TraceContext::new(None, None), None);
+
+        let sample = |sampling_rate| {
+            let tracing = Tracing {
+                sampling_rate,
+                random_sampling: None,
+                trace_arch_internal: None,
+            };
+            TraceContext::new(Some("invalid"), Some(&tracing)).unwrap()
+        };
+        let context = sample(Some(1.0));
+        assert!(context.sampled);
+        assert_eq!(context.parent_span_id, None);
+        assert!(Traceparent::try_from(context.traceparent().to_string()).is_ok());
+
+        assert!(sample(None).sampled);
+        let context = sample(Some(0.0));
+        assert!(!context.sampled);
+        assert_eq!(context.traceparent().flags, "00");
+    }
+}
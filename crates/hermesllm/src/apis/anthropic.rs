@@ -135,7 +135,12 @@ pub enum MessagesContentBlock {
         text: String,
     },
     Thinking {
+        #[serde(rename = "thinking", alias = "text")]
         text: String,
+        signature: Option<String>,
+    },
+    RedactedThinking {
+        data: String,
     },
     Image {
         source: MessagesImageSource,
@@ -272,8 +277,10 @@ pub enum MessagesStopReason {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct MessagesUsage {
+    // message_delta events of a stream only carry the output tokens
+    #[serde(default)]
     pub input_tokens: u32,
     pub output_tokens: u32,
     pub cache_creation_input_tokens: Option<u32>,
@@ -353,6 +360,10 @@ pub enum MessagesContentDelta {
     TextDelta { text: String },
     #[serde(rename = "input_json_delta")]
     InputJsonDelta { partial_json: String },
+    #[serde(rename = "thinking_delta")]
+    ThinkingDelta { thinking: String },
+    #[serde(rename = "signature_delta")]
+    SignatureDelta { signature: String },
 }
 
 #[skip_serializing_none]
@@ -548,7 +559,7 @@ mod tests {
                     "content": [
                         {
                             "type": "thinking",
-                            "text": "Let me analyze the image and then check the weather..."
+                            "thinking": "Let me analyze the image and then check the weather..."
                         },
                         {
                             "type": "text",
@@ -635,7 +646,7 @@ mod tests {
             assert_eq!(content_blocks.len(), 3);
 
             // Validate thinking content block
-            if let MessagesContentBlock::Thinking { text } = &content_blocks[0] {
+            if let MessagesContentBlock::Thinking { text, .. } = &content_blocks[0] {
                 assert_eq!(text, "Let me analyze the image and then check the weather...");
             } else {
                 panic!("Expected thinking content block");
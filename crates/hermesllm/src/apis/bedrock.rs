@@ -0,0 +1,466 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::skip_serializing_none;
+use std::collections::HashMap;
+
+use super::ApiDefinition;
+
+// Enum for all supported Bedrock runtime APIs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BedrockApi {
+    Converse,
+    ConverseStream,
+}
+
+const MODEL_PREFIX: &str = "/model/";
+
+impl BedrockApi {
+    /// The path to call the API on, Bedrock paths carry the model unlike the endpoint templates.
+    /// Model ids and inference profile ARNs contain `:` and `/`, so the model is percent-encoded.
+    pub fn path(&self, model: &str) -> String {
+        let model = percent_encode(model);
+        match self {
+            BedrockApi::Converse => format!("{}{}/converse", MODEL_PREFIX, model),
+            BedrockApi::ConverseStream => format!("{}{}/converse-stream", MODEL_PREFIX, model),
+        }
+    }
+
+    pub fn for_stream(stream: bool) -> Self {
+        if stream {
+            BedrockApi::ConverseStream
+        } else {
+            BedrockApi::Converse
+        }
+    }
+}
+
+impl ApiDefinition for BedrockApi {
+    fn endpoint(&self) -> &'static str {
+        match self {
+            BedrockApi::Converse => "/model/{modelId}/converse",
+            BedrockApi::ConverseStream => "/model/{modelId}/converse-stream",
+        }
+    }
+
+    fn from_endpoint(endpoint: &str) -> Option<Self> {
+        let (model, method) = endpoint.strip_prefix(MODEL_PREFIX)?.rsplit_once('/')?;
+        if model.is_empty() {
+            return None;
+        }
+        match method {
+            "converse" => Some(BedrockApi::Converse),
+            "converse-stream" => Some(BedrockApi::ConverseStream),
+            _ => None,
+        }
+    }
+
+    fn supports_streaming(&self) -> bool {
+        match self {
+            BedrockApi::Converse => false,
+            BedrockApi::ConverseStream => true,
+        }
+    }
+
+    fn supports_tools(&self) -> bool {
+        match self {
+            BedrockApi::Converse | BedrockApi::ConverseStream => true,
+        }
+    }
+
+    fn supports_vision(&self) -> bool {
+        match self {
+            BedrockApi::Converse | BedrockApi::ConverseStream => true,
+        }
+    }
+
+    fn all_variants() -> Vec<Self> {
+        vec![BedrockApi::Converse, BedrockApi::ConverseStream]
+    }
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986
+fn percent_encode(value: &str) -> String {
+    value.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte),
+    }).collect()
+}
+
+// The model is part of the path, not the body
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConverseRequest {
+    pub messages: Vec<BedrockMessage>,
+    pub system: Option<Vec<BedrockSystemContentBlock>>,
+    pub inference_config: Option<BedrockInferenceConfig>,
+    pub tool_config: Option<BedrockToolConfig>,
+    pub guardrail_config: Option<Value>,
+    pub additional_model_request_fields: Option<Value>,
+    pub additional_model_response_field_paths: Option<Vec<String>>,
+    pub request_metadata: Option<HashMap<String, String>>,
+    pub performance_config: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BedrockRole {
+    User,
+    Assistant,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BedrockMessage {
+    pub role: BedrockRole,
+    pub content: Vec<BedrockContentBlock>,
+}
+
+// A content block holds exactly one of its fields
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockContentBlock {
+    pub text: Option<String>,
+    pub image: Option<BedrockImageBlock>,
+    pub document: Option<Value>,
+    pub tool_use: Option<BedrockToolUseBlock>,
+    pub tool_result: Option<BedrockToolResultBlock>,
+    pub reasoning_content: Option<BedrockReasoningContentBlock>,
+    pub guard_content: Option<Value>,
+    pub cache_point: Option<Value>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockSystemContentBlock {
+    pub text: Option<String>,
+    pub guard_content: Option<Value>,
+    pub cache_point: Option<Value>,
+}
+
+// `format` is the image type without the `image/` prefix, e.g. png or jpeg
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BedrockImageBlock {
+    pub format: String,
+    pub source: BedrockImageSource,
+}
+
+// `bytes` is base64 encoded in the JSON protocol
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockImageSource {
+    pub bytes: Option<String>,
+    pub s3_location: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockToolUseBlock {
+    pub tool_use_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub input: Value,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockToolResultBlock {
+    pub tool_use_id: String,
+    pub content: Vec<BedrockToolResultContentBlock>,
+    pub status: Option<BedrockToolResultStatus>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BedrockToolResultContentBlock {
+    pub text: Option<String>,
+    pub json: Option<Value>,
+    pub image: Option<BedrockImageBlock>,
+    pub document: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BedrockToolResultStatus {
+    Success,
+    Error,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockReasoningContentBlock {
+    pub reasoning_text: Option<BedrockReasoningText>,
+    pub redacted_content: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BedrockReasoningText {
+    pub text: String,
+    pub signature: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockInferenceConfig {
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub stop_sequences: Option<Vec<String>>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockToolConfig {
+    pub tools: Vec<BedrockTool>,
+    pub tool_choice: Option<BedrockToolChoice>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockTool {
+    pub tool_spec: Option<BedrockToolSpecification>,
+    pub cache_point: Option<Value>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockToolSpecification {
+    pub name: String,
+    pub description: Option<String>,
+    pub input_schema: BedrockToolInputSchema,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BedrockToolInputSchema {
+    pub json: Value,
+}
+
+// Bedrock has no way to forbid tool use, `none` is expressed by leaving the tools out
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BedrockToolChoice {
+    Auto {},
+    Any {},
+    Tool { name: String },
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConverseResponse {
+    pub output: BedrockConverseOutput,
+    pub stop_reason: BedrockStopReason,
+    #[serde(default)]
+    pub usage: BedrockTokenUsage,
+    pub metrics: Option<Value>,
+    pub additional_model_response_fields: Option<Value>,
+    pub trace: Option<Value>,
+    pub performance_config: Option<Value>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BedrockConverseOutput {
+    pub message: Option<BedrockMessage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BedrockStopReason {
+    EndTurn,
+    ToolUse,
+    MaxTokens,
+    StopSequence,
+    GuardrailIntervened,
+    ContentFiltered,
+    #[serde(other)]
+    Other,
+}
+
+// Input tokens exclude the tokens read from and written to the prompt cache
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BedrockTokenUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub total_tokens: u32,
+    pub cache_read_input_tokens: Option<u32>,
+    pub cache_write_input_tokens: Option<u32>,
+}
+
+// ConverseStream sends each of these as the payload of an event named after it, e.g. the
+// `contentBlockDelta` event carries a BedrockContentBlockDeltaEvent
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BedrockMessageStartEvent {
+    pub role: BedrockRole,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockContentBlockStartEvent {
+    pub start: BedrockContentBlockStart,
+    pub content_block_index: u32,
+}
+
+// Only tool use blocks announce their start
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockContentBlockStart {
+    pub tool_use: Option<BedrockToolUseBlockStart>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockToolUseBlockStart {
+    pub tool_use_id: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockContentBlockDeltaEvent {
+    pub delta: BedrockContentBlockDelta,
+    pub content_block_index: u32,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockContentBlockDelta {
+    pub text: Option<String>,
+    pub tool_use: Option<BedrockToolUseBlockDelta>,
+    pub reasoning_content: Option<BedrockReasoningContentBlockDelta>,
+}
+
+// The input is a fragment of the tool's JSON arguments
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BedrockToolUseBlockDelta {
+    pub input: String,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockReasoningContentBlockDelta {
+    pub text: Option<String>,
+    pub signature: Option<String>,
+    pub redacted_content: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockContentBlockStopEvent {
+    pub content_block_index: u32,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BedrockMessageStopEvent {
+    pub stop_reason: BedrockStopReason,
+    pub additional_model_response_fields: Option<Value>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BedrockMetadataEvent {
+    #[serde(default)]
+    pub usage: BedrockTokenUsage,
+    pub metrics: Option<Value>,
+    pub trace: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bedrock_api_paths() {
+        assert_eq!(
+            BedrockApi::Converse.path("anthropic.claude-3-5-sonnet-20240620-v1:0"),
+            "/model/anthropic.claude-3-5-sonnet-20240620-v1%3A0/converse"
+        );
+        assert_eq!(
+            BedrockApi::ConverseStream.path("arn:aws:bedrock:us-east-1:123456789012:inference-profile/us.meta.llama3-2-1b-instruct-v1:0"),
+            "/model/arn%3Aaws%3Abedrock%3Aus-east-1%3A123456789012%3Ainference-profile%2Fus.meta.llama3-2-1b-instruct-v1%3A0/converse-stream"
+        );
+        assert_eq!(BedrockApi::from_endpoint("/model//converse"), None);
+        assert_eq!(BedrockApi::from_endpoint("/v1/chat/completions"), None);
+
+        for api in BedrockApi::all_variants() {
+            assert_eq!(BedrockApi::from_endpoint(&api.path("amazon.nova-pro-v1:0")), Some(api));
+        }
+    }
+
+    #[test]
+    fn test_converse_request_roundtrip() {
+        let original_json = json!({
+            "messages": [
+                {"role": "user", "content": [
+                    {"text": "What is the weather in Paris?"},
+                    {"image": {"format": "png", "source": {"bytes": "aGVsbG8="}}}
+                ]},
+                {"role": "assistant", "content": [{"toolUse": {"toolUseId": "tooluse_1", "name": "get_weather", "input": {"city": "Paris"}}}]},
+                {"role": "user", "content": [{"toolResult": {"toolUseId": "tooluse_1", "content": [{"json": {"temperature": 21}}], "status": "success"}}]}
+            ],
+            "system": [{"text": "You are helpful"}, {"cachePoint": {"type": "default"}}],
+            "inferenceConfig": {"maxTokens": 512, "temperature": 0.5, "stopSequences": ["END"]},
+            "toolConfig": {
+                "tools": [{"toolSpec": {"name": "get_weather", "inputSchema": {"json": {"type": "object"}}}}],
+                "toolChoice": {"tool": {"name": "get_weather"}}
+            }
+        });
+
+        let request: ConverseRequest = serde_json::from_value(original_json.clone()).unwrap();
+        assert_eq!(request.messages.len(), 3);
+        assert_eq!(request.messages[1].role, BedrockRole::Assistant);
+        assert_eq!(request.messages[1].content[0].tool_use.as_ref().unwrap().input, json!({"city": "Paris"}));
+        assert_eq!(
+            request.tool_config.as_ref().unwrap().tool_choice,
+            Some(BedrockToolChoice::Tool { name: "get_weather".to_string() })
+        );
+
+        assert_eq!(serde_json::to_value(&request).unwrap(), original_json);
+        assert_eq!(serde_json::to_value(BedrockToolChoice::Any {}).unwrap(), json!({"any": {}}));
+    }
+
+    #[test]
+    fn test_converse_response_deserialization() {
+        let response: ConverseResponse = serde_json::from_value(json!({
+            "output": {"message": {"role": "assistant", "content": [
+                {"reasoningContent": {"reasoningText": {"text": "The user wants weather", "signature": "sig"}}},
+                {"text": "Let me check."},
+                {"toolUse": {"toolUseId": "tooluse_1", "name": "get_weather", "input": {"city": "Paris"}}}
+            ]}},
+            "stopReason": "tool_use",
+            "usage": {"inputTokens": 20, "outputTokens": 30, "totalTokens": 50, "cacheReadInputTokens": 100},
+            "metrics": {"latencyMs": 812}
+        }))
+        .unwrap();
+
+        assert_eq!(response.stop_reason, BedrockStopReason::ToolUse);
+        assert_eq!(response.output.message.unwrap().content.len(), 3);
+        assert_eq!(response.usage.cache_read_input_tokens, Some(100));
+
+        let response: ConverseResponse = serde_json::from_value(json!({
+            "output": {"message": {"role": "assistant", "content": []}},
+            "stopReason": "malformed_model_output",
+            "usage": {"inputTokens": 20, "outputTokens": 0, "totalTokens": 20}
+        }))
+        .unwrap();
+        assert_eq!(response.stop_reason, BedrockStopReason::Other);
+    }
+}
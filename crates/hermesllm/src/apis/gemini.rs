@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::skip_serializing_none;
+
+use super::ApiDefinition;
+
+// Enum for all supported Gemini APIs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeminiApi {
+    GenerateContent,
+    StreamGenerateContent,
+}
+
+const MODELS_PREFIX: &str = "/v1beta/models/";
+
+impl GeminiApi {
+    /// The path to call the API on, Gemini paths carry the model unlike the endpoint templates
+    pub fn path(&self, model: &str) -> String {
+        match self {
+            GeminiApi::GenerateContent => format!("{}{}:generateContent", MODELS_PREFIX, model),
+            GeminiApi::StreamGenerateContent => {
+                format!("{}{}:streamGenerateContent?alt=sse", MODELS_PREFIX, model)
+            }
+        }
+    }
+
+    pub fn for_stream(stream: bool) -> Self {
+        if stream {
+            GeminiApi::StreamGenerateContent
+        } else {
+            GeminiApi::GenerateContent
+        }
+    }
+}
+
+impl ApiDefinition for GeminiApi {
+    fn endpoint(&self) -> &'static str {
+        match self {
+            GeminiApi::GenerateContent => "/v1beta/models/{model}:generateContent",
+            GeminiApi::StreamGenerateContent => "/v1beta/models/{model}:streamGenerateContent",
+        }
+    }
+
+    fn from_endpoint(endpoint: &str) -> Option<Self> {
+        let path = endpoint.split('?').next().unwrap_or_default();
+        let (model, method) = path.strip_prefix(MODELS_PREFIX)?.rsplit_once(':')?;
+        if model.is_empty() {
+            return None;
+        }
+        match method {
+            "generateContent" => Some(GeminiApi::GenerateContent),
+            "streamGenerateContent" => Some(GeminiApi::StreamGenerateContent),
+            _ => None,
+        }
+    }
+
+    fn supports_streaming(&self) -> bool {
+        match self {
+            GeminiApi::GenerateContent => false,
+            GeminiApi::StreamGenerateContent => true,
+        }
+    }
+
+    fn supports_tools(&self) -> bool {
+        match self {
+            GeminiApi::GenerateContent | GeminiApi::StreamGenerateContent => true,
+        }
+    }
+
+    fn supports_vision(&self) -> bool {
+        match self {
+            GeminiApi::GenerateContent | GeminiApi::StreamGenerateContent => true,
+        }
+    }
+
+    fn all_variants() -> Vec<Self> {
+        vec![
+            GeminiApi::GenerateContent,
+            GeminiApi::StreamGenerateContent,
+        ]
+    }
+}
+
+// The model is part of the path, not the body
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateContentRequest {
+    pub contents: Vec<GeminiContent>,
+    pub system_instruction: Option<GeminiContent>,
+    pub tools: Option<Vec<GeminiTool>>,
+    pub tool_config: Option<GeminiToolConfig>,
+    pub safety_settings: Option<Vec<GeminiSafetySetting>>,
+    pub generation_config: Option<GeminiGenerationConfig>,
+    pub cached_content: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GeminiRole {
+    User,
+    Model,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GeminiContent {
+    pub role: Option<GeminiRole>,
+    #[serde(default)]
+    pub parts: Vec<GeminiPart>,
+}
+
+// A part holds exactly one of its data fields, `thought` marks the text as the model's reasoning
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiPart {
+    pub text: Option<String>,
+    pub inline_data: Option<GeminiBlob>,
+    pub file_data: Option<GeminiFileData>,
+    pub function_call: Option<GeminiFunctionCall>,
+    pub function_response: Option<GeminiFunctionResponse>,
+    pub thought: Option<bool>,
+    pub thought_signature: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiBlob {
+    pub mime_type: String,
+    pub data: String,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiFileData {
+    pub mime_type: Option<String>,
+    pub file_uri: String,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GeminiFunctionCall {
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GeminiFunctionResponse {
+    pub id: Option<String>,
+    pub name: String,
+    pub response: Value,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiTool {
+    pub function_declarations: Option<Vec<GeminiFunctionDeclaration>>,
+    pub google_search: Option<Value>,
+    pub code_execution: Option<Value>,
+}
+
+// `parameters` takes an OpenAPI schema subset, `parameters_json_schema` takes any JSON schema
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiFunctionDeclaration {
+    pub name: String,
+    pub description: Option<String>,
+    pub parameters: Option<Value>,
+    pub parameters_json_schema: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiToolConfig {
+    pub function_calling_config: GeminiFunctionCallingConfig,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiFunctionCallingConfig {
+    pub mode: GeminiFunctionCallingMode,
+    pub allowed_function_names: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GeminiFunctionCallingMode {
+    Auto,
+    Any,
+    None,
+    Validated,
+}
+
+// Categories and thresholds are kept as strings, Google adds new ones regularly
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GeminiSafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiGenerationConfig {
+    pub stop_sequences: Option<Vec<String>>,
+    pub response_mime_type: Option<String>,
+    pub response_schema: Option<Value>,
+    pub response_json_schema: Option<Value>,
+    pub candidate_count: Option<u32>,
+    pub max_output_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub seed: Option<i32>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub thinking_config: Option<GeminiThinkingConfig>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiThinkingConfig {
+    pub include_thoughts: Option<bool>,
+    pub thinking_budget: Option<i32>,
+}
+
+// Both the whole response and every chunk of a stream
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateContentResponse {
+    #[serde(default)]
+    pub candidates: Vec<GeminiCandidate>,
+    pub prompt_feedback: Option<Value>,
+    pub usage_metadata: Option<GeminiUsageMetadata>,
+    pub model_version: Option<String>,
+    pub response_id: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiCandidate {
+    pub content: Option<GeminiContent>,
+    pub finish_reason: Option<GeminiFinishReason>,
+    pub index: Option<u32>,
+    pub safety_ratings: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GeminiFinishReason {
+    Stop,
+    MaxTokens,
+    Safety,
+    Recitation,
+    Language,
+    Blocklist,
+    ProhibitedContent,
+    Spii,
+    MalformedFunctionCall,
+    #[serde(other)]
+    Other,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GeminiUsageMetadata {
+    pub prompt_token_count: u32,
+    pub candidates_token_count: Option<u32>,
+    pub cached_content_token_count: Option<u32>,
+    pub thoughts_token_count: Option<u32>,
+    pub total_token_count: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_gemini_api_from_endpoint() {
+        assert_eq!(
+            GeminiApi::from_endpoint("/v1beta/models/gemini-2.5-flash:generateContent"),
+            Some(GeminiApi::GenerateContent)
+        );
+        assert_eq!(
+            GeminiApi::from_endpoint("/v1beta/models/gemini-2.5-flash:streamGenerateContent?alt=sse"),
+            Some(GeminiApi::StreamGenerateContent)
+        );
+        assert_eq!(GeminiApi::from_endpoint("/v1beta/models/:generateContent"), None);
+        assert_eq!(GeminiApi::from_endpoint("/v1beta/models/gemini-2.5-flash:embedContent"), None);
+        assert_eq!(GeminiApi::from_endpoint("/v1/chat/completions"), None);
+
+        for api in GeminiApi::all_variants() {
+            assert_eq!(GeminiApi::from_endpoint(&api.path("gemini-2.5-flash")), Some(api));
+        }
+    }
+
+    #[test]
+    fn test_gemini_request_roundtrip() {
+        let original_json = json!({
+            "contents": [
+                {"role": "user", "parts": [{"text": "What is the weather in Paris?"}]},
+                {"role": "model", "parts": [{"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}, "thoughtSignature": "sig"}]},
+                {"role": "user", "parts": [{"functionResponse": {"name": "get_weather", "response": {"temperature": 21}}}]}
+            ],
+            "systemInstruction": {"parts": [{"text": "You are helpful"}]},
+            "tools": [{"functionDeclarations": [{"name": "get_weather", "parameters": {"type": "object"}}]}],
+            "toolConfig": {"functionCallingConfig": {"mode": "ANY", "allowedFunctionNames": ["get_weather"]}},
+            "safetySettings": [{"category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH"}],
+            "generationConfig": {"maxOutputTokens": 1024, "temperature": 0.5, "thinkingConfig": {"includeThoughts": true}}
+        });
+
+        let request: GenerateContentRequest = serde_json::from_value(original_json.clone()).unwrap();
+        assert_eq!(request.contents.len(), 3);
+        assert_eq!(request.contents[1].role, Some(GeminiRole::Model));
+        assert_eq!(
+            request.tool_config.as_ref().unwrap().function_calling_config.mode,
+            GeminiFunctionCallingMode::Any
+        );
+        assert_eq!(serde_json::to_value(&request).unwrap(), original_json);
+    }
+
+    #[test]
+    fn test_gemini_response_with_unknown_finish_reason() {
+        let response: GenerateContentResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "Hello"}]},
+                "finishReason": "IMAGE_SAFETY",
+                "index": 0
+            }],
+            "usageMetadata": {"promptTokenCount": 4, "candidatesTokenCount": 1, "totalTokenCount": 5},
+            "modelVersion": "gemini-2.5-flash"
+        }))
+        .unwrap();
+
+        assert_eq!(response.candidates[0].finish_reason, Some(GeminiFinishReason::Other));
+        assert_eq!(response.usage_metadata.unwrap().total_token_count, 5);
+    }
+}
@@ -1,8 +1,12 @@
 pub mod anthropic;
+pub mod bedrock;
+pub mod gemini;
 pub mod openai;
 
 // Re-export all types for convenience
 pub use anthropic::*;
+pub use bedrock::*;
+pub use gemini::*;
 pub use openai::*;
 
 /// Common trait that all API definitions must implement
@@ -144,6 +148,8 @@ mod tests {
 
         test_api(&OpenAIApi::ChatCompletions);
         test_api(&AnthropicApi::Messages);
+        test_api(&GeminiApi::GenerateContent);
+        test_api(&BedrockApi::Converse);
     }
 
     #[test]
@@ -178,8 +184,10 @@ mod tests {
     fn test_all_variants_method() {
         // Test that all_variants returns the expected variants
         let openai_variants = OpenAIApi::all_variants();
-        assert_eq!(openai_variants.len(), 1);
+        assert_eq!(openai_variants.len(), 3);
         assert!(openai_variants.contains(&OpenAIApi::ChatCompletions));
+        assert!(openai_variants.contains(&OpenAIApi::Embeddings));
+        assert!(openai_variants.contains(&OpenAIApi::Responses));
 
         let anthropic_variants = AnthropicApi::all_variants();
         assert_eq!(anthropic_variants.len(), 1);
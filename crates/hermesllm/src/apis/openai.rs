@@ -13,8 +13,9 @@ use super::ApiDefinition;
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OpenAIApi {
     ChatCompletions,
+    Embeddings,
+    Responses,
     // Future APIs can be added here:
-    // Embeddings,
     // FineTuning,
     // etc.
 }
@@ -23,12 +24,16 @@ impl ApiDefinition for OpenAIApi {
     fn endpoint(&self) -> &'static str {
         match self {
             OpenAIApi::ChatCompletions => "/v1/chat/completions",
+            OpenAIApi::Embeddings => "/v1/embeddings",
+            OpenAIApi::Responses => "/v1/responses",
         }
     }
 
     fn from_endpoint(endpoint: &str) -> Option<Self> {
         match endpoint {
             "/v1/chat/completions" => Some(OpenAIApi::ChatCompletions),
+            "/v1/embeddings" => Some(OpenAIApi::Embeddings),
+            "/v1/responses" => Some(OpenAIApi::Responses),
             _ => None,
         }
     }
@@ -36,24 +41,32 @@ impl ApiDefinition for OpenAIApi {
     fn supports_streaming(&self) -> bool {
         match self {
             OpenAIApi::ChatCompletions => true,
+            OpenAIApi::Embeddings => false,
+            OpenAIApi::Responses => true,
         }
     }
 
     fn supports_tools(&self) -> bool {
          match self {
             OpenAIApi::ChatCompletions => true,
+            OpenAIApi::Embeddings => false,
+            OpenAIApi::Responses => true,
         }
     }
 
     fn supports_vision(&self) -> bool {
         match self {
             OpenAIApi::ChatCompletions => true,
+            OpenAIApi::Embeddings => false,
+            OpenAIApi::Responses => true,
         }
     }
 
     fn all_variants() -> Vec<Self> {
         vec![
             OpenAIApi::ChatCompletions,
+            OpenAIApi::Embeddings,
+            OpenAIApi::Responses,
         ]
     }
 }
@@ -115,6 +128,8 @@ pub enum Role {
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Message {
+    /// Assistant messages that only call tools may have no content
+    #[serde(default)]
     pub content: MessageContent,
     pub role: Role,
     pub name: Option<String>,
@@ -122,9 +137,24 @@ pub struct Message {
     pub tool_calls: Option<Vec<ToolCall>>,
     /// ID of the tool call that this message is responding to (only present for tool role)
     pub tool_call_id: Option<String>,
+    /// Reasoning of a previous assistant turn, sent back to providers that require it
+    pub thinking_blocks: Option<Vec<ThinkingBlock>>,
 }
 
-
+/// A block of reasoning as Anthropic returns it, the signature lets the reasoning be sent back in
+/// the turns that follow
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ThinkingBlock {
+    Thinking {
+        thinking: String,
+        signature: Option<String>,
+    },
+    RedactedThinking {
+        data: String,
+    },
+}
 
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -142,6 +172,10 @@ pub struct ResponseMessage {
     pub function_call: Option<FunctionCall>,
     /// The tool calls generated by the model, such as function calls
     pub tool_calls: Option<Vec<ToolCall>>,
+    /// The model's reasoning as text, where OpenAI compatible clients read it from
+    pub reasoning_content: Option<String>,
+    /// The model's reasoning along with its signature, to be sent back in the turns that follow
+    pub thinking_blocks: Option<Vec<ThinkingBlock>>,
 }
 
 impl ResponseMessage {
@@ -156,6 +190,12 @@ impl ResponseMessage {
             name: None, // Response messages don't have names in the same way request messages do
             tool_calls: self.tool_calls.clone(),
             tool_call_id: None, // Response messages don't have tool_call_id
+            // reasoning without a signature, as openai compatible providers return it, is kept as is
+            thinking_blocks: self.thinking_blocks.clone().or_else(|| {
+                self.reasoning_content.as_ref().map(|reasoning| {
+                    vec![ThinkingBlock::Thinking { thinking: reasoning.clone(), signature: None }]
+                })
+            }),
         }
     }
 }
@@ -170,6 +210,12 @@ pub enum MessageContent {
     Parts(Vec<ContentPart>),
 }
 
+impl Default for MessageContent {
+    fn default() -> Self {
+        MessageContent::Text(String::new())
+    }
+}
+
 /// Individual content part within a message (text or image)
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
@@ -396,6 +442,10 @@ pub struct MessageDelta {
     /// Deprecated and replaced by tool_calls. The name and arguments of a function that should be called
     pub function_call: Option<FunctionCall>,
     pub tool_calls: Option<Vec<ToolCallDelta>>,
+    /// The model's reasoning as text
+    pub reasoning_content: Option<String>,
+    /// Signatures and redacted reasoning, which have no text to add to `reasoning_content`
+    pub thinking_blocks: Option<Vec<ThinkingBlock>>,
 }
 
 /// Tool call delta for streaming tool call updates
@@ -424,6 +474,422 @@ pub struct StreamOptions {
     pub include_usage: Option<bool>,
 }
 
+// ============================================================================
+// EMBEDDINGS API TYPES
+// ============================================================================
+
+/// Embeddings API request
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmbeddingsRequest {
+    pub input: EmbeddingInput,
+    pub model: String,
+    /// `float` or `base64`
+    pub encoding_format: Option<String>,
+    pub dimensions: Option<u32>,
+    pub user: Option<String>,
+}
+
+/// Text or tokens to embed, a single input or a batch of them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Text(String),
+    TextArray(Vec<String>),
+    Tokens(Vec<u32>),
+    TokenArrays(Vec<Vec<u32>>),
+}
+
+impl EmbeddingInput {
+    /// The texts to embed, empty when the input is already tokenized
+    pub fn texts(&self) -> Vec<&str> {
+        match self {
+            EmbeddingInput::Text(text) => vec![text.as_str()],
+            EmbeddingInput::TextArray(texts) => texts.iter().map(String::as_str).collect(),
+            EmbeddingInput::Tokens(_) | EmbeddingInput::TokenArrays(_) => vec![],
+        }
+    }
+
+    /// The number of tokens in an already tokenized input
+    pub fn token_count(&self) -> usize {
+        match self {
+            EmbeddingInput::Tokens(tokens) => tokens.len(),
+            EmbeddingInput::TokenArrays(arrays) => arrays.iter().map(Vec::len).sum(),
+            EmbeddingInput::Text(_) | EmbeddingInput::TextArray(_) => 0,
+        }
+    }
+}
+
+/// Embeddings API response
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmbeddingsResponse {
+    pub object: String,
+    pub data: Vec<Embedding>,
+    pub model: String,
+    pub usage: EmbeddingsUsage,
+}
+
+/// A single embedding in the response
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Embedding {
+    pub object: String,
+    pub embedding: EmbeddingVector,
+    pub index: u32,
+}
+
+/// The embedding, a list of floats or base64 encoded floats depending on the encoding format
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum EmbeddingVector {
+    Float(Vec<f32>),
+    Base64(String),
+}
+
+/// Token usage of an embeddings request, embeddings have no completion tokens
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmbeddingsUsage {
+    pub prompt_tokens: u32,
+    pub total_tokens: u32,
+}
+
+// ============================================================================
+// RESPONSES API TYPES
+// ============================================================================
+
+/// Responses API request
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResponsesRequest {
+    pub model: String,
+    pub input: ResponsesInput,
+    /// A system (or developer) message inserted ahead of the input
+    pub instructions: Option<String>,
+    pub max_output_tokens: Option<u32>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub parallel_tool_calls: Option<bool>,
+    /// Continues a conversation stored by the server, only possible against OpenAI itself
+    pub previous_response_id: Option<String>,
+    pub reasoning: Option<ResponsesReasoning>,
+    pub store: Option<bool>,
+    pub stream: Option<bool>,
+    pub temperature: Option<f32>,
+    pub text: Option<ResponsesTextConfig>,
+    pub tool_choice: Option<ResponsesToolChoice>,
+    pub tools: Option<Vec<ResponsesTool>>,
+    pub top_p: Option<f32>,
+    pub user: Option<String>,
+}
+
+/// The input of a response, a single user message or a list of conversation items
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ResponsesInput {
+    Text(String),
+    Items(Vec<ResponsesInputItem>),
+}
+
+/// An input item, messages may be sent without a `type`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ResponsesInputItem {
+    Message(ResponsesInputMessage),
+    Item(ResponsesItem),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResponsesInputMessage {
+    pub role: ResponsesRole,
+    pub content: ResponsesContent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponsesRole {
+    User,
+    Assistant,
+    System,
+    Developer,
+}
+
+/// Message content, text or a list of content parts
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ResponsesContent {
+    Text(String),
+    Parts(Vec<ResponsesContentPart>),
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponsesContentPart {
+    InputText {
+        text: String,
+    },
+    InputImage {
+        image_url: Option<String>,
+        file_id: Option<String>,
+        detail: Option<String>,
+    },
+    OutputText {
+        text: String,
+        #[serde(default)]
+        annotations: Vec<Value>,
+    },
+    Refusal {
+        refusal: String,
+    },
+}
+
+/// An item of the conversation, the output of a response or an item fed back as input
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponsesItem {
+    Message {
+        id: String,
+        role: ResponsesRole,
+        status: Option<ResponsesStatus>,
+        content: Vec<ResponsesContentPart>,
+    },
+    FunctionCall {
+        id: Option<String>,
+        call_id: String,
+        name: String,
+        arguments: String,
+        status: Option<ResponsesStatus>,
+    },
+    FunctionCallOutput {
+        id: Option<String>,
+        call_id: String,
+        output: String,
+    },
+    Reasoning {
+        id: String,
+        #[serde(default)]
+        summary: Vec<Value>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponsesStatus {
+    Queued,
+    InProgress,
+    Completed,
+    Incomplete,
+    Failed,
+    Cancelled,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResponsesReasoning {
+    /// `minimal`, `low`, `medium` or `high`
+    pub effort: Option<String>,
+    pub summary: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResponsesTextConfig {
+    /// `{"type": "text"}`, `{"type": "json_object"}` or a `json_schema` format with the schema inline
+    pub format: Option<Value>,
+}
+
+/// Tools a response may call, only function tools can be served by other providers
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponsesTool {
+    Function {
+        name: String,
+        description: Option<String>,
+        parameters: Option<Value>,
+        strict: Option<bool>,
+    },
+    /// Built-in tools such as web and file search
+    #[serde(other)]
+    BuiltIn,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ResponsesToolChoice {
+    Type(ToolChoiceType),
+    Function {
+        #[serde(rename = "type")]
+        choice_type: String,
+        name: String,
+    },
+}
+
+/// Responses API response
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResponsesResponse {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub status: ResponsesStatus,
+    pub model: String,
+    pub output: Vec<ResponsesItem>,
+    pub usage: Option<ResponsesUsage>,
+    pub incomplete_details: Option<ResponsesIncompleteDetails>,
+    pub error: Option<ResponsesError>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ResponsesUsage {
+    pub input_tokens: u32,
+    pub input_tokens_details: Option<ResponsesInputTokensDetails>,
+    pub output_tokens: u32,
+    pub output_tokens_details: Option<ResponsesOutputTokensDetails>,
+    pub total_tokens: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ResponsesInputTokensDetails {
+    pub cached_tokens: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ResponsesOutputTokensDetails {
+    pub reasoning_tokens: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ResponsesIncompleteDetails {
+    /// `max_output_tokens` or `content_filter`
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ResponsesError {
+    pub code: String,
+    pub message: String,
+}
+
+/// Server-sent events of a streamed response, every event is numbered in the order it is sent
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum ResponsesStreamEvent {
+    #[serde(rename = "response.created")]
+    Created {
+        response: ResponsesResponse,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.in_progress")]
+    InProgress {
+        response: ResponsesResponse,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.output_item.added")]
+    OutputItemAdded {
+        output_index: u32,
+        item: ResponsesItem,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.content_part.added")]
+    ContentPartAdded {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        part: ResponsesContentPart,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        delta: String,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.output_text.done")]
+    OutputTextDone {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        text: String,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.content_part.done")]
+    ContentPartDone {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        part: ResponsesContentPart,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.function_call_arguments.delta")]
+    FunctionCallArgumentsDelta {
+        item_id: String,
+        output_index: u32,
+        delta: String,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.function_call_arguments.done")]
+    FunctionCallArgumentsDone {
+        item_id: String,
+        output_index: u32,
+        arguments: String,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.output_item.done")]
+    OutputItemDone {
+        output_index: u32,
+        item: ResponsesItem,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.completed")]
+    Completed {
+        response: ResponsesResponse,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.incomplete")]
+    Incomplete {
+        response: ResponsesResponse,
+        sequence_number: u64,
+    },
+    #[serde(rename = "response.failed")]
+    Failed {
+        response: ResponsesResponse,
+        sequence_number: u64,
+    },
+    #[serde(rename = "error")]
+    Error {
+        code: Option<String>,
+        message: String,
+        param: Option<String>,
+        sequence_number: u64,
+    },
+}
+
+impl ResponsesStreamEvent {
+    /// The event's `type`, sent as the server-sent event name
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            ResponsesStreamEvent::Created { .. } => "response.created",
+            ResponsesStreamEvent::InProgress { .. } => "response.in_progress",
+            ResponsesStreamEvent::OutputItemAdded { .. } => "response.output_item.added",
+            ResponsesStreamEvent::ContentPartAdded { .. } => "response.content_part.added",
+            ResponsesStreamEvent::OutputTextDelta { .. } => "response.output_text.delta",
+            ResponsesStreamEvent::OutputTextDone { .. } => "response.output_text.done",
+            ResponsesStreamEvent::ContentPartDone { .. } => "response.content_part.done",
+            ResponsesStreamEvent::FunctionCallArgumentsDelta { .. } => "response.function_call_arguments.delta",
+            ResponsesStreamEvent::FunctionCallArgumentsDone { .. } => "response.function_call_arguments.done",
+            ResponsesStreamEvent::OutputItemDone { .. } => "response.output_item.done",
+            ResponsesStreamEvent::Completed { .. } => "response.completed",
+            ResponsesStreamEvent::Incomplete { .. } => "response.incomplete",
+            ResponsesStreamEvent::Failed { .. } => "response.failed",
+            ResponsesStreamEvent::Error { .. } => "error",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -732,12 +1198,17 @@ mod tests {
         let found_api = OpenAIApi::from_endpoint("/v1/chat/completions");
         assert_eq!(found_api, Some(OpenAIApi::ChatCompletions));
 
+        assert_eq!(OpenAIApi::from_endpoint("/v1/responses"), Some(OpenAIApi::Responses));
+        let found_api = OpenAIApi::from_endpoint("/v1/embeddings");
+        assert_eq!(found_api, Some(OpenAIApi::Embeddings));
+        assert!(!OpenAIApi::Embeddings.supports_streaming());
+
         let not_found = OpenAIApi::from_endpoint("/v1/unknown");
         assert_eq!(not_found, None);
 
         // Test all_variants
         let all_variants = OpenAIApi::all_variants();
-        assert_eq!(all_variants.len(), 1);
+        assert_eq!(all_variants.len(), 3);
         assert_eq!(all_variants[0], OpenAIApi::ChatCompletions);
     }
 
@@ -880,4 +1351,104 @@ mod tests {
         let invalid_result: Result<ToolChoice, _> = serde_json::from_value(json!("invalid"));
         assert!(invalid_result.is_err());
     }
+
+    #[test]
+    fn test_embeddings_request_inputs() {
+        let request: EmbeddingsRequest = serde_json::from_value(json!({
+            "model": "text-embedding-3-small",
+            "input": ["The food was delicious", "and the waiter"],
+            "dimensions": 256
+        }))
+        .unwrap();
+        assert_eq!(request.input.texts(), vec!["The food was delicious", "and the waiter"]);
+        assert_eq!(request.input.token_count(), 0);
+        assert_eq!(request.dimensions, Some(256));
+
+        let input: EmbeddingInput = serde_json::from_value(json!("hello")).unwrap();
+        assert_eq!(input, EmbeddingInput::Text("hello".to_string()));
+        let input: EmbeddingInput = serde_json::from_value(json!([[1, 2, 3], [4]])).unwrap();
+        assert!(input.texts().is_empty());
+        assert_eq!(input.token_count(), 4);
+
+        // optional fields are not sent upstream
+        let serialized = serde_json::to_value(&request).unwrap();
+        assert_eq!(serialized, json!({
+            "model": "text-embedding-3-small",
+            "input": ["The food was delicious", "and the waiter"],
+            "dimensions": 256
+        }));
+    }
+
+    #[test]
+    fn test_embeddings_response() {
+        let response: EmbeddingsResponse = serde_json::from_value(json!({
+            "object": "list",
+            "data": [
+                {"object": "embedding", "embedding": [0.0023, -0.0093], "index": 0},
+                {"object": "embedding", "embedding": "AACAPwAAAEA=", "index": 1}
+            ],
+            "model": "text-embedding-3-small",
+            "usage": {"prompt_tokens": 8, "total_tokens": 8}
+        }))
+        .unwrap();
+        assert_eq!(response.data[0].embedding, EmbeddingVector::Float(vec![0.0023, -0.0093]));
+        assert_eq!(response.data[1].embedding, EmbeddingVector::Base64("AACAPwAAAEA=".to_string()));
+        assert_eq!(response.usage.prompt_tokens, 8);
+    }
+
+    #[test]
+    fn test_responses_request_input_items() {
+        let request: ResponsesRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "instructions": "Be brief.",
+            "input": [
+                {"role": "user", "content": "What is the weather in Paris?"},
+                {"type": "message", "id": "msg_1", "role": "assistant", "status": "completed",
+                 "content": [{"type": "output_text", "text": "Let me check.", "annotations": []}]},
+                {"type": "function_call", "call_id": "call_1", "name": "get_weather", "arguments": "{\"city\":\"Paris\"}"},
+                {"type": "function_call_output", "call_id": "call_1", "output": "18C"}
+            ],
+            "tools": [
+                {"type": "function", "name": "get_weather", "parameters": {"type": "object"}},
+                {"type": "web_search_preview"}
+            ],
+            "tool_choice": {"type": "function", "name": "get_weather"}
+        }))
+        .unwrap();
+
+        let ResponsesInput::Items(items) = &request.input else {
+            panic!("expected input items");
+        };
+        assert!(matches!(&items[0], ResponsesInputItem::Message(message) if message.role == ResponsesRole::User));
+        assert!(matches!(&items[1], ResponsesInputItem::Message(message) if message.role == ResponsesRole::Assistant));
+        assert!(matches!(&items[2], ResponsesInputItem::Item(ResponsesItem::FunctionCall { call_id, .. }) if call_id == "call_1"));
+        assert!(matches!(&items[3], ResponsesInputItem::Item(ResponsesItem::FunctionCallOutput { output, .. }) if output == "18C"));
+        let tools = request.tools.unwrap();
+        assert!(matches!(&tools[0], ResponsesTool::Function { name, .. } if name == "get_weather"));
+        assert!(matches!(&tools[1], ResponsesTool::BuiltIn));
+        assert_eq!(request.tool_choice, Some(ResponsesToolChoice::Function {
+            choice_type: "function".to_string(),
+            name: "get_weather".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_responses_stream_event_serialization() {
+        let event = ResponsesStreamEvent::OutputTextDelta {
+            item_id: "msg_1".to_string(),
+            output_index: 0,
+            content_index: 0,
+            delta: "Hel".to_string(),
+            sequence_number: 3,
+        };
+        assert_eq!(event.event_type(), "response.output_text.delta");
+        assert_eq!(serde_json::to_value(&event).unwrap(), json!({
+            "type": "response.output_text.delta",
+            "item_id": "msg_1",
+            "output_index": 0,
+            "content_index": 0,
+            "delta": "Hel",
+            "sequence_number": 3
+        }));
+    }
 }
@@ -0,0 +1,589 @@
+//! Transformations between the OpenAI chat completions API and the Bedrock Converse API
+//!
+//! Bedrock carries the model in the request path rather than the body and leaves the id and
+//! model out of its responses, so translated responses have them empty for the caller to fill in.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::eventstream::{EventStreamDecoder, EventStreamMessage};
+use super::TransformError;
+use crate::apis::*;
+
+// ============================================================================
+// MAIN REQUEST TRANSFORMATIONS
+// ============================================================================
+
+impl TryFrom<ChatCompletionsRequest> for ConverseRequest {
+    type Error = TransformError;
+
+    fn try_from(req: ChatCompletionsRequest) -> Result<Self, Self::Error> {
+        let mut system = Vec::new();
+        let mut messages = Vec::new();
+
+        for message in req.messages {
+            match message.role {
+                Role::System => system.extend(content_to_blocks(message.content)?.into_iter().map(|block| {
+                    BedrockSystemContentBlock { text: block.text, guard_content: None, cache_point: None }
+                })),
+                Role::User => push_message(&mut messages, BedrockRole::User, content_to_blocks(message.content)?),
+                Role::Assistant => {
+                    let mut content = content_to_blocks(message.content)?;
+                    for tool_call in message.tool_calls.into_iter().flatten() {
+                        content.push(BedrockContentBlock {
+                            tool_use: Some(BedrockToolUseBlock {
+                                tool_use_id: tool_call.id,
+                                name: tool_call.function.name,
+                                input: parse_arguments(&tool_call.function.arguments)?,
+                            }),
+                            ..Default::default()
+                        });
+                    }
+                    push_message(&mut messages, BedrockRole::Assistant, content);
+                }
+                Role::Tool => {
+                    let tool_use_id = message.tool_call_id
+                        .ok_or_else(|| TransformError::MissingField("tool_call_id".to_string()))?;
+                    let block = BedrockContentBlock {
+                        tool_result: Some(BedrockToolResultBlock {
+                            tool_use_id,
+                            content: vec![tool_result_content(message.content)],
+                            status: None,
+                        }),
+                        ..Default::default()
+                    };
+                    push_message(&mut messages, BedrockRole::User, vec![block]);
+                }
+            }
+        }
+
+        // bedrock can't forbid tool use, so the tools are left out instead
+        let tool_config = match (req.tools, req.tool_choice) {
+            (_, Some(ToolChoice::Type(ToolChoiceType::None))) | (None, _) => None,
+            (Some(tools), tool_choice) => Some(BedrockToolConfig {
+                tools: tools.into_iter().map(|tool| BedrockTool {
+                    tool_spec: Some(BedrockToolSpecification {
+                        name: tool.function.name,
+                        description: tool.function.description,
+                        input_schema: BedrockToolInputSchema { json: tool.function.parameters },
+                    }),
+                    cache_point: None,
+                }).collect(),
+                tool_choice: tool_choice.and_then(convert_openai_tool_choice),
+            }),
+        };
+
+        Ok(ConverseRequest {
+            messages,
+            system: if system.is_empty() { None } else { Some(system) },
+            inference_config: Some(BedrockInferenceConfig {
+                max_tokens: req.max_completion_tokens.or(req.max_tokens),
+                temperature: req.temperature,
+                top_p: req.top_p,
+                stop_sequences: req.stop,
+            }),
+            tool_config,
+            ..Default::default()
+        })
+    }
+}
+
+// ============================================================================
+// MAIN RESPONSE TRANSFORMATIONS
+// ============================================================================
+
+impl TryFrom<ConverseResponse> for ChatCompletionsResponse {
+    type Error = TransformError;
+
+    fn try_from(resp: ConverseResponse) -> Result<Self, Self::Error> {
+        let content = resp.output.message.map(|message| message.content).unwrap_or_default();
+        let text: String = content.iter().filter_map(|block| block.text.as_deref()).collect();
+        let tool_calls: Vec<ToolCall> = content.into_iter()
+            .filter_map(|block| block.tool_use)
+            .map(|tool_use| ToolCall {
+                id: tool_use.tool_use_id,
+                call_type: "function".to_string(),
+                function: FunctionCall {
+                    name: tool_use.name,
+                    arguments: tool_use.input.to_string(),
+                },
+            })
+            .collect();
+
+        let choice = Choice {
+            index: 0,
+            finish_reason: Some(resp.stop_reason.into()),
+            message: ResponseMessage {
+                role: Role::Assistant,
+                content: if text.is_empty() { None } else { Some(text) },
+                refusal: None,
+                annotations: None,
+                audio: None,
+                function_call: None,
+                tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+                reasoning_content: None,
+                thinking_blocks: None,
+            },
+            logprobs: None,
+        };
+
+        Ok(ChatCompletionsResponse {
+            id: String::new(),
+            object: "chat.completion".to_string(),
+            created: current_timestamp(),
+            model: String::new(),
+            choices: vec![choice],
+            usage: resp.usage.into(),
+            system_fingerprint: None,
+        })
+    }
+}
+
+// ============================================================================
+// STANDARD RUST TRAIT IMPLEMENTATIONS
+// ============================================================================
+
+impl From<BedrockTokenUsage> for Usage {
+    fn from(usage: BedrockTokenUsage) -> Self {
+        // Bedrock's input tokens exclude the tokens read from and written to the prompt cache,
+        // OpenAI's prompt tokens include the cached ones
+        let cache_read_tokens = usage.cache_read_input_tokens.unwrap_or(0);
+        let prompt_tokens = usage.input_tokens
+            + cache_read_tokens
+            + usage.cache_write_input_tokens.unwrap_or(0);
+        Usage {
+            prompt_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: prompt_tokens + usage.output_tokens,
+            prompt_tokens_details: usage.cache_read_input_tokens.map(|_| PromptTokensDetails {
+                cached_tokens: Some(cache_read_tokens),
+                audio_tokens: None,
+            }),
+            completion_tokens_details: None,
+        }
+    }
+}
+
+impl From<BedrockStopReason> for FinishReason {
+    fn from(stop_reason: BedrockStopReason) -> Self {
+        match stop_reason {
+            BedrockStopReason::ToolUse => FinishReason::ToolCalls,
+            BedrockStopReason::MaxTokens => FinishReason::Length,
+            BedrockStopReason::GuardrailIntervened
+            | BedrockStopReason::ContentFiltered => FinishReason::ContentFilter,
+            BedrockStopReason::EndTurn
+            | BedrockStopReason::StopSequence
+            | BedrockStopReason::Other => FinishReason::Stop,
+        }
+    }
+}
+
+// ============================================================================
+// BODY TRANSLATION - Raw provider bodies to OpenAI bodies
+// ============================================================================
+
+/// Translates a whole Bedrock Converse response body into an OpenAI chat completions body
+pub fn translate_converse_response(body: &[u8]) -> Result<Vec<u8>, TransformError> {
+    let response: ConverseResponse = serde_json::from_slice(body)?;
+    let response: ChatCompletionsResponse = response.try_into()?;
+    Ok(serde_json::to_vec(&response)?)
+}
+
+/// Translates the `application/vnd.amazon.eventstream` messages of a Bedrock ConverseStream
+/// response into the server-sent events of OpenAI chat completion chunks. Exceptions raised
+/// mid-stream are sent on as OpenAI error events.
+#[derive(Debug, Default)]
+pub struct ConverseStreamTranslator {
+    decoder: EventStreamDecoder,
+    skipped_events: usize,
+    // the tool call index of each tool use content block
+    tool_calls: HashMap<u32, u32>,
+}
+
+impl ConverseStreamTranslator {
+    /// Translates the complete messages in `chunk`, a message split across chunks is held back
+    /// until the rest of it arrives. Messages that can't be translated are left out.
+    pub fn translate(&mut self, chunk: &[u8]) -> Result<Vec<u8>, TransformError> {
+        let mut translated = String::new();
+        for message in self.decoder.decode(chunk)? {
+            let translated_events = self.translate_message(message).unwrap_or_else(|_| {
+                self.skipped_events += 1;
+                Vec::new()
+            });
+            for data in translated_events {
+                translated.push_str("data: ");
+                translated.push_str(&data);
+                translated.push_str("\n\n");
+            }
+        }
+        Ok(translated.into_bytes())
+    }
+
+    /// Number of events left out since the last call because they could not be translated.
+    pub fn take_skipped_events(&mut self) -> usize {
+        std::mem::take(&mut self.skipped_events)
+    }
+
+    fn translate_message(&mut self, message: EventStreamMessage) -> Result<Vec<String>, TransformError> {
+        match message.header(":message-type") {
+            Some("exception") => {
+                let error: Value = serde_json::from_slice(&message.payload)?;
+                return Ok(vec![error_event(message.header(":exception-type"), error["message"].as_str())]);
+            }
+            Some("error") => {
+                return Ok(vec![error_event(message.header(":error-code"), message.header(":error-message"))]);
+            }
+            _ => {}
+        }
+
+        let mut delta = MessageDelta {
+            role: None,
+            content: None,
+            refusal: None,
+            function_call: None,
+            tool_calls: None,
+            reasoning_content: None,
+            thinking_blocks: None,
+        };
+        let mut finish_reason = None;
+        let mut usage = None;
+
+        match message.header(":event-type") {
+            Some("messageStart") => {
+                serde_json::from_slice::<BedrockMessageStartEvent>(&message.payload)?;
+                delta.role = Some(Role::Assistant);
+            }
+            Some("contentBlockStart") => {
+                let event: BedrockContentBlockStartEvent = serde_json::from_slice(&message.payload)?;
+                let Some(tool_use) = event.start.tool_use else {
+                    return Ok(vec![]);
+                };
+                let index = self.tool_calls.len() as u32;
+                self.tool_calls.insert(event.content_block_index, index);
+                delta.tool_calls = Some(vec![ToolCallDelta {
+                    index,
+                    id: Some(tool_use.tool_use_id),
+                    call_type: Some("function".to_string()),
+                    function: Some(FunctionCallDelta {
+                        name: Some(tool_use.name),
+                        arguments: Some(String::new()),
+                    }),
+                }]);
+            }
+            Some("contentBlockDelta") => {
+                let event: BedrockContentBlockDeltaEvent = serde_json::from_slice(&message.payload)?;
+                if let Some(text) = event.delta.text {
+                    delta.content = Some(text);
+                } else if let Some(tool_use) = event.delta.tool_use {
+                    let index = *self.tool_calls.get(&event.content_block_index).ok_or_else(|| {
+                        TransformError::MissingField("contentBlockStart for tool use".to_string())
+                    })?;
+                    delta.tool_calls = Some(vec![ToolCallDelta {
+                        index,
+                        id: None,
+                        call_type: None,
+                        function: Some(FunctionCallDelta { name: None, arguments: Some(tool_use.input) }),
+                    }]);
+                } else {
+                    // reasoning has no place in chat completions
+                    return Ok(vec![]);
+                }
+            }
+            Some("messageStop") => {
+                let event: BedrockMessageStopEvent = serde_json::from_slice(&message.payload)?;
+                finish_reason = Some(event.stop_reason.into());
+            }
+            Some("metadata") => {
+                let event: BedrockMetadataEvent = serde_json::from_slice(&message.payload)?;
+                usage = Some(event.usage.into());
+            }
+            _ => return Ok(vec![]),
+        }
+
+        let choices = if usage.is_some() {
+            vec![]
+        } else {
+            vec![StreamChoice { index: 0, delta, finish_reason, logprobs: None }]
+        };
+        let mut chunks = vec![serde_json::to_string(&ChatCompletionsStreamResponse {
+            id: String::new(),
+            object: "chat.completion.chunk".to_string(),
+            created: current_timestamp(),
+            model: String::new(),
+            choices,
+            usage: usage.clone(),
+            system_fingerprint: None,
+            service_tier: None,
+        })?];
+        // the metadata event ends the stream
+        if usage.is_some() {
+            chunks.push("[DONE]".to_string());
+        }
+        Ok(chunks)
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Helper to create a current unix timestamp
+fn current_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+/// An OpenAI error event for an exception raised mid-stream
+pub(super) fn error_event(error_type: Option<&str>, message: Option<&str>) -> String {
+    json!({
+        "error": {
+            "type": error_type.unwrap_or("unknown"),
+            "message": message.unwrap_or_default(),
+        }
+    })
+    .to_string()
+}
+
+/// Append content to the conversation, consecutive turns of a role are merged since bedrock
+/// requires the roles to alternate
+fn push_message(messages: &mut Vec<BedrockMessage>, role: BedrockRole, content: Vec<BedrockContentBlock>) {
+    if content.is_empty() {
+        return;
+    }
+    match messages.last_mut() {
+        Some(last) if last.role == role => last.content.extend(content),
+        _ => messages.push(BedrockMessage { role, content }),
+    }
+}
+
+/// Convert OpenAI message content to Bedrock content blocks, bedrock rejects blank text blocks
+fn content_to_blocks(content: MessageContent) -> Result<Vec<BedrockContentBlock>, TransformError> {
+    match content {
+        MessageContent::Text(text) if text.trim().is_empty() => Ok(vec![]),
+        MessageContent::Text(text) => Ok(vec![BedrockContentBlock { text: Some(text), ..Default::default() }]),
+        MessageContent::Parts(parts) => parts.into_iter().map(|part| match part {
+            ContentPart::Text { text } => Ok(BedrockContentBlock { text: Some(text), ..Default::default() }),
+            ContentPart::ImageUrl { image_url } => Ok(BedrockContentBlock {
+                image: Some(image_url_to_image(&image_url.url)?),
+                ..Default::default()
+            }),
+        }).collect(),
+    }
+}
+
+/// Bedrock only takes images inline or from S3, so only data URLs can be converted
+fn image_url_to_image(url: &str) -> Result<BedrockImageBlock, TransformError> {
+    let (mime_type, data) = url.strip_prefix("data:")
+        .and_then(|url| url.split_once(";base64,"))
+        .ok_or_else(|| TransformError::UnsupportedContent("image urls other than base64 data urls".to_string()))?;
+    Ok(BedrockImageBlock {
+        format: mime_type.trim_start_matches("image/").to_string(),
+        source: BedrockImageSource { bytes: Some(data.to_string()), s3_location: None },
+    })
+}
+
+/// Tool results that are JSON objects are sent as JSON, anything else as text
+fn tool_result_content(content: MessageContent) -> BedrockToolResultContentBlock {
+    let text = match content {
+        MessageContent::Text(text) => text,
+        MessageContent::Parts(parts) => parts.into_iter().filter_map(|part| match part {
+            ContentPart::Text { text } => Some(text),
+            ContentPart::ImageUrl { .. } => None,
+        }).collect(),
+    };
+    match serde_json::from_str::<Value>(&text) {
+        Ok(json @ Value::Object(_)) => BedrockToolResultContentBlock { json: Some(json), ..Default::default() },
+        _ => BedrockToolResultContentBlock { text: Some(text), ..Default::default() },
+    }
+}
+
+/// Parse OpenAI function call arguments, an empty string means no arguments
+fn parse_arguments(arguments: &str) -> Result<Value, TransformError> {
+    if arguments.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    serde_json::from_str(arguments).map_err(|_| TransformError::InvalidToolInput)
+}
+
+/// Convert OpenAI tool choice to Bedrock tool choice, `none` is handled by leaving the tools out
+fn convert_openai_tool_choice(tool_choice: ToolChoice) -> Option<BedrockToolChoice> {
+    match tool_choice {
+        ToolChoice::Type(ToolChoiceType::Auto) => Some(BedrockToolChoice::Auto {}),
+        ToolChoice::Type(ToolChoiceType::Required) => Some(BedrockToolChoice::Any {}),
+        ToolChoice::Type(ToolChoiceType::None) => None,
+        ToolChoice::Function { function, .. } => Some(BedrockToolChoice::Tool { name: function.name }),
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::eventstream::tests::encode_message;
+
+    fn event(event_type: &str, payload: Value) -> Vec<u8> {
+        encode_message(
+            &[(":event-type", event_type), (":content-type", "application/json"), (":message-type", "event")],
+            payload.to_string().as_bytes(),
+        )
+    }
+
+    fn data_events(translated: &[u8]) -> Vec<String> {
+        String::from_utf8(translated.to_vec()).unwrap()
+            .split("\n\n")
+            .filter_map(|chunk| chunk.strip_prefix("data: "))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_openai_to_converse_request_with_tools() {
+        let request: ChatCompletionsRequest = serde_json::from_value(json!({
+            "model": "anthropic.claude-3-5-sonnet-20240620-v1:0",
+            "messages": [
+                {"role": "system", "content": "You are helpful"},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is the weather here?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,aGVsbG8="}}
+                ]},
+                {"role": "assistant", "content": "", "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}},
+                    {"id": "call_2", "type": "function", "function": {"name": "get_time", "arguments": ""}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "{\"temperature\":21}"},
+                {"role": "tool", "tool_call_id": "call_2", "content": "noon"}
+            ],
+            "tools": [{"type": "function", "function": {"name": "get_weather", "parameters": {"type": "object"}}}],
+            "tool_choice": "required",
+            "max_tokens": 512,
+            "temperature": 0.5,
+            "stop": ["END"]
+        }))
+        .unwrap();
+
+        let request = ConverseRequest::try_from(request).unwrap();
+        let request = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(request["system"], json!([{"text": "You are helpful"}]));
+        assert_eq!(request["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(request["messages"][0]["content"][1]["image"], json!({"format": "png", "source": {"bytes": "aGVsbG8="}}));
+        // the blank assistant text is left out
+        assert_eq!(request["messages"][1]["content"], json!([
+            {"toolUse": {"toolUseId": "call_1", "name": "get_weather", "input": {"city": "Paris"}}},
+            {"toolUse": {"toolUseId": "call_2", "name": "get_time", "input": {}}}
+        ]));
+        // both results answer the same assistant turn so they share a user turn
+        assert_eq!(request["messages"][2], json!({"role": "user", "content": [
+            {"toolResult": {"toolUseId": "call_1", "content": [{"json": {"temperature": 21}}]}},
+            {"toolResult": {"toolUseId": "call_2", "content": [{"text": "noon"}]}}
+        ]}));
+        assert_eq!(request["toolConfig"], json!({
+            "tools": [{"toolSpec": {"name": "get_weather", "inputSchema": {"json": {"type": "object"}}}}],
+            "toolChoice": {"any": {}}
+        }));
+        assert_eq!(request["inferenceConfig"], json!({"maxTokens": 512, "temperature": 0.5, "stopSequences": ["END"]}));
+    }
+
+    #[test]
+    fn test_openai_to_converse_request_rejects_remote_images() {
+        let request: ChatCompletionsRequest = serde_json::from_value(json!({
+            "model": "amazon.nova-pro-v1:0",
+            "messages": [{"role": "user", "content": [
+                {"type": "image_url", "image_url": {"url": "https://example.com/cat.png"}}
+            ]}]
+        }))
+        .unwrap();
+
+        assert!(matches!(ConverseRequest::try_from(request), Err(TransformError::UnsupportedContent(_))));
+    }
+
+    #[test]
+    fn test_converse_to_openai_response() {
+        let body = json!({
+            "output": {"message": {"role": "assistant", "content": [
+                {"reasoningContent": {"reasoningText": {"text": "The user wants weather", "signature": "sig"}}},
+                {"text": "Let me check."},
+                {"toolUse": {"toolUseId": "tooluse_1", "name": "get_weather", "input": {"city": "Paris"}}}
+            ]}},
+            "stopReason": "tool_use",
+            "usage": {"inputTokens": 20, "outputTokens": 30, "totalTokens": 50, "cacheReadInputTokens": 100},
+            "metrics": {"latencyMs": 812}
+        });
+
+        let translated = translate_converse_response(body.to_string().as_bytes()).unwrap();
+        let translated: Value = serde_json::from_slice(&translated).unwrap();
+
+        assert_eq!(translated["choices"][0]["message"]["content"], "Let me check.");
+        assert_eq!(translated["choices"][0]["message"]["tool_calls"][0]["id"], "tooluse_1");
+        assert_eq!(translated["choices"][0]["message"]["tool_calls"][0]["function"]["arguments"], "{\"city\":\"Paris\"}");
+        assert_eq!(translated["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(translated["usage"]["prompt_tokens"], 120);
+        assert_eq!(translated["usage"]["completion_tokens"], 30);
+        assert_eq!(translated["usage"]["total_tokens"], 150);
+        assert_eq!(translated["usage"]["prompt_tokens_details"]["cached_tokens"], 100);
+    }
+
+    #[test]
+    fn test_converse_stream_translator() {
+        let stream = [
+            event("messageStart", json!({"role": "assistant", "p": "abcd"})),
+            event("contentBlockDelta", json!({"contentBlockIndex": 0, "delta": {"reasoningContent": {"text": "Hmm"}}})),
+            event("contentBlockDelta", json!({"contentBlockIndex": 1, "delta": {"text": "Hello"}})),
+            event("contentBlockDelta", json!({"contentBlockIndex": 1, "delta": {"text": " there"}})),
+            event("contentBlockStop", json!({"contentBlockIndex": 1})),
+            event("contentBlockStart", json!({"contentBlockIndex": 2, "start": {"toolUse": {"toolUseId": "tooluse_1", "name": "get_weather"}}})),
+            event("contentBlockDelta", json!({"contentBlockIndex": 2, "delta": {"toolUse": {"input": "{\"city\":"}}})),
+            event("contentBlockDelta", json!({"contentBlockIndex": 2, "delta": {"toolUse": {"input": "\"Paris\"}"}}})),
+            event("contentBlockStop", json!({"contentBlockIndex": 2})),
+            event("messageStop", json!({"stopReason": "tool_use"})),
+            event("metadata", json!({"usage": {"inputTokens": 8, "outputTokens": 12, "totalTokens": 20}, "metrics": {"latencyMs": 300}})),
+        ]
+        .concat();
+
+        let (first, second) = stream.split_at(stream.len() / 2);
+        let mut translator = ConverseStreamTranslator::default();
+        let mut translated = translator.translate(first).unwrap();
+        translated.extend(translator.translate(second).unwrap());
+
+        let events = data_events(&translated);
+        assert_eq!(events.len(), 9);
+        assert_eq!(events[8], "[DONE]");
+
+        let chunks: Vec<Value> = events[..8].iter().map(|chunk| serde_json::from_str(chunk).unwrap()).collect();
+        assert_eq!(chunks[0]["choices"][0]["delta"], json!({"role": "assistant"}));
+        assert_eq!(chunks[1]["choices"][0]["delta"], json!({"content": "Hello"}));
+        assert_eq!(chunks[2]["choices"][0]["delta"], json!({"content": " there"}));
+        assert_eq!(
+            chunks[3]["choices"][0]["delta"]["tool_calls"][0],
+            json!({"index": 0, "id": "tooluse_1", "type": "function", "function": {"name": "get_weather", "arguments": ""}})
+        );
+        assert_eq!(chunks[4]["choices"][0]["delta"]["tool_calls"][0], json!({"index": 0, "function": {"arguments": "{\"city\":"}}));
+        assert_eq!(chunks[5]["choices"][0]["delta"]["tool_calls"][0], json!({"index": 0, "function": {"arguments": "\"Paris\"}"}}));
+        assert_eq!(chunks[6]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(chunks[7]["choices"], json!([]));
+        assert_eq!(chunks[7]["usage"]["prompt_tokens"], 8);
+        assert_eq!(chunks[7]["usage"]["completion_tokens"], 12);
+    }
+
+    #[test]
+    fn test_converse_stream_translator_exception() {
+        let exception = encode_message(
+            &[(":exception-type", "throttlingException"), (":content-type", "application/json"), (":message-type", "exception")],
+            br#"{"message":"Too many requests, please wait before trying again."}"#,
+        );
+        let stream = [event("messageStart", json!({"role": "assistant"})), exception].concat();
+
+        let translated = ConverseStreamTranslator::default().translate(&stream).unwrap();
+        let events = data_events(&translated);
+
+        assert_eq!(events.len(), 2);
+        let error: Value = serde_json::from_str(&events[1]).unwrap();
+        assert_eq!(error, json!({"error": {
+            "type": "throttlingException",
+            "message": "Too many requests, please wait before trying again."
+        }}));
+    }
+}
@@ -11,16 +11,19 @@
 //! // Check if we support an endpoint
 //! assert!(is_supported_endpoint("/v1/chat/completions"));
 //! assert!(is_supported_endpoint("/v1/messages"));
+//! assert!(is_supported_endpoint("/v1/embeddings"));
+//! assert!(is_supported_endpoint("/v1beta/models/gemini-2.5-flash:generateContent"));
+//! assert!(is_supported_endpoint("/model/amazon.nova-pro-v1%3A0/converse"));
 //! assert!(!is_supported_endpoint("/v1/unknown"));
 //!
 //! // Get all supported endpoints
 //! let endpoints = supported_endpoints();
-//! assert_eq!(endpoints.len(), 2);
+//! assert_eq!(endpoints.len(), 8);
 //! assert!(endpoints.contains(&"/v1/chat/completions"));
 //! assert!(endpoints.contains(&"/v1/messages"));
 //! ```
 
-use crate::apis::{AnthropicApi, OpenAIApi, ApiDefinition};
+use crate::apis::{AnthropicApi, BedrockApi, GeminiApi, OpenAIApi, ApiDefinition};
 
 /// Check if the given endpoint path is supported
 pub fn is_supported_endpoint(endpoint: &str) -> bool {
@@ -34,6 +37,16 @@ pub fn is_supported_endpoint(endpoint: &str) -> bool {
         return true;
     }
 
+    // Try Gemini APIs
+    if GeminiApi::from_endpoint(endpoint).is_some() {
+        return true;
+    }
+
+    // Try Bedrock APIs
+    if BedrockApi::from_endpoint(endpoint).is_some() {
+        return true;
+    }
+
     false
 }
 
@@ -51,6 +64,16 @@ pub fn supported_endpoints() -> Vec<&'static str> {
         endpoints.push(api.endpoint());
     }
 
+    // Add all Gemini endpoints
+    for api in GeminiApi::all_variants() {
+        endpoints.push(api.endpoint());
+    }
+
+    // Add all Bedrock endpoints
+    for api in BedrockApi::all_variants() {
+        endpoints.push(api.endpoint());
+    }
+
     endpoints
 }
 
@@ -64,6 +87,14 @@ pub fn identify_provider(endpoint: &str) -> Option<&'static str> {
         return Some("anthropic");
     }
 
+    if GeminiApi::from_endpoint(endpoint).is_some() {
+        return Some("gemini");
+    }
+
+    if BedrockApi::from_endpoint(endpoint).is_some() {
+        return Some("bedrock");
+    }
+
     None
 }
 
@@ -75,10 +106,20 @@ mod tests {
     fn test_is_supported_endpoint() {
         // OpenAI endpoints
         assert!(is_supported_endpoint("/v1/chat/completions"));
+        assert!(is_supported_endpoint("/v1/embeddings"));
+        assert!(is_supported_endpoint("/v1/responses"));
 
         // Anthropic endpoints
         assert!(is_supported_endpoint("/v1/messages"));
 
+        // Gemini endpoints
+        assert!(is_supported_endpoint("/v1beta/models/gemini-2.5-flash:generateContent"));
+        assert!(is_supported_endpoint("/v1beta/models/gemini-2.5-flash:streamGenerateContent?alt=sse"));
+
+        // Bedrock endpoints
+        assert!(is_supported_endpoint("/model/amazon.nova-pro-v1%3A0/converse"));
+        assert!(is_supported_endpoint("/model/amazon.nova-pro-v1%3A0/converse-stream"));
+
         // Unsupported endpoints
         assert!(!is_supported_endpoint("/v1/unknown"));
         assert!(!is_supported_endpoint("/v2/chat"));
@@ -88,15 +129,22 @@ mod tests {
     #[test]
     fn test_supported_endpoints() {
         let endpoints = supported_endpoints();
-        assert_eq!(endpoints.len(), 2);
+        assert_eq!(endpoints.len(), 8);
         assert!(endpoints.contains(&"/v1/chat/completions"));
+        assert!(endpoints.contains(&"/v1/embeddings"));
+        assert!(endpoints.contains(&"/v1/responses"));
         assert!(endpoints.contains(&"/v1/messages"));
+        assert!(endpoints.contains(&"/v1beta/models/{model}:generateContent"));
+        assert!(endpoints.contains(&"/model/{modelId}/converse"));
     }
 
     #[test]
     fn test_identify_provider() {
         assert_eq!(identify_provider("/v1/chat/completions"), Some("openai"));
+        assert_eq!(identify_provider("/v1/embeddings"), Some("openai"));
         assert_eq!(identify_provider("/v1/messages"), Some("anthropic"));
+        assert_eq!(identify_provider("/v1beta/models/gemini-2.5-flash:generateContent"), Some("gemini"));
+        assert_eq!(identify_provider("/model/amazon.nova-pro-v1%3A0/converse"), Some("bedrock"));
         assert_eq!(identify_provider("/v1/unknown"), None);
     }
 
@@ -125,6 +173,10 @@ mod tests {
         }
 
         // Total should match
-        assert_eq!(endpoints.len(), OpenAIApi::all_variants().len() + AnthropicApi::all_variants().len());
+        assert_eq!(
+            endpoints.len(),
+            OpenAIApi::all_variants().len() + AnthropicApi::all_variants().len() + GeminiApi::all_variants().len()
+                + BedrockApi::all_variants().len()
+        );
     }
 }
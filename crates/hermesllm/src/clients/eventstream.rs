@@ -0,0 +1,248 @@
+//! Decoding of the `application/vnd.amazon.eventstream` binary framing AWS streams responses in
+//!
+//! Every message is framed as:
+//!
+//! ```text
+//! total length (u32) | headers length (u32) | prelude crc (u32) | headers | payload | message crc (u32)
+//! ```
+//!
+//! with big endian integers and CRC32 checksums over the bytes that precede them.
+
+use super::TransformError;
+
+const PRELUDE_LENGTH: usize = 12;
+const CRC_LENGTH: usize = 4;
+const MIN_MESSAGE_LENGTH: usize = PRELUDE_LENGTH + CRC_LENGTH;
+// AWS limits payloads to 16 MiB and headers to 128 KiB
+const MAX_MESSAGE_LENGTH: usize = 16 * 1024 * 1024 + 128 * 1024 + MIN_MESSAGE_LENGTH;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventStreamHeaderValue {
+    Bool(bool),
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Bytes(Vec<u8>),
+    String(String),
+    /// Milliseconds since the epoch
+    Timestamp(i64),
+    Uuid([u8; 16]),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventStreamMessage {
+    pub headers: Vec<(String, EventStreamHeaderValue)>,
+    pub payload: Vec<u8>,
+}
+
+impl EventStreamMessage {
+    /// The value of a string header such as `:event-type` or `:message-type`
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find_map(|(header, value)| match value {
+            EventStreamHeaderValue::String(value) if header == name => Some(value.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// Decodes the messages of a stream that arrives in arbitrary chunks
+#[derive(Debug, Default)]
+pub struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    /// Decodes the complete messages in `chunk`, a message split across chunks is held back until
+    /// the rest of it arrives.
+    pub fn decode(&mut self, chunk: &[u8]) -> Result<Vec<EventStreamMessage>, TransformError> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut messages = Vec::new();
+        let mut offset = 0;
+        while self.buffer.len() - offset >= PRELUDE_LENGTH {
+            let total_length = read_u32(&self.buffer[offset..]) as usize;
+            if !(MIN_MESSAGE_LENGTH..=MAX_MESSAGE_LENGTH).contains(&total_length) {
+                return Err(invalid(format!("message length {} is out of bounds", total_length)));
+            }
+            if self.buffer.len() - offset < total_length {
+                break;
+            }
+            messages.push(decode_message(&self.buffer[offset..offset + total_length])?);
+            offset += total_length;
+        }
+        self.buffer.drain(..offset);
+        Ok(messages)
+    }
+}
+
+fn decode_message(message: &[u8]) -> Result<EventStreamMessage, TransformError> {
+    let prelude_crc = read_u32(&message[8..]);
+    if crc32fast::hash(&message[..8]) != prelude_crc {
+        return Err(invalid("prelude checksum mismatch".to_string()));
+    }
+    let crc_offset = message.len() - CRC_LENGTH;
+    if crc32fast::hash(&message[..crc_offset]) != read_u32(&message[crc_offset..]) {
+        return Err(invalid("message checksum mismatch".to_string()));
+    }
+
+    let headers_length = read_u32(&message[4..]) as usize;
+    let headers_end = PRELUDE_LENGTH + headers_length;
+    if headers_end > crc_offset {
+        return Err(invalid(format!("headers length {} is out of bounds", headers_length)));
+    }
+
+    Ok(EventStreamMessage {
+        headers: decode_headers(&message[PRELUDE_LENGTH..headers_end])?,
+        payload: message[headers_end..crc_offset].to_vec(),
+    })
+}
+
+fn decode_headers(mut bytes: &[u8]) -> Result<Vec<(String, EventStreamHeaderValue)>, TransformError> {
+    let mut headers = Vec::new();
+    while !bytes.is_empty() {
+        let name_length = take(&mut bytes, 1)?[0] as usize;
+        let name = utf8(take(&mut bytes, name_length)?)?;
+        let value_type = take(&mut bytes, 1)?[0];
+        let value = match value_type {
+            0 => EventStreamHeaderValue::Bool(true),
+            1 => EventStreamHeaderValue::Bool(false),
+            2 => EventStreamHeaderValue::Byte(take(&mut bytes, 1)?[0] as i8),
+            3 => EventStreamHeaderValue::Short(i16::from_be_bytes(take_array(&mut bytes)?)),
+            4 => EventStreamHeaderValue::Int(i32::from_be_bytes(take_array(&mut bytes)?)),
+            5 => EventStreamHeaderValue::Long(i64::from_be_bytes(take_array(&mut bytes)?)),
+            6 | 7 => {
+                let length = u16::from_be_bytes(take_array(&mut bytes)?) as usize;
+                let value = take(&mut bytes, length)?;
+                if value_type == 6 {
+                    EventStreamHeaderValue::Bytes(value.to_vec())
+                } else {
+                    EventStreamHeaderValue::String(utf8(value)?)
+                }
+            }
+            8 => EventStreamHeaderValue::Timestamp(i64::from_be_bytes(take_array(&mut bytes)?)),
+            9 => EventStreamHeaderValue::Uuid(take_array(&mut bytes)?),
+            value_type => return Err(invalid(format!("unknown header value type {}", value_type))),
+        };
+        headers.push((name, value));
+    }
+    Ok(headers)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Splits the first `length` bytes off `bytes`
+fn take<'a>(bytes: &mut &'a [u8], length: usize) -> Result<&'a [u8], TransformError> {
+    if bytes.len() < length {
+        return Err(invalid("truncated header".to_string()));
+    }
+    let (taken, rest) = bytes.split_at(length);
+    *bytes = rest;
+    Ok(taken)
+}
+
+fn take_array<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], TransformError> {
+    let mut array = [0; N];
+    array.copy_from_slice(take(bytes, N)?);
+    Ok(array)
+}
+
+fn utf8(bytes: &[u8]) -> Result<String, TransformError> {
+    String::from_utf8(bytes.to_vec()).map_err(|e| invalid(e.to_string()))
+}
+
+fn invalid(reason: String) -> TransformError {
+    TransformError::InvalidEventStream(reason)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Frames a message with string headers the way AWS does
+    pub(crate) fn encode_message(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+        let mut encoded_headers = Vec::new();
+        for (name, value) in headers {
+            encoded_headers.push(name.len() as u8);
+            encoded_headers.extend_from_slice(name.as_bytes());
+            encoded_headers.push(7);
+            encoded_headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            encoded_headers.extend_from_slice(value.as_bytes());
+        }
+
+        let total_length = MIN_MESSAGE_LENGTH + encoded_headers.len() + payload.len();
+        let mut message = Vec::with_capacity(total_length);
+        message.extend_from_slice(&(total_length as u32).to_be_bytes());
+        message.extend_from_slice(&(encoded_headers.len() as u32).to_be_bytes());
+        message.extend_from_slice(&crc32fast::hash(&message).to_be_bytes());
+        message.extend_from_slice(&encoded_headers);
+        message.extend_from_slice(payload);
+        message.extend_from_slice(&crc32fast::hash(&message).to_be_bytes());
+        message
+    }
+
+    #[test]
+    fn test_decode_messages_split_across_chunks() {
+        let mut stream = encode_message(
+            &[(":event-type", "messageStart"), (":content-type", "application/json"), (":message-type", "event")],
+            br#"{"role":"assistant"}"#,
+        );
+        stream.extend(encode_message(&[(":event-type", "contentBlockStop")], br#"{"contentBlockIndex":0}"#));
+
+        let mut decoder = EventStreamDecoder::default();
+        let mut messages = Vec::new();
+        for chunk in stream.chunks(7) {
+            messages.extend(decoder.decode(chunk).unwrap());
+        }
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].header(":event-type"), Some("messageStart"));
+        assert_eq!(messages[0].header(":message-type"), Some("event"));
+        assert_eq!(messages[0].payload, br#"{"role":"assistant"}"#);
+        assert_eq!(messages[1].header(":event-type"), Some("contentBlockStop"));
+        assert_eq!(messages[1].header(":missing"), None);
+    }
+
+    #[test]
+    fn test_decode_typed_headers() {
+        // a message with no payload, a bool and a timestamp header
+        let headers = [
+            &[4][..], b"flag", &[0],
+            &[4], b"time", &[8], &1_440_938_160_000i64.to_be_bytes(),
+        ].concat();
+        let total_length = (MIN_MESSAGE_LENGTH + headers.len()) as u32;
+        let mut message = [total_length.to_be_bytes(), (headers.len() as u32).to_be_bytes()].concat();
+        message.extend_from_slice(&crc32fast::hash(&message).to_be_bytes());
+        message.extend_from_slice(&headers);
+        message.extend_from_slice(&crc32fast::hash(&message).to_be_bytes());
+
+        let messages = EventStreamDecoder::default().decode(&message).unwrap();
+        assert_eq!(messages[0].headers, vec![
+            ("flag".to_string(), EventStreamHeaderValue::Bool(true)),
+            ("time".to_string(), EventStreamHeaderValue::Timestamp(1_440_938_160_000)),
+        ]);
+        assert!(messages[0].payload.is_empty());
+    }
+
+    #[test]
+    fn test_decode_rejects_corrupt_messages() {
+        let message = encode_message(&[(":event-type", "messageStart")], br#"{"role":"assistant"}"#);
+
+        let mut corrupt_payload = message.clone();
+        let payload_byte = corrupt_payload.len() - 6;
+        corrupt_payload[payload_byte] ^= 1;
+        assert!(matches!(
+            EventStreamDecoder::default().decode(&corrupt_payload),
+            Err(TransformError::InvalidEventStream(_))
+        ));
+
+        let mut corrupt_prelude = message;
+        corrupt_prelude[5] ^= 1;
+        assert!(matches!(
+            EventStreamDecoder::default().decode(&corrupt_prelude),
+            Err(TransformError::InvalidEventStream(_))
+        ));
+    }
+}
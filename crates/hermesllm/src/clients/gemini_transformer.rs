@@ -0,0 +1,808 @@
+//! Transformations between the OpenAI chat completions API and the Gemini generateContent API
+//!
+//! Gemini carries the model in the request path rather than the body, so requests converted to
+//! OpenAI have an empty model for the caller to fill in.
+
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{SseDecoder, TransformError};
+use crate::apis::*;
+
+// ============================================================================
+// MAIN REQUEST TRANSFORMATIONS
+// ============================================================================
+
+impl TryFrom<ChatCompletionsRequest> for GenerateContentRequest {
+    type Error = TransformError;
+
+    fn try_from(req: ChatCompletionsRequest) -> Result<Self, Self::Error> {
+        let mut system_parts = Vec::new();
+        let mut contents = Vec::new();
+        // tool results only carry the id of the call they answer, gemini matches them by name
+        let mut tool_call_names = HashMap::new();
+
+        for message in req.messages {
+            match message.role {
+                Role::System => system_parts.extend(content_to_parts(message.content)),
+                Role::User => push_content(&mut contents, GeminiRole::User, content_to_parts(message.content)),
+                Role::Assistant => {
+                    let mut parts = content_to_parts(message.content);
+                    for tool_call in message.tool_calls.into_iter().flatten() {
+                        tool_call_names.insert(tool_call.id, tool_call.function.name.clone());
+                        parts.push(GeminiPart {
+                            function_call: Some(GeminiFunctionCall {
+                                id: None,
+                                name: tool_call.function.name,
+                                args: parse_arguments(&tool_call.function.arguments)?,
+                            }),
+                            ..Default::default()
+                        });
+                    }
+                    push_content(&mut contents, GeminiRole::Model, parts);
+                }
+                Role::Tool => {
+                    let tool_call_id = message.tool_call_id
+                        .ok_or_else(|| TransformError::MissingField("tool_call_id".to_string()))?;
+                    let name = tool_call_names.get(&tool_call_id).cloned()
+                        .or(message.name)
+                        .ok_or_else(|| TransformError::MissingField("tool call name".to_string()))?;
+                    let part = GeminiPart {
+                        function_response: Some(GeminiFunctionResponse {
+                            id: None,
+                            name,
+                            response: tool_result_to_response(message.content),
+                        }),
+                        ..Default::default()
+                    };
+                    push_content(&mut contents, GeminiRole::User, vec![part]);
+                }
+            }
+        }
+
+        let system_instruction = if system_parts.is_empty() {
+            None
+        } else {
+            Some(GeminiContent { role: None, parts: system_parts })
+        };
+
+        let tools = req.tools.map(|tools| {
+            vec![GeminiTool {
+                function_declarations: Some(tools.into_iter().map(|tool| GeminiFunctionDeclaration {
+                    name: tool.function.name,
+                    description: tool.function.description,
+                    parameters: None,
+                    parameters_json_schema: Some(tool.function.parameters),
+                }).collect()),
+                ..Default::default()
+            }]
+        });
+
+        let (response_mime_type, response_json_schema) = convert_response_format(req.response_format.as_ref());
+        let generation_config = GeminiGenerationConfig {
+            stop_sequences: req.stop,
+            response_mime_type,
+            response_schema: None,
+            response_json_schema,
+            candidate_count: req.n,
+            max_output_tokens: req.max_completion_tokens.or(req.max_tokens),
+            temperature: req.temperature,
+            top_p: req.top_p,
+            top_k: None,
+            seed: req.seed,
+            presence_penalty: req.presence_penalty,
+            frequency_penalty: req.frequency_penalty,
+            thinking_config: None,
+        };
+
+        Ok(GenerateContentRequest {
+            contents,
+            system_instruction,
+            tools,
+            tool_config: req.tool_choice.map(convert_openai_tool_choice),
+            safety_settings: None,
+            generation_config: Some(generation_config),
+            cached_content: None,
+        })
+    }
+}
+
+impl TryFrom<GenerateContentRequest> for ChatCompletionsRequest {
+    type Error = TransformError;
+
+    fn try_from(req: GenerateContentRequest) -> Result<Self, Self::Error> {
+        let mut messages = Vec::new();
+        if let Some(system_instruction) = req.system_instruction {
+            messages.push(Message {
+                role: Role::System,
+                content: MessageContent::Text(parts_text(&system_instruction.parts)),
+                name: None,
+                tool_calls: None,
+                tool_call_id: None,
+                thinking_blocks: None,
+            });
+        }
+
+        // calls without an id are answered in order, so their generated ids are queued by name
+        let mut pending_tool_calls: HashMap<String, VecDeque<String>> = HashMap::new();
+        let mut tool_call_count = 0;
+        for content in req.contents {
+            match content.role.unwrap_or(GeminiRole::User) {
+                GeminiRole::Model => {
+                    let mut tool_calls = Vec::new();
+                    for function_call in content.parts.iter().filter_map(|part| part.function_call.as_ref()) {
+                        let id = tool_call_id(function_call, tool_call_count);
+                        tool_call_count += 1;
+                        pending_tool_calls.entry(function_call.name.clone()).or_default().push_back(id.clone());
+                        tool_calls.push(ToolCall {
+                            id,
+                            call_type: "function".to_string(),
+                            function: FunctionCall {
+                                name: function_call.name.clone(),
+                                arguments: function_call.args.to_string(),
+                            },
+                        });
+                    }
+                    messages.push(Message {
+                        role: Role::Assistant,
+                        content: MessageContent::Text(parts_text(&content.parts)),
+                        name: None,
+                        tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+                        tool_call_id: None,
+                        thinking_blocks: None,
+                    });
+                }
+                GeminiRole::User => {
+                    let mut content_parts = Vec::new();
+                    for part in content.parts {
+                        if let Some(function_response) = part.function_response {
+                            let id = function_response.id.clone()
+                                .or_else(|| pending_tool_calls.get_mut(&function_response.name).and_then(|ids| ids.pop_front()))
+                                .unwrap_or_else(|| function_response.name.clone());
+                            messages.push(Message {
+                                role: Role::Tool,
+                                content: MessageContent::Text(function_response.response.to_string()),
+                                name: Some(function_response.name),
+                                tool_calls: None,
+                                tool_call_id: Some(id),
+                                thinking_blocks: None,
+                            });
+                        } else if let Some(content_part) = part_to_content_part(part) {
+                            content_parts.push(content_part);
+                        }
+                    }
+                    if !content_parts.is_empty() {
+                        messages.push(Message {
+                            role: Role::User,
+                            content: MessageContent::Parts(content_parts),
+                            name: None,
+                            tool_calls: None,
+                            tool_call_id: None,
+                            thinking_blocks: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        let tools: Vec<Tool> = req.tools.into_iter().flatten()
+            .flat_map(|tool| tool.function_declarations.unwrap_or_default())
+            .map(|declaration| Tool {
+                tool_type: "function".to_string(),
+                function: Function {
+                    name: declaration.name,
+                    description: declaration.description,
+                    parameters: declaration.parameters_json_schema
+                        .or(declaration.parameters)
+                        .unwrap_or_else(|| json!({"type": "object"})),
+                    strict: None,
+                },
+            })
+            .collect();
+
+        let config = req.generation_config.unwrap_or_default();
+        let response_format = match (config.response_mime_type.as_deref(), config.response_json_schema.or(config.response_schema)) {
+            (Some("application/json"), Some(schema)) => Some(json!({
+                "type": "json_schema",
+                "json_schema": {"name": "response", "schema": schema}
+            })),
+            (Some("application/json"), None) => Some(json!({"type": "json_object"})),
+            _ => None,
+        };
+
+        Ok(ChatCompletionsRequest {
+            messages,
+            tools: if tools.is_empty() { None } else { Some(tools) },
+            tool_choice: req.tool_config.and_then(convert_gemini_tool_config),
+            stop: config.stop_sequences,
+            n: config.candidate_count,
+            max_completion_tokens: config.max_output_tokens,
+            temperature: config.temperature,
+            top_p: config.top_p,
+            seed: config.seed,
+            presence_penalty: config.presence_penalty,
+            frequency_penalty: config.frequency_penalty,
+            response_format,
+            ..Default::default()
+        })
+    }
+}
+
+// ============================================================================
+// MAIN RESPONSE TRANSFORMATIONS
+// ============================================================================
+
+impl TryFrom<GenerateContentResponse> for ChatCompletionsResponse {
+    type Error = TransformError;
+
+    fn try_from(resp: GenerateContentResponse) -> Result<Self, Self::Error> {
+        let mut tool_call_count = 0;
+        let choices = resp.candidates.into_iter().enumerate().map(|(index, candidate)| {
+            let parts = candidate.content.map(|content| content.parts).unwrap_or_default();
+            let tool_calls: Vec<ToolCall> = parts.iter()
+                .filter_map(|part| part.function_call.as_ref())
+                .map(|function_call| {
+                    let tool_call = convert_function_call(function_call, tool_call_count);
+                    tool_call_count += 1;
+                    tool_call
+                })
+                .collect();
+            let text = parts_text(&parts);
+
+            Choice {
+                index: candidate.index.unwrap_or(index as u32),
+                finish_reason: candidate.finish_reason
+                    .map(|finish_reason| finish_reason_for(finish_reason, !tool_calls.is_empty())),
+                message: ResponseMessage {
+                    role: Role::Assistant,
+                    content: if text.is_empty() { None } else { Some(text) },
+                    refusal: None,
+                    annotations: None,
+                    audio: None,
+                    function_call: None,
+                    tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+                    reasoning_content: None,
+                    thinking_blocks: None,
+                },
+                logprobs: None,
+            }
+        }).collect();
+
+        Ok(ChatCompletionsResponse {
+            id: resp.response_id.unwrap_or_default(),
+            object: "chat.completion".to_string(),
+            created: current_timestamp(),
+            model: resp.model_version.unwrap_or_default(),
+            choices,
+            usage: resp.usage_metadata.unwrap_or_default().into(),
+            system_fingerprint: None,
+        })
+    }
+}
+
+// ============================================================================
+// STANDARD RUST TRAIT IMPLEMENTATIONS
+// ============================================================================
+
+impl From<GeminiUsageMetadata> for Usage {
+    fn from(usage: GeminiUsageMetadata) -> Self {
+        // gemini's prompt tokens include the cached ones like openai's, thoughts are billed as output
+        let thoughts_tokens = usage.thoughts_token_count.unwrap_or(0);
+        let completion_tokens = usage.candidates_token_count.unwrap_or(0) + thoughts_tokens;
+        Usage {
+            prompt_tokens: usage.prompt_token_count,
+            completion_tokens,
+            total_tokens: usage.total_token_count.max(usage.prompt_token_count + completion_tokens),
+            prompt_tokens_details: usage.cached_content_token_count.map(|cached_tokens| PromptTokensDetails {
+                cached_tokens: Some(cached_tokens),
+                audio_tokens: None,
+            }),
+            completion_tokens_details: usage.thoughts_token_count.map(|reasoning_tokens| CompletionTokensDetails {
+                reasoning_tokens: Some(reasoning_tokens),
+                audio_tokens: None,
+                accepted_prediction_tokens: None,
+                rejected_prediction_tokens: None,
+            }),
+        }
+    }
+}
+
+impl From<GeminiFinishReason> for FinishReason {
+    fn from(finish_reason: GeminiFinishReason) -> Self {
+        match finish_reason {
+            GeminiFinishReason::MaxTokens => FinishReason::Length,
+            GeminiFinishReason::Safety
+            | GeminiFinishReason::Recitation
+            | GeminiFinishReason::Blocklist
+            | GeminiFinishReason::ProhibitedContent
+            | GeminiFinishReason::Spii => FinishReason::ContentFilter,
+            GeminiFinishReason::Stop
+            | GeminiFinishReason::Language
+            | GeminiFinishReason::MalformedFunctionCall
+            | GeminiFinishReason::Other => FinishReason::Stop,
+        }
+    }
+}
+
+// ============================================================================
+// BODY TRANSLATION - Raw provider bodies to OpenAI bodies
+// ============================================================================
+
+/// Translates a whole Gemini generateContent response body into an OpenAI chat completions body
+pub fn translate_generate_content_response(body: &[u8]) -> Result<Vec<u8>, TransformError> {
+    let response: GenerateContentResponse = serde_json::from_slice(body)?;
+    let response: ChatCompletionsResponse = response.try_into()?;
+    Ok(serde_json::to_vec(&response)?)
+}
+
+/// Translates the server-sent events of a Gemini streamGenerateContent stream into OpenAI chat
+/// completion chunks. Gemini sends whole function calls in one chunk and repeats the usage on
+/// every chunk, so the usage is only reported with the chunk that finishes the response.
+#[derive(Debug, Default)]
+pub struct GenerateContentStreamTranslator {
+    decoder: SseDecoder,
+    skipped_events: usize,
+    started: bool,
+    tool_call_count: u32,
+}
+
+impl GenerateContentStreamTranslator {
+    /// Translates the complete events in `chunk`, an event split across chunks is held back until
+    /// the rest of it arrives. Events that can't be translated are left out.
+    pub fn translate(&mut self, chunk: &[u8]) -> Result<Vec<u8>, TransformError> {
+        let mut translated = String::new();
+        for sse_event in self.decoder.decode(chunk) {
+            let translated_events = serde_json::from_str(&sse_event.data)
+                .map_err(TransformError::from)
+                .and_then(|response| self.translate_response(response))
+                .unwrap_or_else(|_| {
+                    self.skipped_events += 1;
+                    Vec::new()
+                });
+            for data in translated_events {
+                translated.push_str("data: ");
+                translated.push_str(&data);
+                translated.push_str("\n\n");
+            }
+        }
+        Ok(translated.into_bytes())
+    }
+
+    /// Number of events left out since the last call because they could not be translated.
+    pub fn take_skipped_events(&mut self) -> usize {
+        std::mem::take(&mut self.skipped_events)
+    }
+
+    fn translate_response(&mut self, response: GenerateContentResponse) -> Result<Vec<String>, TransformError> {
+        let id = response.response_id.unwrap_or_default();
+        let model = response.model_version.unwrap_or_default();
+        let chunk = |choices: Vec<StreamChoice>, usage: Option<Usage>| ChatCompletionsStreamResponse {
+            id: id.clone(),
+            object: "chat.completion.chunk".to_string(),
+            created: current_timestamp(),
+            model: model.clone(),
+            choices,
+            usage,
+            system_fingerprint: None,
+            service_tier: None,
+        };
+
+        let mut delta = MessageDelta {
+            role: None,
+            content: None,
+            refusal: None,
+            function_call: None,
+            tool_calls: None,
+            reasoning_content: None,
+            thinking_blocks: None,
+        };
+        if !self.started {
+            self.started = true;
+            delta.role = Some(Role::Assistant);
+        }
+
+        let candidate = response.candidates.into_iter().next();
+        let finish_reason = candidate.as_ref().and_then(|candidate| candidate.finish_reason.clone());
+        let parts = candidate.and_then(|candidate| candidate.content).map(|content| content.parts).unwrap_or_default();
+
+        let text = parts_text(&parts);
+        if !text.is_empty() {
+            delta.content = Some(text);
+        }
+        let tool_calls: Vec<ToolCallDelta> = parts.iter()
+            .filter_map(|part| part.function_call.as_ref())
+            .map(|function_call| {
+                let tool_call = convert_function_call(function_call, self.tool_call_count);
+                let delta = ToolCallDelta {
+                    index: self.tool_call_count,
+                    id: Some(tool_call.id),
+                    call_type: Some(tool_call.call_type),
+                    function: Some(FunctionCallDelta {
+                        name: Some(tool_call.function.name),
+                        arguments: Some(tool_call.function.arguments),
+                    }),
+                };
+                self.tool_call_count += 1;
+                delta
+            })
+            .collect();
+        if !tool_calls.is_empty() {
+            delta.tool_calls = Some(tool_calls);
+        }
+
+        let mut chunks = Vec::new();
+        let has_delta = delta.role.is_some() || delta.content.is_some() || delta.tool_calls.is_some();
+        if has_delta || finish_reason.is_some() {
+            let choice = StreamChoice {
+                index: 0,
+                delta,
+                finish_reason: finish_reason.clone()
+                    .map(|finish_reason| finish_reason_for(finish_reason, self.tool_call_count > 0)),
+                logprobs: None,
+            };
+            chunks.push(serde_json::to_string(&chunk(vec![choice], None))?);
+        }
+        if finish_reason.is_some() {
+            let usage = response.usage_metadata.unwrap_or_default().into();
+            chunks.push(serde_json::to_string(&chunk(vec![], Some(usage)))?);
+            chunks.push("[DONE]".to_string());
+        }
+        Ok(chunks)
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Helper to create a current unix timestamp
+fn current_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+/// Append parts to the conversation, consecutive turns of a role are merged since gemini expects
+/// every function response to a model turn in a single user turn
+fn push_content(contents: &mut Vec<GeminiContent>, role: GeminiRole, parts: Vec<GeminiPart>) {
+    if parts.is_empty() {
+        return;
+    }
+    match contents.last_mut() {
+        Some(last) if last.role.as_ref() == Some(&role) => last.parts.extend(parts),
+        _ => contents.push(GeminiContent { role: Some(role), parts }),
+    }
+}
+
+/// Convert OpenAI message content to Gemini parts
+fn content_to_parts(content: MessageContent) -> Vec<GeminiPart> {
+    match content {
+        MessageContent::Text(text) if text.is_empty() => vec![],
+        MessageContent::Text(text) => vec![GeminiPart { text: Some(text), ..Default::default() }],
+        MessageContent::Parts(parts) => parts.into_iter().map(|part| match part {
+            ContentPart::Text { text } => GeminiPart { text: Some(text), ..Default::default() },
+            ContentPart::ImageUrl { image_url } => image_url_to_part(image_url.url),
+        }).collect(),
+    }
+}
+
+/// Data URLs are sent inline, any other URL is left for gemini to fetch
+fn image_url_to_part(url: String) -> GeminiPart {
+    if let Some((mime_type, data)) = url.strip_prefix("data:").and_then(|url| url.split_once(";base64,")) {
+        return GeminiPart {
+            inline_data: Some(GeminiBlob { mime_type: mime_type.to_string(), data: data.to_string() }),
+            ..Default::default()
+        };
+    }
+    GeminiPart {
+        file_data: Some(GeminiFileData { mime_type: None, file_uri: url }),
+        ..Default::default()
+    }
+}
+
+/// Convert a Gemini part to OpenAI content, function calls and responses are handled separately
+fn part_to_content_part(part: GeminiPart) -> Option<ContentPart> {
+    if part.thought == Some(true) {
+        return None;
+    }
+    if let Some(text) = part.text {
+        return Some(ContentPart::Text { text });
+    }
+    if let Some(inline_data) = part.inline_data {
+        let url = format!("data:{};base64,{}", inline_data.mime_type, inline_data.data);
+        return Some(ContentPart::ImageUrl { image_url: ImageUrl { url, detail: None } });
+    }
+    part.file_data.map(|file_data| ContentPart::ImageUrl {
+        image_url: ImageUrl { url: file_data.file_uri, detail: None },
+    })
+}
+
+/// The text of the parts, the model's thoughts are left out
+fn parts_text(parts: &[GeminiPart]) -> String {
+    parts.iter()
+        .filter(|part| part.thought != Some(true))
+        .filter_map(|part| part.text.as_deref())
+        .collect()
+}
+
+/// Gemini function responses must be objects, other tool results are wrapped
+fn tool_result_to_response(content: MessageContent) -> Value {
+    let text = match content {
+        MessageContent::Text(text) => text,
+        MessageContent::Parts(parts) => parts.into_iter().filter_map(|part| match part {
+            ContentPart::Text { text } => Some(text),
+            ContentPart::ImageUrl { .. } => None,
+        }).collect(),
+    };
+    match serde_json::from_str::<Value>(&text) {
+        Ok(response @ Value::Object(_)) => response,
+        _ => json!({ "content": text }),
+    }
+}
+
+/// Parse OpenAI function call arguments, an empty string means no arguments
+fn parse_arguments(arguments: &str) -> Result<Value, TransformError> {
+    if arguments.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    serde_json::from_str(arguments).map_err(|_| TransformError::InvalidToolInput)
+}
+
+/// Gemini only sometimes ids its function calls
+fn tool_call_id(function_call: &GeminiFunctionCall, index: u32) -> String {
+    function_call.id.clone().unwrap_or_else(|| format!("call_{}", index))
+}
+
+/// Convert a Gemini function call to an OpenAI tool call
+fn convert_function_call(function_call: &GeminiFunctionCall, index: u32) -> ToolCall {
+    ToolCall {
+        id: tool_call_id(function_call, index),
+        call_type: "function".to_string(),
+        function: FunctionCall {
+            name: function_call.name.clone(),
+            arguments: function_call.args.to_string(),
+        },
+    }
+}
+
+/// Gemini finishes tool calls with STOP, OpenAI with tool_calls
+fn finish_reason_for(finish_reason: GeminiFinishReason, has_tool_calls: bool) -> FinishReason {
+    match finish_reason {
+        GeminiFinishReason::Stop if has_tool_calls => FinishReason::ToolCalls,
+        finish_reason => finish_reason.into(),
+    }
+}
+
+/// Convert OpenAI tool choice to Gemini tool config
+fn convert_openai_tool_choice(tool_choice: ToolChoice) -> GeminiToolConfig {
+    let (mode, allowed_function_names) = match tool_choice {
+        ToolChoice::Type(ToolChoiceType::Auto) => (GeminiFunctionCallingMode::Auto, None),
+        ToolChoice::Type(ToolChoiceType::Required) => (GeminiFunctionCallingMode::Any, None),
+        ToolChoice::Type(ToolChoiceType::None) => (GeminiFunctionCallingMode::None, None),
+        ToolChoice::Function { function, .. } => (GeminiFunctionCallingMode::Any, Some(vec![function.name])),
+    };
+    GeminiToolConfig {
+        function_calling_config: GeminiFunctionCallingConfig { mode, allowed_function_names },
+    }
+}
+
+/// Convert Gemini tool config to OpenAI tool choice
+fn convert_gemini_tool_config(tool_config: GeminiToolConfig) -> Option<ToolChoice> {
+    let config = tool_config.function_calling_config;
+    match (config.mode, config.allowed_function_names.as_deref()) {
+        (GeminiFunctionCallingMode::Any, Some([name])) => Some(ToolChoice::Function {
+            choice_type: "function".to_string(),
+            function: FunctionChoice { name: name.clone() },
+        }),
+        (GeminiFunctionCallingMode::Any, _) => Some(ToolChoice::Type(ToolChoiceType::Required)),
+        (GeminiFunctionCallingMode::Auto, _) => Some(ToolChoice::Type(ToolChoiceType::Auto)),
+        (GeminiFunctionCallingMode::None, _) => Some(ToolChoice::Type(ToolChoiceType::None)),
+        (GeminiFunctionCallingMode::Validated, _) => None,
+    }
+}
+
+/// Convert OpenAI response format to a Gemini response mime type and schema
+fn convert_response_format(response_format: Option<&Value>) -> (Option<String>, Option<Value>) {
+    match response_format.and_then(|format| format["type"].as_str()) {
+        Some("json_object") => (Some("application/json".to_string()), None),
+        Some("json_schema") => (
+            Some("application/json".to_string()),
+            response_format.and_then(|format| format["json_schema"].get("schema")).cloned(),
+        ),
+        _ => (None, None),
+    }
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_to_gemini_request_with_tools() {
+        let request: ChatCompletionsRequest = serde_json::from_value(json!({
+            "model": "gemini-2.5-flash",
+            "messages": [
+                {"role": "system", "content": "You are helpful"},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is the weather here?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,aGVsbG8="}}
+                ]},
+                {"role": "assistant", "content": "", "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}},
+                    {"id": "call_2", "type": "function", "function": {"name": "get_time", "arguments": ""}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "{\"temperature\":21}"},
+                {"role": "tool", "tool_call_id": "call_2", "content": "noon"}
+            ],
+            "tools": [{"type": "function", "function": {"name": "get_weather", "parameters": {"type": "object", "additionalProperties": false}}}],
+            "tool_choice": {"type": "function", "function": {"name": "get_weather"}},
+            "max_tokens": 512,
+            "stop": ["END"],
+            "response_format": {"type": "json_object"}
+        }))
+        .unwrap();
+
+        let request = GenerateContentRequest::try_from(request).unwrap();
+        let request = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(request["systemInstruction"], json!({"parts": [{"text": "You are helpful"}]}));
+        assert_eq!(request["contents"].as_array().unwrap().len(), 3);
+        assert_eq!(request["contents"][0]["role"], "user");
+        assert_eq!(request["contents"][0]["parts"][1]["inlineData"], json!({"mimeType": "image/png", "data": "aGVsbG8="}));
+        assert_eq!(request["contents"][1]["role"], "model");
+        assert_eq!(request["contents"][1]["parts"][0]["functionCall"], json!({"name": "get_weather", "args": {"city": "Paris"}}));
+        assert_eq!(request["contents"][1]["parts"][1]["functionCall"], json!({"name": "get_time", "args": {}}));
+        // both results answer the same model turn so they share a user turn
+        assert_eq!(request["contents"][2]["parts"][0]["functionResponse"], json!({"name": "get_weather", "response": {"temperature": 21}}));
+        assert_eq!(request["contents"][2]["parts"][1]["functionResponse"], json!({"name": "get_time", "response": {"content": "noon"}}));
+        assert_eq!(
+            request["tools"][0]["functionDeclarations"][0],
+            json!({"name": "get_weather", "parametersJsonSchema": {"type": "object", "additionalProperties": false}})
+        );
+        assert_eq!(request["toolConfig"], json!({"functionCallingConfig": {"mode": "ANY", "allowedFunctionNames": ["get_weather"]}}));
+        assert_eq!(
+            request["generationConfig"],
+            json!({"maxOutputTokens": 512, "stopSequences": ["END"], "responseMimeType": "application/json"})
+        );
+    }
+
+    #[test]
+    fn test_gemini_to_openai_request() {
+        let request: GenerateContentRequest = serde_json::from_value(json!({
+            "systemInstruction": {"parts": [{"text": "You are helpful"}]},
+            "contents": [
+                {"role": "user", "parts": [{"text": "Weather in Paris and Rome?"}]},
+                {"role": "model", "parts": [
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}},
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Rome"}}}
+                ]},
+                {"role": "user", "parts": [
+                    {"functionResponse": {"name": "get_weather", "response": {"temperature": 21}}},
+                    {"functionResponse": {"name": "get_weather", "response": {"temperature": 25}}}
+                ]}
+            ],
+            "tools": [{"functionDeclarations": [{"name": "get_weather", "parameters": {"type": "object"}}]}],
+            "toolConfig": {"functionCallingConfig": {"mode": "AUTO"}},
+            "generationConfig": {"maxOutputTokens": 256, "temperature": 0.2}
+        }))
+        .unwrap();
+
+        let request = ChatCompletionsRequest::try_from(request).unwrap();
+
+        assert_eq!(request.messages.len(), 5);
+        assert_eq!(request.messages[0].role, Role::System);
+        let tool_calls = request.messages[2].tool_calls.as_ref().unwrap();
+        assert_eq!(tool_calls.len(), 2);
+        assert_eq!(tool_calls[1].function.arguments, "{\"city\":\"Rome\"}");
+        // responses without ids answer the calls of the same name in order
+        assert_eq!(request.messages[3].tool_call_id.as_ref(), Some(&tool_calls[0].id));
+        assert_eq!(request.messages[4].tool_call_id.as_ref(), Some(&tool_calls[1].id));
+        assert_eq!(request.tools.as_ref().unwrap()[0].function.name, "get_weather");
+        assert_eq!(request.tool_choice, Some(ToolChoice::Type(ToolChoiceType::Auto)));
+        assert_eq!(request.max_completion_tokens, Some(256));
+        assert_eq!(request.temperature, Some(0.2));
+    }
+
+    #[test]
+    fn test_gemini_to_openai_response() {
+        let body = json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "Thinking about the weather", "thought": true},
+                    {"text": "Let me check."},
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}}
+                ]},
+                "finishReason": "STOP",
+                "index": 0
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 120,
+                "candidatesTokenCount": 10,
+                "thoughtsTokenCount": 30,
+                "cachedContentTokenCount": 100,
+                "totalTokenCount": 160
+            },
+            "modelVersion": "gemini-2.5-flash",
+            "responseId": "resp-1"
+        });
+
+        let translated = translate_generate_content_response(body.to_string().as_bytes()).unwrap();
+        let translated: Value = serde_json::from_slice(&translated).unwrap();
+
+        assert_eq!(translated["id"], "resp-1");
+        assert_eq!(translated["model"], "gemini-2.5-flash");
+        assert_eq!(translated["choices"][0]["message"]["content"], "Let me check.");
+        assert_eq!(translated["choices"][0]["message"]["tool_calls"][0]["function"]["name"], "get_weather");
+        assert_eq!(translated["choices"][0]["message"]["tool_calls"][0]["function"]["arguments"], "{\"city\":\"Paris\"}");
+        assert_eq!(translated["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(translated["usage"]["prompt_tokens"], 120);
+        assert_eq!(translated["usage"]["completion_tokens"], 40);
+        assert_eq!(translated["usage"]["total_tokens"], 160);
+        assert_eq!(translated["usage"]["prompt_tokens_details"]["cached_tokens"], 100);
+        assert_eq!(translated["usage"]["completion_tokens_details"]["reasoning_tokens"], 30);
+    }
+
+    #[test]
+    fn test_generate_content_stream_translator() {
+        let events = [
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "Hello"}]}, "index": 0}], "usageMetadata": {"promptTokenCount": 8, "totalTokenCount": 8}, "modelVersion": "gemini-2.5-flash", "responseId": "resp-1"}),
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": " there"}]}, "index": 0}], "usageMetadata": {"promptTokenCount": 8, "totalTokenCount": 8}, "modelVersion": "gemini-2.5-flash", "responseId": "resp-1"}),
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "!"}]}, "finishReason": "STOP", "index": 0}], "usageMetadata": {"promptTokenCount": 8, "candidatesTokenCount": 3, "totalTokenCount": 11}, "modelVersion": "gemini-2.5-flash", "responseId": "resp-1"}),
+        ];
+        let stream: String = events.iter().map(|event| format!("data: {}\r\n\r\n", event)).collect();
+
+        let (first, second) = stream.as_bytes().split_at(stream.len() / 2);
+        let mut translator = GenerateContentStreamTranslator::default();
+        let mut translated = translator.translate(first).unwrap();
+        translated.extend(translator.translate(second).unwrap());
+        let translated = String::from_utf8(translated).unwrap();
+
+        let chunks: Vec<&str> = translated
+            .split("\n\n")
+            .filter(|chunk| !chunk.is_empty())
+            .map(|chunk| chunk.strip_prefix("data: ").unwrap())
+            .collect();
+        assert_eq!(chunks.len(), 5);
+        assert_eq!(chunks[4], "[DONE]");
+
+        let chunks: Vec<Value> = chunks[..4].iter().map(|chunk| serde_json::from_str(chunk).unwrap()).collect();
+        assert_eq!(chunks[0]["id"], "resp-1");
+        assert_eq!(chunks[0]["choices"][0]["delta"], json!({"role": "assistant", "content": "Hello"}));
+        assert_eq!(chunks[1]["choices"][0]["delta"], json!({"content": " there"}));
+        assert_eq!(chunks[2]["choices"][0]["finish_reason"], "stop");
+        assert!(chunks[..3].iter().all(|chunk| chunk.get("usage").is_none()));
+        assert_eq!(chunks[3]["choices"], json!([]));
+        assert_eq!(chunks[3]["usage"]["prompt_tokens"], 8);
+        assert_eq!(chunks[3]["usage"]["completion_tokens"], 3);
+    }
+
+    #[test]
+    fn test_generate_content_stream_translator_tool_calls() {
+        let events = [
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}}]}, "index": 0}]}),
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"functionCall": {"name": "get_weather", "args": {"city": "Rome"}}}]}, "finishReason": "STOP", "index": 0}], "usageMetadata": {"promptTokenCount": 8, "candidatesTokenCount": 20, "totalTokenCount": 28}}),
+        ];
+        let stream: String = events.iter().map(|event| format!("data: {}\r\n\r\n", event)).collect();
+
+        let translated = GenerateContentStreamTranslator::default().translate(stream.as_bytes()).unwrap();
+        let translated = String::from_utf8(translated).unwrap();
+        let chunks: Vec<Value> = translated
+            .split("\n\n")
+            .filter_map(|chunk| chunk.strip_prefix("data: "))
+            .filter(|chunk| *chunk != "[DONE]")
+            .map(|chunk| serde_json::from_str(chunk).unwrap())
+            .collect();
+
+        let first = &chunks[0]["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(first["index"], 0);
+        assert_eq!(first["id"], "call_0");
+        assert_eq!(first["function"]["arguments"], "{\"city\":\"Paris\"}");
+        let second = &chunks[1]["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(second["index"], 1);
+        assert_eq!(second["id"], "call_1");
+        assert_eq!(chunks[1]["choices"][0]["finish_reason"], "tool_calls");
+    }
+}
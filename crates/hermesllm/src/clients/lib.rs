@@ -19,6 +19,8 @@ pub enum TransformError {
     MissingField(String),
     #[error("Unsupported conversion: {0}")]
     UnsupportedConversion(String),
+    #[error("Invalid event stream: {0}")]
+    InvalidEventStream(String),
 }
 
 #[cfg(test)]
@@ -1,9 +1,25 @@
 pub mod lib;
 pub mod transformer;
+pub mod gemini_transformer;
+pub mod bedrock_transformer;
+pub mod responses_transformer;
+pub mod eventstream;
+pub mod sse;
+pub mod sigv4;
 pub mod endpoints;
 
 // Re-export the main items for easier access
 pub use lib::*;
 pub use endpoints::{is_supported_endpoint, supported_endpoints, identify_provider};
+pub use transformer::{
+    translate_chat_completions_response, translate_messages_response,
+    ChatCompletionsStreamTranslator, MessagesStreamTranslator,
+};
+pub use gemini_transformer::{translate_generate_content_response, GenerateContentStreamTranslator};
+pub use bedrock_transformer::{translate_converse_response, ConverseStreamTranslator};
+pub use responses_transformer::{translate_responses_response, ResponsesStreamTranslator};
+pub use eventstream::{EventStreamDecoder, EventStreamHeaderValue, EventStreamMessage};
+pub use sse::{SseDecoder, SseEvent};
+pub use sigv4::{AwsCredentials, SigV4Signer};
 
 // Note: transformer module contains TryFrom trait implementations that are automatically available
@@ -0,0 +1,789 @@
+//! Transformations between the OpenAI Responses API and the chat completions API
+//!
+//! Responses clients are served by any provider by converting their requests to chat completions
+//! and the chat completions responses back. Anthropic Messages are reached through chat completions.
+//! Conversations are sent in full as input items: `previous_response_id` needs the responses stored
+//! by OpenAI and can't be converted.
+
+use serde_json::{json, Value};
+
+use super::{SseDecoder, TransformError};
+use crate::apis::*;
+
+// ============================================================================
+// MAIN REQUEST TRANSFORMATIONS
+// ============================================================================
+
+impl TryFrom<ResponsesRequest> for ChatCompletionsRequest {
+    type Error = TransformError;
+
+    fn try_from(req: ResponsesRequest) -> Result<Self, Self::Error> {
+        if req.previous_response_id.is_some() {
+            return Err(TransformError::UnsupportedConversion(
+                "previous_response_id needs responses stored by OpenAI, send the conversation as input instead".to_string(),
+            ));
+        }
+
+        let mut messages = Vec::new();
+        if let Some(instructions) = req.instructions {
+            messages.push(text_message(Role::System, instructions));
+        }
+        match req.input {
+            ResponsesInput::Text(text) => messages.push(text_message(Role::User, text)),
+            ResponsesInput::Items(items) => {
+                for item in items {
+                    push_input_item(&mut messages, item)?;
+                }
+            }
+        }
+
+        let tools = req.tools
+            .map(|tools| tools.into_iter().map(Tool::try_from).collect::<Result<Vec<_>, _>>())
+            .transpose()?;
+        let stream_options = req.stream
+            .filter(|stream| *stream)
+            .map(|_| StreamOptions { include_usage: Some(true) });
+
+        Ok(ChatCompletionsRequest {
+            messages,
+            model: req.model,
+            max_completion_tokens: req.max_output_tokens,
+            metadata: req.metadata,
+            parallel_tool_calls: req.parallel_tool_calls,
+            response_format: req.text.and_then(|text| text.format).map(convert_text_format),
+            stream: req.stream,
+            stream_options,
+            temperature: req.temperature,
+            tool_choice: req.tool_choice.map(Into::into),
+            tools,
+            top_p: req.top_p,
+            user: req.user,
+            ..Default::default()
+        })
+    }
+}
+
+impl TryFrom<ResponsesRequest> for MessagesRequest {
+    type Error = TransformError;
+
+    fn try_from(req: ResponsesRequest) -> Result<Self, Self::Error> {
+        MessagesRequest::try_from(ChatCompletionsRequest::try_from(req)?)
+    }
+}
+
+// ============================================================================
+// MAIN RESPONSE TRANSFORMATIONS
+// ============================================================================
+
+impl TryFrom<ChatCompletionsResponse> for ResponsesResponse {
+    type Error = TransformError;
+
+    fn try_from(resp: ChatCompletionsResponse) -> Result<Self, Self::Error> {
+        let id = response_id(&resp.id);
+        let mut output = Vec::new();
+        let mut finish_reason = None;
+
+        if let Some(choice) = resp.choices.into_iter().next() {
+            finish_reason = choice.finish_reason;
+            let mut content = Vec::new();
+            if let Some(text) = choice.message.content.filter(|text| !text.is_empty()) {
+                content.push(ResponsesContentPart::OutputText { text, annotations: vec![] });
+            }
+            if let Some(refusal) = choice.message.refusal {
+                content.push(ResponsesContentPart::Refusal { refusal });
+            }
+            if !content.is_empty() {
+                output.push(ResponsesItem::Message {
+                    id: item_id("msg", &id, 0),
+                    role: ResponsesRole::Assistant,
+                    status: Some(ResponsesStatus::Completed),
+                    content,
+                });
+            }
+            for tool_call in choice.message.tool_calls.into_iter().flatten() {
+                output.push(ResponsesItem::FunctionCall {
+                    id: Some(item_id("fc", &id, output.len() as u32)),
+                    call_id: tool_call.id,
+                    name: tool_call.function.name,
+                    arguments: tool_call.function.arguments,
+                    status: Some(ResponsesStatus::Completed),
+                });
+            }
+        }
+
+        let (status, incomplete_details) = response_status(finish_reason.as_ref());
+        Ok(ResponsesResponse {
+            id,
+            object: "response".to_string(),
+            created_at: resp.created,
+            status,
+            model: resp.model,
+            output,
+            usage: Some(resp.usage.into()),
+            incomplete_details,
+            error: None,
+        })
+    }
+}
+
+impl TryFrom<MessagesResponse> for ResponsesResponse {
+    type Error = TransformError;
+
+    fn try_from(resp: MessagesResponse) -> Result<Self, Self::Error> {
+        ResponsesResponse::try_from(ChatCompletionsResponse::try_from(resp)?)
+    }
+}
+
+// ============================================================================
+// STANDARD RUST TRAIT IMPLEMENTATIONS
+// ============================================================================
+
+impl TryFrom<ResponsesTool> for Tool {
+    type Error = TransformError;
+
+    fn try_from(tool: ResponsesTool) -> Result<Self, Self::Error> {
+        match tool {
+            ResponsesTool::Function { name, description, parameters, strict } => Ok(Tool {
+                tool_type: "function".to_string(),
+                function: Function {
+                    name,
+                    description,
+                    parameters: parameters.unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+                    strict,
+                },
+            }),
+            ResponsesTool::BuiltIn => Err(TransformError::UnsupportedConversion(
+                "built-in tools are only served by OpenAI".to_string(),
+            )),
+        }
+    }
+}
+
+impl From<ResponsesToolChoice> for ToolChoice {
+    fn from(tool_choice: ResponsesToolChoice) -> Self {
+        match tool_choice {
+            ResponsesToolChoice::Type(choice_type) => ToolChoice::Type(choice_type),
+            ResponsesToolChoice::Function { name, .. } => ToolChoice::Function {
+                choice_type: "function".to_string(),
+                function: FunctionChoice { name },
+            },
+        }
+    }
+}
+
+impl TryFrom<ResponsesInputMessage> for Message {
+    type Error = TransformError;
+
+    fn try_from(message: ResponsesInputMessage) -> Result<Self, Self::Error> {
+        let role = match message.role {
+            ResponsesRole::User => Role::User,
+            ResponsesRole::Assistant => Role::Assistant,
+            ResponsesRole::System | ResponsesRole::Developer => Role::System,
+        };
+        let content = match message.content {
+            ResponsesContent::Text(text) => MessageContent::Text(text),
+            // only user messages carry images, the other roles take text
+            ResponsesContent::Parts(parts) if role != Role::User => MessageContent::Text(
+                parts.iter().filter_map(part_text).collect::<Vec<_>>().join(""),
+            ),
+            ResponsesContent::Parts(parts) => MessageContent::Parts(
+                parts.into_iter().map(ContentPart::try_from).collect::<Result<_, _>>()?,
+            ),
+        };
+        Ok(Message {
+            content,
+            role,
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+            thinking_blocks: None,
+        })
+    }
+}
+
+impl TryFrom<ResponsesContentPart> for ContentPart {
+    type Error = TransformError;
+
+    fn try_from(part: ResponsesContentPart) -> Result<Self, Self::Error> {
+        match part {
+            ResponsesContentPart::InputImage { image_url: Some(url), detail, .. } => Ok(ContentPart::ImageUrl {
+                image_url: ImageUrl { url, detail },
+            }),
+            ResponsesContentPart::InputImage { .. } => Err(TransformError::UnsupportedContent(
+                "images uploaded as files are only served by OpenAI".to_string(),
+            )),
+            part => Ok(ContentPart::Text { text: part_text(&part).unwrap_or_default().to_string() }),
+        }
+    }
+}
+
+impl From<Usage> for ResponsesUsage {
+    fn from(usage: Usage) -> Self {
+        ResponsesUsage {
+            input_tokens: usage.prompt_tokens,
+            input_tokens_details: usage.prompt_tokens_details
+                .and_then(|details| details.cached_tokens)
+                .map(|cached_tokens| ResponsesInputTokensDetails { cached_tokens }),
+            output_tokens: usage.completion_tokens,
+            output_tokens_details: usage.completion_tokens_details
+                .and_then(|details| details.reasoning_tokens)
+                .map(|reasoning_tokens| ResponsesOutputTokensDetails { reasoning_tokens }),
+            total_tokens: usage.total_tokens,
+        }
+    }
+}
+
+// ============================================================================
+// BODY TRANSLATION - OpenAI bodies to Responses bodies
+// ============================================================================
+
+/// Translates a whole OpenAI chat completions response body into a Responses body
+pub fn translate_responses_response(body: &[u8]) -> Result<Vec<u8>, TransformError> {
+    let response: ChatCompletionsResponse = serde_json::from_slice(body)?;
+    let response: ResponsesResponse = response.try_into()?;
+    Ok(serde_json::to_vec(&response)?)
+}
+
+#[derive(Debug)]
+struct StreamItem {
+    id: String,
+    output_index: u32,
+    kind: StreamItemKind,
+}
+
+#[derive(Debug)]
+enum StreamItemKind {
+    Text(String),
+    FunctionCall {
+        tool_call_index: u32,
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+}
+
+/// Translates the chunks of an OpenAI chat completions stream into Responses events. Responses
+/// frame every output item with added and done events, and repeat the whole response once it
+/// completes, so the output items are kept until `[DONE]`.
+#[derive(Debug, Default)]
+pub struct ResponsesStreamTranslator {
+    decoder: SseDecoder,
+    skipped_events: usize,
+    sequence_number: u64,
+    response: Option<ResponsesResponse>,
+    item: Option<StreamItem>,
+    finish_reason: Option<FinishReason>,
+    usage: Option<ResponsesUsage>,
+}
+
+impl ResponsesStreamTranslator {
+    /// Translates the complete chunks in `chunk`, a chunk split across calls is held back until
+    /// the rest of it arrives.
+    pub fn translate(&mut self, chunk: &[u8]) -> Result<Vec<u8>, TransformError> {
+        let mut events = Vec::new();
+        for sse_event in self.decoder.decode(chunk) {
+            if sse_event.is_done() {
+                self.finish(&mut events);
+                continue;
+            }
+            match serde_json::from_str::<ChatCompletionsStreamResponse>(&sse_event.data) {
+                Ok(chunk) => self.translate_chunk(chunk, &mut events),
+                Err(_) => self.skipped_events += 1,
+            }
+        }
+
+        let mut translated = String::new();
+        for event in events {
+            translated.push_str("event: ");
+            translated.push_str(event.event_type());
+            translated.push_str("\ndata: ");
+            translated.push_str(&serde_json::to_string(&event)?);
+            translated.push_str("\n\n");
+        }
+        Ok(translated.into_bytes())
+    }
+
+    /// Number of events left out since the last call because they could not be translated.
+    pub fn take_skipped_events(&mut self) -> usize {
+        std::mem::take(&mut self.skipped_events)
+    }
+
+    fn next_sequence_number(&mut self) -> u64 {
+        self.sequence_number += 1;
+        self.sequence_number - 1
+    }
+
+    fn translate_chunk(&mut self, chunk: ChatCompletionsStreamResponse, events: &mut Vec<ResponsesStreamEvent>) {
+        if self.response.is_none() {
+            let response = ResponsesResponse {
+                id: response_id(&chunk.id),
+                object: "response".to_string(),
+                created_at: chunk.created,
+                status: ResponsesStatus::InProgress,
+                model: chunk.model.clone(),
+                output: vec![],
+                usage: None,
+                incomplete_details: None,
+                error: None,
+            };
+            events.push(ResponsesStreamEvent::Created {
+                response: response.clone(),
+                sequence_number: self.next_sequence_number(),
+            });
+            events.push(ResponsesStreamEvent::InProgress {
+                response: response.clone(),
+                sequence_number: self.next_sequence_number(),
+            });
+            self.response = Some(response);
+        }
+
+        if let Some(usage) = chunk.usage {
+            self.usage = Some(usage.into());
+        }
+
+        let Some(choice) = chunk.choices.into_iter().next() else {
+            return;
+        };
+        if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+            if !matches!(self.item, Some(StreamItem { kind: StreamItemKind::Text(_), .. })) {
+                self.start_item(StreamItemKind::Text(String::new()), events);
+            }
+            let sequence_number = self.next_sequence_number();
+            let item = self.item.as_mut().expect("a text item was started");
+            if let StreamItemKind::Text(item_text) = &mut item.kind {
+                item_text.push_str(&text);
+            }
+            events.push(ResponsesStreamEvent::OutputTextDelta {
+                item_id: item.id.clone(),
+                output_index: item.output_index,
+                content_index: 0,
+                delta: text,
+                sequence_number,
+            });
+        }
+        for tool_call in choice.delta.tool_calls.into_iter().flatten() {
+            let function = tool_call.function.unwrap_or(FunctionCallDelta { name: None, arguments: None });
+            let is_current = matches!(
+                self.item,
+                Some(StreamItem { kind: StreamItemKind::FunctionCall { tool_call_index, .. }, .. })
+                    if tool_call_index == tool_call.index
+            );
+            if !is_current || tool_call.id.is_some() {
+                let kind = StreamItemKind::FunctionCall {
+                    tool_call_index: tool_call.index,
+                    call_id: tool_call.id.unwrap_or_default(),
+                    name: function.name.unwrap_or_default(),
+                    arguments: String::new(),
+                };
+                self.start_item(kind, events);
+            }
+            if let Some(delta) = function.arguments.filter(|arguments| !arguments.is_empty()) {
+                let sequence_number = self.next_sequence_number();
+                let item = self.item.as_mut().expect("a function call item was started");
+                if let StreamItemKind::FunctionCall { arguments, .. } = &mut item.kind {
+                    arguments.push_str(&delta);
+                }
+                events.push(ResponsesStreamEvent::FunctionCallArgumentsDelta {
+                    item_id: item.id.clone(),
+                    output_index: item.output_index,
+                    delta,
+                    sequence_number,
+                });
+            }
+        }
+        if let Some(finish_reason) = choice.finish_reason {
+            self.finish_reason = Some(finish_reason);
+        }
+    }
+
+    fn start_item(&mut self, kind: StreamItemKind, events: &mut Vec<ResponsesStreamEvent>) {
+        self.finish_item(events);
+        let Some(response) = self.response.as_ref() else {
+            return;
+        };
+        let output_index = response.output.len() as u32;
+        let item = match &kind {
+            StreamItemKind::Text(_) => ResponsesItem::Message {
+                id: item_id("msg", &response.id, output_index),
+                role: ResponsesRole::Assistant,
+                status: Some(ResponsesStatus::InProgress),
+                content: vec![],
+            },
+            StreamItemKind::FunctionCall { call_id, name, .. } => ResponsesItem::FunctionCall {
+                id: Some(item_id("fc", &response.id, output_index)),
+                call_id: call_id.clone(),
+                name: name.clone(),
+                arguments: String::new(),
+                status: Some(ResponsesStatus::InProgress),
+            },
+        };
+        let id = match &item {
+            ResponsesItem::Message { id, .. } => id.clone(),
+            ResponsesItem::FunctionCall { id, .. } => id.clone().unwrap_or_default(),
+            _ => unreachable!("only messages and function calls are streamed"),
+        };
+
+        events.push(ResponsesStreamEvent::OutputItemAdded {
+            output_index,
+            item,
+            sequence_number: self.next_sequence_number(),
+        });
+        if matches!(kind, StreamItemKind::Text(_)) {
+            events.push(ResponsesStreamEvent::ContentPartAdded {
+                item_id: id.clone(),
+                output_index,
+                content_index: 0,
+                part: ResponsesContentPart::OutputText { text: String::new(), annotations: vec![] },
+                sequence_number: self.next_sequence_number(),
+            });
+        }
+        self.item = Some(StreamItem { id, output_index, kind });
+    }
+
+    fn finish_item(&mut self, events: &mut Vec<ResponsesStreamEvent>) {
+        let Some(StreamItem { id, output_index, kind }) = self.item.take() else {
+            return;
+        };
+        let item = match kind {
+            StreamItemKind::Text(text) => {
+                let part = ResponsesContentPart::OutputText { text: text.clone(), annotations: vec![] };
+                events.push(ResponsesStreamEvent::OutputTextDone {
+                    item_id: id.clone(),
+                    output_index,
+                    content_index: 0,
+                    text,
+                    sequence_number: self.next_sequence_number(),
+                });
+                events.push(ResponsesStreamEvent::ContentPartDone {
+                    item_id: id.clone(),
+                    output_index,
+                    content_index: 0,
+                    part: part.clone(),
+                    sequence_number: self.next_sequence_number(),
+                });
+                ResponsesItem::Message {
+                    id,
+                    role: ResponsesRole::Assistant,
+                    status: Some(ResponsesStatus::Completed),
+                    content: vec![part],
+                }
+            }
+            StreamItemKind::FunctionCall { call_id, name, arguments, .. } => {
+                events.push(ResponsesStreamEvent::FunctionCallArgumentsDone {
+                    item_id: id.clone(),
+                    output_index,
+                    arguments: arguments.clone(),
+                    sequence_number: self.next_sequence_number(),
+                });
+                ResponsesItem::FunctionCall {
+                    id: Some(id),
+                    call_id,
+                    name,
+                    arguments,
+                    status: Some(ResponsesStatus::Completed),
+                }
+            }
+        };
+        events.push(ResponsesStreamEvent::OutputItemDone {
+            output_index,
+            item: item.clone(),
+            sequence_number: self.next_sequence_number(),
+        });
+        if let Some(response) = self.response.as_mut() {
+            response.output.push(item);
+        }
+    }
+
+    fn finish(&mut self, events: &mut Vec<ResponsesStreamEvent>) {
+        self.finish_item(events);
+        let Some(mut response) = self.response.take() else {
+            return;
+        };
+        let (status, incomplete_details) = response_status(self.finish_reason.take().as_ref());
+        response.status = status;
+        response.incomplete_details = incomplete_details;
+        response.usage = self.usage.take();
+
+        let sequence_number = self.next_sequence_number();
+        events.push(match status {
+            ResponsesStatus::Incomplete => ResponsesStreamEvent::Incomplete { response, sequence_number },
+            _ => ResponsesStreamEvent::Completed { response, sequence_number },
+        });
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn text_message(role: Role, text: String) -> Message {
+    Message {
+        content: MessageContent::Text(text),
+        role,
+        name: None,
+        tool_calls: None,
+        tool_call_id: None,
+        thinking_blocks: None,
+    }
+}
+
+fn push_input_item(messages: &mut Vec<Message>, item: ResponsesInputItem) -> Result<(), TransformError> {
+    match item {
+        ResponsesInputItem::Message(message) => messages.push(message.try_into()?),
+        ResponsesInputItem::Item(ResponsesItem::Message { role, content, .. }) => {
+            let message = ResponsesInputMessage { role, content: ResponsesContent::Parts(content) };
+            messages.push(message.try_into()?);
+        }
+        ResponsesInputItem::Item(ResponsesItem::FunctionCall { call_id, name, arguments, .. }) => {
+            let tool_call = ToolCall {
+                id: call_id,
+                call_type: "function".to_string(),
+                function: FunctionCall { name, arguments },
+            };
+            // the calls of a turn belong to the assistant message they follow
+            match messages.last_mut() {
+                Some(message) if message.role == Role::Assistant => {
+                    message.tool_calls.get_or_insert_with(Vec::new).push(tool_call);
+                }
+                _ => {
+                    let mut message = text_message(Role::Assistant, String::new());
+                    message.tool_calls = Some(vec![tool_call]);
+                    messages.push(message);
+                }
+            }
+        }
+        ResponsesInputItem::Item(ResponsesItem::FunctionCallOutput { call_id, output, .. }) => {
+            let mut message = text_message(Role::Tool, output);
+            message.tool_call_id = Some(call_id);
+            messages.push(message);
+        }
+        // reasoning is specific to the model that produced it and is not replayed
+        ResponsesInputItem::Item(ResponsesItem::Reasoning { .. }) => {}
+    }
+    Ok(())
+}
+
+fn part_text(part: &ResponsesContentPart) -> Option<&str> {
+    match part {
+        ResponsesContentPart::InputText { text } | ResponsesContentPart::OutputText { text, .. } => Some(text),
+        ResponsesContentPart::Refusal { refusal } => Some(refusal),
+        ResponsesContentPart::InputImage { .. } => None,
+    }
+}
+
+/// Responses carry a json schema inline, chat completions nest it under `json_schema`
+fn convert_text_format(format: Value) -> Value {
+    match format {
+        Value::Object(mut json_schema) if json_schema.get("type") == Some(&json!("json_schema")) => {
+            json_schema.remove("type");
+            json!({"type": "json_schema", "json_schema": json_schema})
+        }
+        format => format,
+    }
+}
+
+fn response_id(id: &str) -> String {
+    format!("resp_{}", id.trim_start_matches("chatcmpl-"))
+}
+
+fn item_id(prefix: &str, response_id: &str, output_index: u32) -> String {
+    format!("{}_{}_{}", prefix, response_id.trim_start_matches("resp_"), output_index)
+}
+
+fn response_status(finish_reason: Option<&FinishReason>) -> (ResponsesStatus, Option<ResponsesIncompleteDetails>) {
+    let reason = match finish_reason {
+        Some(FinishReason::Length) => "max_output_tokens",
+        Some(FinishReason::ContentFilter) => "content_filter",
+        _ => return (ResponsesStatus::Completed, None),
+    };
+    (ResponsesStatus::Incomplete, Some(ResponsesIncompleteDetails { reason: reason.to_string() }))
+}
+
+// ============================================================================
+// TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_responses_to_openai_request() {
+        let request: ResponsesRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "instructions": "Be brief.",
+            "input": [
+                {"role": "user", "content": [
+                    {"type": "input_text", "text": "What is the weather here?"},
+                    {"type": "input_image", "image_url": "https://example.com/paris.png"}
+                ]},
+                {"type": "message", "id": "msg_1", "role": "assistant",
+                 "content": [{"type": "output_text", "text": "Let me check."}]},
+                {"type": "function_call", "call_id": "call_1", "name": "get_weather", "arguments": "{\"city\":\"Paris\"}"},
+                {"type": "function_call_output", "call_id": "call_1", "output": "18C"},
+                {"type": "reasoning", "id": "rs_1", "summary": []}
+            ],
+            "tools": [{"type": "function", "name": "get_weather", "parameters": {"type": "object"}}],
+            "tool_choice": "auto",
+            "max_output_tokens": 256,
+            "stream": true,
+            "text": {"format": {"type": "json_schema", "name": "weather", "schema": {"type": "object"}, "strict": true}}
+        }))
+        .unwrap();
+
+        let chat: ChatCompletionsRequest = request.try_into().unwrap();
+        let chat = serde_json::to_value(&chat).unwrap();
+        assert_eq!(chat["messages"], json!([
+            {"role": "system", "content": "Be brief."},
+            {"role": "user", "content": [
+                {"type": "text", "text": "What is the weather here?"},
+                {"type": "image_url", "image_url": {"url": "https://example.com/paris.png"}}
+            ]},
+            {"role": "assistant", "content": "Let me check.", "tool_calls": [
+                {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}}
+            ]},
+            {"role": "tool", "content": "18C", "tool_call_id": "call_1"}
+        ]));
+        assert_eq!(chat["tools"][0]["function"]["name"], "get_weather");
+        assert_eq!(chat["tool_choice"], "auto");
+        assert_eq!(chat["max_completion_tokens"], 256);
+        assert_eq!(chat["stream_options"], json!({"include_usage": true}));
+        assert_eq!(chat["response_format"], json!({
+            "type": "json_schema",
+            "json_schema": {"name": "weather", "schema": {"type": "object"}, "strict": true}
+        }));
+    }
+
+    #[test]
+    fn test_responses_request_unsupported_features() {
+        let request: ResponsesRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "input": "Hi",
+            "previous_response_id": "resp_1"
+        }))
+        .unwrap();
+        assert!(matches!(ChatCompletionsRequest::try_from(request), Err(TransformError::UnsupportedConversion(_))));
+
+        let request: ResponsesRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "input": "Hi",
+            "tools": [{"type": "web_search_preview"}]
+        }))
+        .unwrap();
+        assert!(matches!(ChatCompletionsRequest::try_from(request), Err(TransformError::UnsupportedConversion(_))));
+    }
+
+    #[test]
+    fn test_responses_to_anthropic_request() {
+        let request: ResponsesRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-0",
+            "instructions": "Be brief.",
+            "input": "Hi"
+        }))
+        .unwrap();
+        let messages: MessagesRequest = request.try_into().unwrap();
+        let messages = serde_json::to_value(&messages).unwrap();
+        assert_eq!(messages["system"], "Be brief.");
+        assert_eq!(messages["messages"][0]["role"], "user");
+    }
+
+    #[test]
+    fn test_openai_to_responses_response() {
+        let response: ChatCompletionsResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "Checking.",
+                    "tool_calls": [{"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{}"}}]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15,
+                      "prompt_tokens_details": {"cached_tokens": 4}}
+        }))
+        .unwrap();
+
+        let response = serde_json::to_value(ResponsesResponse::try_from(response).unwrap()).unwrap();
+        assert_eq!(response, json!({
+            "id": "resp_123",
+            "object": "response",
+            "created_at": 1700000000,
+            "status": "completed",
+            "model": "gpt-4o",
+            "output": [
+                {"type": "message", "id": "msg_123_0", "role": "assistant", "status": "completed",
+                 "content": [{"type": "output_text", "text": "Checking.", "annotations": []}]},
+                {"type": "function_call", "id": "fc_123_1", "call_id": "call_1", "name": "get_weather",
+                 "arguments": "{}", "status": "completed"}
+            ],
+            "usage": {"input_tokens": 10, "input_tokens_details": {"cached_tokens": 4}, "output_tokens": 5, "total_tokens": 15}
+        }));
+    }
+
+    #[test]
+    fn test_responses_stream_translator() {
+        let chunks = [
+            r#"{"id":"chatcmpl-1","object":"chat.completion.chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{"role":"assistant","content":"Hel"}}]}"#,
+            r#"{"id":"chatcmpl-1","object":"chat.completion.chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{"content":"lo"}}]}"#,
+            r#"{"id":"chatcmpl-1","object":"chat.completion.chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"get_weather","arguments":"{\"ci"}}]}}]}"#,
+            r#"{"id":"chatcmpl-1","object":"chat.completion.chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"ty\":1}"}}]},"finish_reason":"length"}]}"#,
+            r#"{"id":"chatcmpl-1","object":"chat.completion.chunk","created":1,"model":"gpt-4o","choices":[],"usage":{"prompt_tokens":3,"completion_tokens":4,"total_tokens":7}}"#,
+            "[DONE]",
+        ];
+        let stream: String = chunks.iter().map(|chunk| format!("data: {}\n\n", chunk)).collect();
+
+        let mut translator = ResponsesStreamTranslator::default();
+        let mut translated = Vec::new();
+        for piece in stream.as_bytes().chunks(50) {
+            translated.extend(translator.translate(piece).unwrap());
+        }
+        let translated = String::from_utf8(translated).unwrap();
+
+        let events: Vec<Value> = translated
+            .split("\n\n")
+            .filter(|event| !event.is_empty())
+            .map(|event| {
+                let (name, data) = event.split_once('\n').unwrap();
+                let data: Value = serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap();
+                assert_eq!(name.strip_prefix("event: ").unwrap(), data["type"]);
+                data
+            })
+            .collect();
+        let types: Vec<&str> = events.iter().map(|event| event["type"].as_str().unwrap()).collect();
+        assert_eq!(types, vec![
+            "response.created",
+            "response.in_progress",
+            "response.output_item.added",
+            "response.content_part.added",
+            "response.output_text.delta",
+            "response.output_text.delta",
+            "response.output_text.done",
+            "response.content_part.done",
+            "response.output_item.done",
+            "response.output_item.added",
+            "response.function_call_arguments.delta",
+            "response.function_call_arguments.delta",
+            "response.function_call_arguments.done",
+            "response.output_item.done",
+            "response.incomplete",
+        ]);
+        for (sequence_number, event) in events.iter().enumerate() {
+            assert_eq!(event["sequence_number"], sequence_number);
+        }
+        assert_eq!(events[6]["text"], "Hello");
+        assert_eq!(events[9]["item"]["call_id"], "call_1");
+        assert_eq!(events[12]["arguments"], "{\"city\":1}");
+
+        let response = &events[14]["response"];
+        assert_eq!(response["status"], "incomplete");
+        assert_eq!(response["incomplete_details"]["reason"], "max_output_tokens");
+        assert_eq!(response["output"].as_array().unwrap().len(), 2);
+        assert_eq!(response["output"][1]["id"], "fc_1_1");
+        assert_eq!(response["usage"]["total_tokens"], 7);
+    }
+}
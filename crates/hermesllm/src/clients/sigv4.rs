@@ -0,0 +1,334 @@
+//! AWS Signature Version 4 signing of outgoing requests
+//!
+//! Signing is pure computation over the request and the time it is signed at, the caller passes
+//! the time in so that requests can be signed without a clock.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// The credentials of an AWS principal
+#[derive(Debug, Clone, PartialEq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Only set for temporary credentials
+    pub session_token: Option<String>,
+}
+
+/// Signs requests to one service in one region
+#[derive(Debug, Clone)]
+pub struct SigV4Signer<'a> {
+    credentials: &'a AwsCredentials,
+    region: &'a str,
+    service: &'a str,
+}
+
+impl<'a> SigV4Signer<'a> {
+    pub fn new(credentials: &'a AwsCredentials, region: &'a str, service: &'a str) -> Self {
+        SigV4Signer { credentials, region, service }
+    }
+
+    /// Signs a request and returns the headers to add to it. `path` may carry a query string and
+    /// is expected to be percent-encoded as it is sent, `headers` must include `host`. Every
+    /// header passed in is signed, so it must reach AWS unchanged.
+    pub fn sign(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+        unix_time: u64,
+    ) -> Vec<(&'static str, String)> {
+        let amz_date = format_amz_date(unix_time);
+        let date = &amz_date[..8];
+
+        let mut signed_headers: Vec<(String, String)> = headers.iter()
+            .map(|(name, value)| (name.to_lowercase(), canonical_header_value(value)))
+            .collect();
+        signed_headers.push(("x-amz-date".to_string(), amz_date.clone()));
+        if let Some(session_token) = self.credentials.session_token.as_ref() {
+            signed_headers.push(("x-amz-security-token".to_string(), session_token.clone()));
+        }
+        signed_headers.sort();
+
+        let (canonical_headers, signed_header_names) = canonical_headers(&signed_headers);
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let canonical_request = [
+            method,
+            &canonical_uri(path),
+            &canonical_query(query),
+            &canonical_headers,
+            &signed_header_names,
+            &hex::encode(Sha256::digest(body)),
+        ]
+        .join("\n");
+
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = [
+            ALGORITHM,
+            &amz_date,
+            &scope,
+            &hex::encode(Sha256::digest(canonical_request.as_bytes())),
+        ]
+        .join("\n");
+        let signature = hex::encode(hmac(&self.signing_key(date), string_to_sign.as_bytes()));
+
+        let mut auth_headers = vec![("x-amz-date", amz_date)];
+        if let Some(session_token) = self.credentials.session_token.as_ref() {
+            auth_headers.push(("x-amz-security-token", session_token.clone()));
+        }
+        auth_headers.push((
+            "authorization",
+            format!(
+                "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+                ALGORITHM, self.credentials.access_key_id, scope, signed_header_names, signature
+            ),
+        ));
+        auth_headers
+    }
+
+    /// The key derived from the secret for the day, region and service
+    fn signing_key(&self, date: &str) -> Vec<u8> {
+        let secret = format!("AWS4{}", self.credentials.secret_access_key);
+        let key = hmac(secret.as_bytes(), date.as_bytes());
+        let key = hmac(&key, self.region.as_bytes());
+        let key = hmac(&key, self.service.as_bytes());
+        hmac(&key, b"aws4_request")
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("hmac accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Formats a unix timestamp as the ISO 8601 basic format AWS expects, e.g. 20150830T123600Z
+fn format_amz_date(unix_time: u64) -> String {
+    let days = (unix_time / 86_400) as i64;
+    let seconds = unix_time % 86_400;
+
+    // converts days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+/// Trims the value and collapses runs of spaces into one
+fn canonical_header_value(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The canonical header block and the list of signed header names, values of repeated headers
+/// are joined with commas
+fn canonical_headers(sorted_headers: &[(String, String)]) -> (String, String) {
+    let mut merged: Vec<(&str, String)> = Vec::new();
+    for (name, value) in sorted_headers {
+        match merged.last_mut() {
+            Some((last_name, last_value)) if last_name == name => {
+                last_value.push(',');
+                last_value.push_str(value);
+            }
+            _ => merged.push((name, value.clone())),
+        }
+    }
+    let canonical = merged.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+    let names = merged.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    (canonical, names)
+}
+
+/// Every service but S3 signs the path encoded a second time
+fn canonical_uri(path: &str) -> String {
+    if path.is_empty() {
+        return "/".to_string();
+    }
+    path.split('/').map(uri_encode).collect::<Vec<_>>().join("/")
+}
+
+/// Query parameters sorted by name and then value, each encoded the same strict way
+fn canonical_query(query: &str) -> String {
+    let mut params: Vec<(String, String)> = query.split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            (uri_encode(&percent_decode(name)), uri_encode(&percent_decode(value)))
+        })
+        .collect();
+    params.sort();
+    params.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&")
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986
+fn uri_encode(value: &str) -> String {
+    value.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte),
+    }).collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex_byte = bytes.get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex_byte {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the credentials and time of the AWS SigV4 test suite and documentation examples
+    const EXAMPLE_TIME: u64 = 1_440_938_160;
+
+    fn example_credentials(session_token: Option<&str>) -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: session_token.map(str::to_string),
+        }
+    }
+
+    fn authorization<'a>(headers: &'a [(&'static str, String)]) -> &'a str {
+        &headers.iter().find(|(name, _)| *name == "authorization").unwrap().1
+    }
+
+    #[test]
+    fn test_format_amz_date() {
+        assert_eq!(format_amz_date(EXAMPLE_TIME), "20150830T123600Z");
+        assert_eq!(format_amz_date(0), "19700101T000000Z");
+        assert_eq!(format_amz_date(951_825_599), "20000229T115959Z");
+        assert_eq!(format_amz_date(1_767_225_599), "20251231T235959Z");
+    }
+
+    #[test]
+    fn test_signing_key() {
+        let credentials = example_credentials(None);
+        let signer = SigV4Signer::new(&credentials, "us-east-1", "iam");
+        assert_eq!(
+            hex::encode(signer.signing_key("20150830")),
+            "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9"
+        );
+    }
+
+    #[test]
+    fn test_get_vanilla() {
+        let credentials = example_credentials(None);
+        let headers = SigV4Signer::new(&credentials, "us-east-1", "service")
+            .sign("GET", "/", &[("Host", "example.amazonaws.com")], b"", EXAMPLE_TIME);
+
+        assert_eq!(headers[0], ("x-amz-date", "20150830T123600Z".to_string()));
+        assert_eq!(
+            authorization(&headers),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_get_vanilla_query_order_key_case() {
+        let credentials = example_credentials(None);
+        let headers = SigV4Signer::new(&credentials, "us-east-1", "service").sign(
+            "GET",
+            "/?Param2=value2&Param1=value1",
+            &[("Host", "example.amazonaws.com")],
+            b"",
+            EXAMPLE_TIME,
+        );
+
+        assert!(authorization(&headers)
+            .ends_with("Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"));
+    }
+
+    #[test]
+    fn test_post_x_www_form_urlencoded() {
+        let credentials = example_credentials(None);
+        let headers = SigV4Signer::new(&credentials, "us-east-1", "service").sign(
+            "POST",
+            "/",
+            &[("Content-Type", "application/x-www-form-urlencoded"), ("Host", "example.amazonaws.com")],
+            b"Param1=value1",
+            EXAMPLE_TIME,
+        );
+
+        assert!(authorization(&headers).contains("SignedHeaders=content-type;host;x-amz-date,"));
+        assert!(authorization(&headers)
+            .ends_with("Signature=ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a"));
+    }
+
+    #[test]
+    fn test_post_sts_header_after() {
+        let session_token = "AQoDYXdzEPT//////////wEXAMPLEtc764bNrC9SAPBSM22wDOk4x4HIZ8j4FZTwdQWLWsKWHGBuFqwAeMicRXmxfpSPfIeoIYRqTflfKD8YUuwthAx7mSEI/qkPpKPi/kMcGdQrmGdeehM4IC1NtBmUpp2wUE8phUZampKsburEDy0KPkyQDYwT7WZ0wq5VSXDvp75YU9HFvlRd8Tx6q6fE8YQcHNVXAkiY9q6d+xo0rKwT38xVqr7ZD0u0iPPkUL64lIZbqBAz+scqKmlzm8FDrypNC9Yjc8fPOLn9FX9KSYvKTr4rvx3iSIlTJabIQwj2ICCR/oLxBA==";
+        let credentials = example_credentials(Some(session_token));
+        let headers = SigV4Signer::new(&credentials, "us-east-1", "service")
+            .sign("POST", "/", &[("Host", "example.amazonaws.com")], b"", EXAMPLE_TIME);
+
+        assert_eq!(headers[1], ("x-amz-security-token", session_token.to_string()));
+        assert!(authorization(&headers).contains("SignedHeaders=host;x-amz-date;x-amz-security-token,"));
+        assert!(authorization(&headers)
+            .ends_with("Signature=85d96828115b5dc0cfc3bd16ad9e210dd772bbebba041836c64533a82be05ead"));
+    }
+
+    #[test]
+    fn test_iam_list_users_example() {
+        let credentials = example_credentials(None);
+        let headers = SigV4Signer::new(&credentials, "us-east-1", "iam").sign(
+            "GET",
+            "/?Action=ListUsers&Version=2010-05-08",
+            &[
+                ("content-type", "application/x-www-form-urlencoded; charset=utf-8"),
+                ("host", "iam.amazonaws.com"),
+            ],
+            b"",
+            EXAMPLE_TIME,
+        );
+
+        assert!(authorization(&headers)
+            .ends_with("Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"));
+    }
+
+    #[test]
+    fn test_canonical_uri_is_encoded_twice() {
+        assert_eq!(
+            canonical_uri("/model/amazon.nova-pro-v1%3A0/converse"),
+            "/model/amazon.nova-pro-v1%253A0/converse"
+        );
+        assert_eq!(canonical_uri(""), "/");
+    }
+}
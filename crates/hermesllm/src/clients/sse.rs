@@ -0,0 +1,188 @@
+//! Incremental decoding of `text/event-stream` bodies
+//!
+//! Follows the event stream interpretation rules of the HTML living standard: lines end in
+//! `\n`, `\r\n` or `\r`, lines starting with `:` are comments, a single space after the field
+//! colon is dropped, consecutive `data:` lines are joined with `\n` and a blank line dispatches
+//! the event.
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+    pub id: Option<String>,
+}
+
+impl SseEvent {
+    /// Whether this is the `[DONE]` sentinel OpenAI compatible APIs end their streams with
+    pub fn is_done(&self) -> bool {
+        self.data.trim() == "[DONE]"
+    }
+}
+
+/// Decodes the events of a stream that arrives in arbitrary chunks
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    // a `\r` that ended the previous chunk, the `\n` of a `\r\n` may start the next one
+    pending_cr: bool,
+    event: Option<String>,
+    data: Option<String>,
+    id: Option<String>,
+}
+
+impl SseDecoder {
+    /// Decodes the complete events in `chunk`, a line or event split across chunks is held back
+    /// until the rest of it arrives.
+    pub fn decode(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut chunk = chunk;
+        if self.pending_cr {
+            self.pending_cr = false;
+            if let Some(rest) = chunk.strip_prefix(b"\n") {
+                chunk = rest;
+            }
+        }
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < self.buffer.len() {
+            let end = i;
+            match self.buffer[i] {
+                b'\n' => i += 1,
+                b'\r' => match self.buffer.get(i + 1) {
+                    Some(b'\n') => i += 2,
+                    Some(_) => i += 1,
+                    None => {
+                        self.pending_cr = true;
+                        i += 1;
+                    }
+                },
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            }
+            let line = String::from_utf8_lossy(&self.buffer[start..end]).into_owned();
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+            start = i;
+        }
+        self.buffer.drain(..start);
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => match self.data.as_mut() {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            // `retry` and unknown fields do not affect how we read the stream
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        let data = self.data.take()?;
+        Some(SseEvent {
+            event,
+            data,
+            id: self.id.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_in_chunks(input: &str, chunk_size: usize) -> Vec<SseEvent> {
+        let mut decoder = SseDecoder::default();
+        input
+            .as_bytes()
+            .chunks(chunk_size)
+            .flat_map(|chunk| decoder.decode(chunk))
+            .collect()
+    }
+
+    #[test]
+    fn test_decodes_events_split_across_chunks() {
+        let input = "data: {\"id\":\"1\"}\n\ndata: {\"id\":\"2\"}\n\ndata: [DONE]\n\n";
+        let expected = decode_in_chunks(input, input.len());
+        assert_eq!(expected.len(), 3);
+        assert_eq!(expected[0].data, r#"{"id":"1"}"#);
+        assert!(expected[2].is_done());
+
+        for chunk_size in 1..input.len() {
+            assert_eq!(decode_in_chunks(input, chunk_size), expected);
+        }
+    }
+
+    #[test]
+    fn test_holds_back_incomplete_event() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.decode(b"data: {\"id\":").is_empty());
+        assert!(decoder.decode(b"\"1\"}\n").is_empty());
+        let events = decoder.decode(b"\n");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, r#"{"id":"1"}"#);
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let input = "event: a\r\ndata: 1\r\n\r\nevent: b\rdata: 2\r\rdata: 3\n\n";
+        for chunk_size in 1..input.len() {
+            let events = decode_in_chunks(input, chunk_size);
+            assert_eq!(events.len(), 3, "chunk size {}", chunk_size);
+            assert_eq!(events[0].event.as_deref(), Some("a"));
+            assert_eq!(events[0].data, "1");
+            assert_eq!(events[1].event.as_deref(), Some("b"));
+            assert_eq!(events[1].data, "2");
+            assert_eq!(events[2].event, None);
+            assert_eq!(events[2].data, "3");
+        }
+    }
+
+    #[test]
+    fn test_comments_fields_and_multi_line_data() {
+        let input = ": keep-alive\n\
+                     event: message_delta\n\
+                     id: 7\n\
+                     retry: 1000\n\
+                     data:first\n\
+                     data:  second\n\
+                     data\n\
+                     \n\
+                     event: ping\n\
+                     \n";
+        let events = decode_in_chunks(input, input.len());
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                event: Some("message_delta".to_string()),
+                data: "first\n second\n".to_string(),
+                id: Some("7".to_string()),
+            }]
+        );
+    }
+}
@@ -43,11 +43,13 @@
 //! ```
 
 use serde_json::Value;
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 // Import centralized types
 use crate::apis::*;
-use super::TransformError;
+use super::bedrock_transformer::error_event;
+use super::{SseDecoder, TransformError};
 
 // ============================================================================
 // CONSTANTS
@@ -168,6 +170,7 @@ impl TryFrom<MessagesResponse> for ChatCompletionsResponse {
 
     fn try_from(resp: MessagesResponse) -> Result<Self, Self::Error> {
         let content = convert_anthropic_content_to_openai(&resp.content)?;
+        let thinking_blocks = extract_thinking_blocks(&resp.content);
         let finish_reason: FinishReason = resp.stop_reason.into();
         let tool_calls = resp.content.extract_tool_calls()?;
 
@@ -188,6 +191,8 @@ impl TryFrom<MessagesResponse> for ChatCompletionsResponse {
             audio: None,
             function_call: None,
             tool_calls,
+            reasoning_content: reasoning_text(&thinking_blocks),
+            thinking_blocks: if thinking_blocks.is_empty() { None } else { Some(thinking_blocks) },
         };
 
         let choice = Choice {
@@ -197,13 +202,7 @@ impl TryFrom<MessagesResponse> for ChatCompletionsResponse {
             logprobs: None,
         };
 
-        let usage = Usage {
-            prompt_tokens: resp.usage.input_tokens,
-            completion_tokens: resp.usage.output_tokens,
-            total_tokens: resp.usage.input_tokens + resp.usage.output_tokens,
-            prompt_tokens_details: None,
-            completion_tokens_details: None,
-        };
+        let usage = resp.usage.into();
 
         Ok(ChatCompletionsResponse {
             id: resp.id,
@@ -229,12 +228,7 @@ impl TryFrom<ChatCompletionsResponse> for MessagesResponse {
             .map(|fr| fr.into())
             .unwrap_or(MessagesStopReason::EndTurn);
 
-        let usage = MessagesUsage {
-            input_tokens: resp.usage.prompt_tokens,
-            output_tokens: resp.usage.completion_tokens,
-            cache_creation_input_tokens: None,
-            cache_read_input_tokens: None,
-        };
+        let usage = resp.usage.into();
 
         Ok(MessagesResponse {
             id: resp.id,
@@ -269,6 +263,8 @@ impl TryFrom<MessagesStreamEvent> for ChatCompletionsStreamResponse {
                         refusal: None,
                         function_call: None,
                         tool_calls: None,
+                        reasoning_content: None,
+                        thinking_blocks: None,
                     },
                     None,
                     None,
@@ -300,6 +296,8 @@ impl TryFrom<MessagesStreamEvent> for ChatCompletionsStreamResponse {
                         refusal: None,
                         function_call: None,
                         tool_calls: None,
+                        reasoning_content: None,
+                        thinking_blocks: None,
                     },
                     finish_reason,
                     openai_usage,
@@ -316,6 +314,8 @@ impl TryFrom<MessagesStreamEvent> for ChatCompletionsStreamResponse {
                         refusal: None,
                         function_call: None,
                         tool_calls: None,
+                        reasoning_content: None,
+                        thinking_blocks: None,
                     },
                     Some(FinishReason::Stop),
                     None,
@@ -432,6 +432,7 @@ impl Into<Message> for MessagesSystemPrompt {
             name: None,
             tool_calls: None,
             tool_call_id: None,
+            thinking_blocks: None,
         }
     }
 }
@@ -461,6 +462,7 @@ impl TryFrom<MessagesMessage> for Vec<Message> {
                     name: None,
                     tool_calls: None,
                     tool_call_id: None,
+                    thinking_blocks: None,
                 });
             }
             MessagesMessageContent::Blocks(blocks) => {
@@ -474,6 +476,7 @@ impl TryFrom<MessagesMessage> for Vec<Message> {
                     name: None,
                     tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
                     tool_call_id: None,
+                    thinking_blocks: None,
                 };
                 result.push(main_message);
 
@@ -485,6 +488,7 @@ impl TryFrom<MessagesMessage> for Vec<Message> {
                         name: None,
                         tool_calls: None,
                         tool_call_id: Some(tool_use_id),
+                        thinking_blocks: None,
                     });
                 }
             }
@@ -675,11 +679,20 @@ impl Into<MessagesStopReason> for FinishReason {
 // Usage Conversions
 impl Into<Usage> for MessagesUsage {
     fn into(self) -> Usage {
+        // Anthropic's input tokens exclude the tokens read from and written to the prompt cache,
+        // OpenAI's prompt tokens include the cached ones
+        let cache_read_tokens = self.cache_read_input_tokens.unwrap_or(0);
+        let prompt_tokens = self.input_tokens
+            + cache_read_tokens
+            + self.cache_creation_input_tokens.unwrap_or(0);
         Usage {
-            prompt_tokens: self.input_tokens,
+            prompt_tokens,
             completion_tokens: self.output_tokens,
-            total_tokens: self.input_tokens + self.output_tokens,
-            prompt_tokens_details: None,
+            total_tokens: prompt_tokens + self.output_tokens,
+            prompt_tokens_details: self.cache_read_input_tokens.map(|_| PromptTokensDetails {
+                cached_tokens: Some(cache_read_tokens),
+                audio_tokens: None,
+            }),
             completion_tokens_details: None,
         }
     }
@@ -687,15 +700,280 @@ impl Into<Usage> for MessagesUsage {
 
 impl Into<MessagesUsage> for Usage {
     fn into(self) -> MessagesUsage {
+        let cached_tokens = self.prompt_tokens_details.and_then(|details| details.cached_tokens);
         MessagesUsage {
-            input_tokens: self.prompt_tokens,
+            input_tokens: self.prompt_tokens.saturating_sub(cached_tokens.unwrap_or(0)),
             output_tokens: self.completion_tokens,
             cache_creation_input_tokens: None,
-            cache_read_input_tokens: None,
+            cache_read_input_tokens: cached_tokens,
         }
     }
 }
 
+// ============================================================================
+// BODY TRANSLATION - Raw provider bodies to OpenAI bodies
+// ============================================================================
+
+/// Translates a whole Anthropic Messages response body into an OpenAI chat completions body
+pub fn translate_messages_response(body: &[u8]) -> Result<Vec<u8>, TransformError> {
+    let response: MessagesResponse = serde_json::from_slice(body)?;
+    let response: ChatCompletionsResponse = response.try_into()?;
+    Ok(serde_json::to_vec(&response)?)
+}
+
+/// Translates the server-sent events of an Anthropic Messages stream into OpenAI chat completion
+/// chunks. The stream is stateful: the id, model and input usage only arrive with `message_start`,
+/// and tool calls are numbered by their order rather than by their content block.
+#[derive(Debug, Default)]
+pub struct MessagesStreamTranslator {
+    decoder: SseDecoder,
+    skipped_events: usize,
+    id: String,
+    model: String,
+    input_usage: MessagesUsage,
+    tool_call_indexes: HashMap<u32, u32>,
+}
+
+impl MessagesStreamTranslator {
+    /// Translates the complete events in `chunk`, an event split across chunks is held back until
+    /// the rest of it arrives. `event:` lines are dropped since OpenAI streams only carry `data:` lines.
+    /// Errors raised mid-stream are sent on as OpenAI error events, events that can't be translated
+    /// are left out.
+    pub fn translate(&mut self, chunk: &[u8]) -> Result<Vec<u8>, TransformError> {
+        let mut translated = String::new();
+        for sse_event in self.decoder.decode(chunk) {
+            let data = if sse_event.event.as_deref() == Some("error") {
+                serde_json::from_str::<Value>(&sse_event.data).ok().map(|error| {
+                    error_event(error["error"]["type"].as_str(), error["error"]["message"].as_str())
+                })
+            } else {
+                serde_json::from_str(&sse_event.data)
+                    .map_err(TransformError::from)
+                    .and_then(|event| self.translate_event(event))
+                    .unwrap_or_else(|_| {
+                        self.skipped_events += 1;
+                        None
+                    })
+            };
+            if let Some(data) = data {
+                translated.push_str("data: ");
+                translated.push_str(&data);
+                translated.push_str("\n\n");
+            }
+        }
+        Ok(translated.into_bytes())
+    }
+
+    /// Number of events left out since the last call because they could not be translated.
+    pub fn take_skipped_events(&mut self) -> usize {
+        std::mem::take(&mut self.skipped_events)
+    }
+
+    fn translate_event(&mut self, event: MessagesStreamEvent) -> Result<Option<String>, TransformError> {
+        let tool_call_index = match &event {
+            MessagesStreamEvent::MessageStart { message } => {
+                self.id = message.id.clone();
+                self.model = message.model.clone();
+                self.input_usage = message.usage.clone();
+                None
+            }
+            MessagesStreamEvent::MessageStop => return Ok(Some("[DONE]".to_string())),
+            MessagesStreamEvent::Ping | MessagesStreamEvent::ContentBlockStop { .. } => return Ok(None),
+            MessagesStreamEvent::ContentBlockStart { index, content_block } => match content_block {
+                MessagesContentBlock::ToolUse { .. }
+                | MessagesContentBlock::ServerToolUse { .. }
+                | MessagesContentBlock::McpToolUse { .. } => {
+                    let tool_call_index = self.tool_call_indexes.len() as u32;
+                    self.tool_call_indexes.insert(*index, tool_call_index);
+                    Some(tool_call_index)
+                }
+                _ => None,
+            },
+            MessagesStreamEvent::ContentBlockDelta { index, .. } => self.tool_call_indexes.get(index).copied(),
+            MessagesStreamEvent::MessageDelta { .. } => None,
+        };
+
+        let event = match event {
+            MessagesStreamEvent::MessageDelta { delta, mut usage } => {
+                // the final usage only counts the output, the input was reported on message_start
+                usage.input_tokens = usage.input_tokens.max(self.input_usage.input_tokens);
+                usage.cache_read_input_tokens = usage.cache_read_input_tokens.or(self.input_usage.cache_read_input_tokens);
+                usage.cache_creation_input_tokens = usage.cache_creation_input_tokens.or(self.input_usage.cache_creation_input_tokens);
+                MessagesStreamEvent::MessageDelta { delta, usage }
+            }
+            event => event,
+        };
+
+        let mut chunk: ChatCompletionsStreamResponse = event.try_into()?;
+        let is_empty = chunk.usage.is_none()
+            && chunk.choices.iter().all(|choice| {
+                choice.finish_reason.is_none()
+                    && choice.delta.role.is_none()
+                    && choice.delta.content.is_none()
+                    && choice.delta.tool_calls.is_none()
+                    && choice.delta.reasoning_content.is_none()
+                    && choice.delta.thinking_blocks.is_none()
+            });
+        if is_empty {
+            return Ok(None);
+        }
+
+        chunk.id = self.id.clone();
+        chunk.model = self.model.clone();
+        if let Some(tool_call_index) = tool_call_index {
+            for choice in chunk.choices.iter_mut() {
+                for tool_call in choice.delta.tool_calls.iter_mut().flatten() {
+                    tool_call.index = tool_call_index;
+                }
+            }
+        }
+        Ok(Some(serde_json::to_string(&chunk)?))
+    }
+}
+
+/// Translates a whole OpenAI chat completions response body into an Anthropic Messages body
+pub fn translate_chat_completions_response(body: &[u8]) -> Result<Vec<u8>, TransformError> {
+    let response: ChatCompletionsResponse = serde_json::from_slice(body)?;
+    let response: MessagesResponse = response.try_into()?;
+    Ok(serde_json::to_vec(&response)?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StreamBlock {
+    Text,
+    ToolUse { tool_call_index: u32 },
+}
+
+/// Translates the chunks of an OpenAI chat completions stream into Anthropic Messages events.
+/// Anthropic streams frame every content block with start and stop events and only report the
+/// stop reason and usage once the message is done, so both are held until `[DONE]`.
+#[derive(Debug, Default)]
+pub struct ChatCompletionsStreamTranslator {
+    decoder: SseDecoder,
+    skipped_events: usize,
+    started: bool,
+    block: Option<StreamBlock>,
+    block_index: u32,
+    stop_reason: Option<MessagesStopReason>,
+    usage: MessagesUsage,
+}
+
+impl ChatCompletionsStreamTranslator {
+    /// Translates the complete chunks in `chunk`, a chunk split across calls is held back until
+    /// the rest of it arrives.
+    pub fn translate(&mut self, chunk: &[u8]) -> Result<Vec<u8>, TransformError> {
+        let mut events = Vec::new();
+        for sse_event in self.decoder.decode(chunk) {
+            if sse_event.is_done() {
+                self.finish(&mut events);
+                continue;
+            }
+            match serde_json::from_str::<ChatCompletionsStreamResponse>(&sse_event.data) {
+                Ok(chunk) => self.translate_chunk(chunk, &mut events),
+                Err(_) => self.skipped_events += 1,
+            }
+        }
+
+        let mut translated = String::new();
+        for event in events {
+            let data = serde_json::to_value(&event)?;
+            translated.push_str("event: ");
+            translated.push_str(data["type"].as_str().unwrap_or_default());
+            translated.push_str("\ndata: ");
+            translated.push_str(&data.to_string());
+            translated.push_str("\n\n");
+        }
+        Ok(translated.into_bytes())
+    }
+
+    /// Number of events left out since the last call because they could not be translated.
+    pub fn take_skipped_events(&mut self) -> usize {
+        std::mem::take(&mut self.skipped_events)
+    }
+
+    fn translate_chunk(&mut self, chunk: ChatCompletionsStreamResponse, events: &mut Vec<MessagesStreamEvent>) {
+        if !self.started {
+            self.started = true;
+            events.push(MessagesStreamEvent::MessageStart {
+                message: MessagesStreamMessage {
+                    id: chunk.id.clone(),
+                    obj_type: "message".to_string(),
+                    role: MessagesRole::Assistant,
+                    content: vec![],
+                    model: chunk.model.clone(),
+                    stop_reason: None,
+                    stop_sequence: None,
+                    usage: MessagesUsage::default(),
+                },
+            });
+        }
+
+        if let Some(usage) = chunk.usage {
+            self.usage = usage.into();
+        }
+
+        let Some(choice) = chunk.choices.into_iter().next() else {
+            return;
+        };
+        if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+            if self.block != Some(StreamBlock::Text) {
+                self.start_block(StreamBlock::Text, MessagesContentBlock::Text { text: String::new() }, events);
+            }
+            events.push(MessagesStreamEvent::ContentBlockDelta {
+                index: self.block_index,
+                delta: MessagesContentDelta::TextDelta { text },
+            });
+        }
+        for tool_call in choice.delta.tool_calls.into_iter().flatten() {
+            let block = StreamBlock::ToolUse { tool_call_index: tool_call.index };
+            let function = tool_call.function.unwrap_or(FunctionCallDelta { name: None, arguments: None });
+            if self.block != Some(block) || tool_call.id.is_some() {
+                let content_block = MessagesContentBlock::ToolUse {
+                    id: tool_call.id.unwrap_or_default(),
+                    name: function.name.unwrap_or_default(),
+                    input: Value::Object(Default::default()),
+                };
+                self.start_block(block, content_block, events);
+            }
+            if let Some(arguments) = function.arguments.filter(|arguments| !arguments.is_empty()) {
+                events.push(MessagesStreamEvent::ContentBlockDelta {
+                    index: self.block_index,
+                    delta: MessagesContentDelta::InputJsonDelta { partial_json: arguments },
+                });
+            }
+        }
+        if let Some(finish_reason) = choice.finish_reason {
+            self.stop_reason = Some(finish_reason.into());
+        }
+    }
+
+    fn start_block(&mut self, block: StreamBlock, content_block: MessagesContentBlock, events: &mut Vec<MessagesStreamEvent>) {
+        if self.block.is_some() {
+            events.push(MessagesStreamEvent::ContentBlockStop { index: self.block_index });
+            self.block_index += 1;
+        }
+        self.block = Some(block);
+        events.push(MessagesStreamEvent::ContentBlockStart {
+            index: self.block_index,
+            content_block,
+        });
+    }
+
+    fn finish(&mut self, events: &mut Vec<MessagesStreamEvent>) {
+        if self.block.take().is_some() {
+            events.push(MessagesStreamEvent::ContentBlockStop { index: self.block_index });
+        }
+        events.push(MessagesStreamEvent::MessageDelta {
+            delta: MessagesMessageDelta {
+                stop_reason: self.stop_reason.take().unwrap_or(MessagesStopReason::EndTurn),
+                stop_sequence: None,
+            },
+            usage: std::mem::take(&mut self.usage),
+        });
+        events.push(MessagesStreamEvent::MessageStop);
+    }
+}
+
 // ============================================================================
 // HELPER FUNCTIONS - Organized by domain
 // ============================================================================
@@ -732,6 +1010,14 @@ fn create_openai_chunk(
 
 /// Helper to create empty OpenAI streaming chunk
 fn create_empty_openai_chunk() -> ChatCompletionsStreamResponse {
+    create_reasoning_chunk(None, None)
+}
+
+/// Helper to create OpenAI streaming chunk carrying only the model's reasoning
+fn create_reasoning_chunk(
+    reasoning_content: Option<String>,
+    thinking_blocks: Option<Vec<ThinkingBlock>>
+) -> ChatCompletionsStreamResponse {
     create_openai_chunk(
         "stream",
         "unknown",
@@ -741,6 +1027,8 @@ fn create_empty_openai_chunk() -> ChatCompletionsStreamResponse {
             refusal: None,
             function_call: None,
             tool_calls: None,
+            reasoning_content,
+            thinking_blocks,
         },
         None,
         None,
@@ -869,10 +1157,6 @@ fn convert_anthropic_content_to_openai(content: &[MessagesContentBlock]) -> Resu
             MessagesContentBlock::Text { text } => {
                 text_parts.push(text.clone());
             }
-            MessagesContentBlock::Thinking { text } => {
-                // Include thinking as regular text for OpenAI
-                text_parts.push(format!("[Thinking: {}]", text));
-            }
             _ => {
                 // Skip other content types for basic text conversion
                 continue;
@@ -883,10 +1167,48 @@ fn convert_anthropic_content_to_openai(content: &[MessagesContentBlock]) -> Resu
     Ok(MessageContent::Text(text_parts.join("\n")))
 }
 
+/// Collect the thinking blocks of an Anthropic response in the order they were returned
+fn extract_thinking_blocks(content: &[MessagesContentBlock]) -> Vec<ThinkingBlock> {
+    content
+        .iter()
+        .filter_map(|block| match block {
+            MessagesContentBlock::Thinking { text, signature } => Some(ThinkingBlock::Thinking {
+                thinking: text.clone(),
+                signature: signature.clone(),
+            }),
+            MessagesContentBlock::RedactedThinking { data } => Some(ThinkingBlock::RedactedThinking { data: data.clone() }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Join the text of thinking blocks into the `reasoning_content` OpenAI compatible clients read
+fn reasoning_text(blocks: &[ThinkingBlock]) -> Option<String> {
+    let texts: Vec<&str> = blocks
+        .iter()
+        .filter_map(|block| match block {
+            ThinkingBlock::Thinking { thinking, .. } => Some(thinking.as_str()),
+            ThinkingBlock::RedactedThinking { .. } => None,
+        })
+        .collect();
+    if texts.is_empty() { None } else { Some(texts.join("\n")) }
+}
+
 /// Convert OpenAI message to Anthropic content blocks
 fn convert_openai_message_to_anthropic_content(message: &Message) -> Result<Vec<MessagesContentBlock>, TransformError> {
     let mut blocks = Vec::new();
 
+    // Anthropic expects the reasoning of a previous turn, with its signature, before the rest of it
+    for block in message.thinking_blocks.iter().flatten() {
+        blocks.push(match block {
+            ThinkingBlock::Thinking { thinking, signature } => MessagesContentBlock::Thinking {
+                text: thinking.clone(),
+                signature: signature.clone(),
+            },
+            ThinkingBlock::RedactedThinking { data } => MessagesContentBlock::RedactedThinking { data: data.clone() },
+        });
+    }
+
     // Handle regular content
     match &message.content {
         MessageContent::Text(text) => {
@@ -967,6 +1289,17 @@ fn convert_content_block_start(content_block: MessagesContentBlock) -> Result<Ch
             // No immediate output for text block start
             Ok(create_empty_openai_chunk())
         }
+        MessagesContentBlock::Thinking { text, signature } => {
+            // The thinking usually arrives in deltas, the block start only carries what is already known
+            let reasoning_content = if text.is_empty() { None } else { Some(text.clone()) };
+            let thinking_blocks = signature
+                .filter(|signature| !signature.is_empty())
+                .map(|signature| vec![ThinkingBlock::Thinking { thinking: text, signature: Some(signature) }]);
+            Ok(create_reasoning_chunk(reasoning_content, thinking_blocks))
+        }
+        MessagesContentBlock::RedactedThinking { data } => {
+            Ok(create_reasoning_chunk(None, Some(vec![ThinkingBlock::RedactedThinking { data }])))
+        }
         MessagesContentBlock::ToolUse { id, name, .. } |
         MessagesContentBlock::ServerToolUse { id, name, .. } |
         MessagesContentBlock::McpToolUse { id, name, .. } => {
@@ -988,6 +1321,8 @@ fn convert_content_block_start(content_block: MessagesContentBlock) -> Result<Ch
                             arguments: Some("".to_string()),
                         }),
                     }]),
+                    reasoning_content: None,
+                    thinking_blocks: None,
                 },
                 None,
                 None,
@@ -1010,11 +1345,23 @@ fn convert_content_delta(delta: MessagesContentDelta) -> Result<ChatCompletionsS
                     refusal: None,
                     function_call: None,
                     tool_calls: None,
+                    reasoning_content: None,
+                    thinking_blocks: None,
                 },
                 None,
                 None,
             ))
         }
+        MessagesContentDelta::ThinkingDelta { thinking } => {
+            Ok(create_reasoning_chunk(Some(thinking), None))
+        }
+        MessagesContentDelta::SignatureDelta { signature } => {
+            // The signature closes the thinking block its text was streamed in
+            Ok(create_reasoning_chunk(None, Some(vec![ThinkingBlock::Thinking {
+                thinking: String::new(),
+                signature: Some(signature),
+            }])))
+        }
         MessagesContentDelta::InputJsonDelta { partial_json } => {
             Ok(create_openai_chunk(
                 "stream",
@@ -1033,6 +1380,8 @@ fn convert_content_delta(delta: MessagesContentDelta) -> Result<ChatCompletionsS
                             arguments: Some(partial_json),
                         }),
                     }]),
+                    reasoning_content: None,
+                    thinking_blocks: None,
                 },
                 None,
                 None,
@@ -1211,6 +1560,7 @@ mod tests {
                 name: None,
                 tool_calls: None,
                 tool_call_id: None,
+                thinking_blocks: None,
             }],
             max_tokens: None, // No max_tokens specified
             ..Default::default()
@@ -1221,6 +1571,37 @@ mod tests {
         assert_eq!(anthropic_req.max_tokens, DEFAULT_MAX_TOKENS);
     }
 
+    #[test]
+    fn test_thinking_blocks_sent_back_to_anthropic() {
+        let openai_req: ChatCompletionsRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": [
+                {"role": "user", "content": "What's the weather in Paris?"},
+                {
+                    "role": "assistant",
+                    "content": "Let me check.",
+                    "thinking_blocks": [
+                        {"type": "thinking", "thinking": "The user wants the weather", "signature": "sig"},
+                        {"type": "redacted_thinking", "data": "opaque"}
+                    ]
+                }
+            ]
+        })).unwrap();
+
+        let anthropic_req: AnthropicMessagesRequest = openai_req.try_into().unwrap();
+
+        let MessagesMessageContent::Blocks(blocks) = &anthropic_req.messages[1].content else {
+            panic!("expected content blocks, got {:?}", anthropic_req.messages[1].content);
+        };
+        assert_eq!(blocks.len(), 3);
+        assert!(matches!(
+            &blocks[0],
+            MessagesContentBlock::Thinking { text, signature: Some(signature) } if text == "The user wants the weather" && signature == "sig"
+        ));
+        assert!(matches!(&blocks[1], MessagesContentBlock::RedactedThinking { data } if data == "opaque"));
+        assert!(matches!(&blocks[2], MessagesContentBlock::Text { text } if text == "Let me check."));
+    }
+
     #[test]
     fn test_anthropic_message_start_streaming() {
         let event = MessagesStreamEvent::MessageStart {
@@ -1384,6 +1765,8 @@ mod tests {
                     refusal: None,
                     function_call: None,
                     tool_calls: None,
+                    reasoning_content: None,
+                    thinking_blocks: None,
                 },
                 finish_reason: None,
                 logprobs: None,
@@ -1420,6 +1803,8 @@ mod tests {
                     refusal: None,
                     function_call: None,
                     tool_calls: None,
+                    reasoning_content: None,
+                    thinking_blocks: None,
                 },
                 finish_reason: None,
                 logprobs: None,
@@ -1468,6 +1853,8 @@ mod tests {
                             arguments: Some("".to_string()),
                         }),
                     }]),
+                    reasoning_content: None,
+                    thinking_blocks: None,
                 },
                 finish_reason: None,
                 logprobs: None,
@@ -1509,6 +1896,8 @@ mod tests {
                     refusal: None,
                     function_call: None,
                     tool_calls: None,
+                    reasoning_content: None,
+                    thinking_blocks: None,
                 },
                 finish_reason: Some(FinishReason::Stop),
                 logprobs: None,
@@ -1689,6 +2078,8 @@ mod tests {
                     refusal: None,
                     function_call: None,
                     tool_calls: None,
+                    reasoning_content: None,
+                    thinking_blocks: None,
                 },
                 finish_reason: None,
                 logprobs: None,
@@ -1719,4 +2110,279 @@ mod tests {
         assert_eq!(choice.delta.tool_calls, None);
         assert_eq!(choice.finish_reason, None);
     }
+
+    #[test]
+    fn test_translate_messages_response_with_cache_usage() {
+        let body = json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-20250514",
+            "content": [
+                {"type": "thinking", "thinking": "The user wants the weather", "signature": "sig"},
+                {"type": "text", "text": "Let me check."},
+                {"type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {"city": "Paris"}}
+            ],
+            "stop_reason": "tool_use",
+            "stop_sequence": null,
+            "usage": {
+                "input_tokens": 10,
+                "output_tokens": 20,
+                "cache_creation_input_tokens": 5,
+                "cache_read_input_tokens": 100
+            }
+        });
+
+        let translated = translate_messages_response(body.to_string().as_bytes()).unwrap();
+        let translated: serde_json::Value = serde_json::from_slice(&translated).unwrap();
+
+        assert_eq!(translated["id"], "msg_01");
+        assert_eq!(
+            translated["choices"][0]["message"]["content"],
+            "Let me check."
+        );
+        assert_eq!(
+            translated["choices"][0]["message"]["reasoning_content"],
+            "The user wants the weather"
+        );
+        assert_eq!(
+            translated["choices"][0]["message"]["thinking_blocks"],
+            json!([{"type": "thinking", "thinking": "The user wants the weather", "signature": "sig"}])
+        );
+        assert_eq!(translated["choices"][0]["finish_reason"], "tool_calls");
+        let tool_call = &translated["choices"][0]["message"]["tool_calls"][0];
+        assert_eq!(tool_call["id"], "toolu_01");
+        assert_eq!(tool_call["function"]["name"], "get_weather");
+        assert_eq!(translated["usage"]["prompt_tokens"], 115);
+        assert_eq!(translated["usage"]["completion_tokens"], 20);
+        assert_eq!(translated["usage"]["total_tokens"], 135);
+        assert_eq!(translated["usage"]["prompt_tokens_details"]["cached_tokens"], 100);
+    }
+
+    #[test]
+    fn test_messages_stream_translator() {
+        let events = [
+            json!({"type": "message_start", "message": {"id": "msg_01", "type": "message", "role": "assistant", "model": "claude-sonnet-4-20250514", "content": [], "stop_reason": null, "stop_sequence": null, "usage": {"input_tokens": 10, "output_tokens": 1, "cache_read_input_tokens": 100}}}),
+            json!({"type": "ping"}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": "", "signature": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "weather for two cities"}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "signature_delta", "signature": "sig"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"city\": \"Paris\"}"}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "content_block_start", "index": 2, "content_block": {"type": "tool_use", "id": "toolu_02", "name": "get_weather", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "input_json_delta", "partial_json": "{\"city\": \"Rome\"}"}}),
+            json!({"type": "content_block_stop", "index": 2}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use", "stop_sequence": null}, "usage": {"output_tokens": 42}}),
+            json!({"type": "message_stop"}),
+        ];
+        let stream: String = events
+            .iter()
+            .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+            .collect();
+
+        // split the stream mid event, the partial event is held back until the rest arrives
+        let (first, second) = stream.as_bytes().split_at(stream.len() / 2);
+        let mut translator = MessagesStreamTranslator::default();
+        let mut translated = translator.translate(first).unwrap();
+        translated.extend(translator.translate(second).unwrap());
+        let translated = String::from_utf8(translated).unwrap();
+
+        let chunks: Vec<&str> = translated
+            .split("\n\n")
+            .filter(|chunk| !chunk.is_empty())
+            .map(|chunk| chunk.strip_prefix("data: ").unwrap())
+            .collect();
+        assert_eq!(chunks.last(), Some(&"[DONE]"));
+
+        let chunks: Vec<serde_json::Value> = chunks[..chunks.len() - 1]
+            .iter()
+            .map(|chunk| serde_json::from_str(chunk).unwrap())
+            .collect();
+        assert!(chunks.iter().all(|chunk| chunk["id"] == "msg_01"));
+        assert!(chunks.iter().all(|chunk| chunk["model"] == "claude-sonnet-4-20250514"));
+        let reasoning: Vec<&serde_json::Value> = chunks
+            .iter()
+            .map(|chunk| &chunk["choices"][0]["delta"])
+            .filter(|delta| delta.get("reasoning_content").is_some() || delta.get("thinking_blocks").is_some())
+            .collect();
+        assert_eq!(reasoning.len(), 2);
+        assert_eq!(reasoning[0]["reasoning_content"], "weather for two cities");
+        assert_eq!(reasoning[1]["thinking_blocks"], json!([{"type": "thinking", "thinking": "", "signature": "sig"}]));
+
+        let tool_calls: Vec<&serde_json::Value> = chunks
+            .iter()
+            .filter_map(|chunk| chunk["choices"][0]["delta"]["tool_calls"].get(0))
+            .collect();
+        assert_eq!(tool_calls.len(), 4);
+        assert_eq!(tool_calls[0]["index"], 0);
+        assert_eq!(tool_calls[0]["id"], "toolu_01");
+        assert_eq!(tool_calls[1]["index"], 0);
+        assert_eq!(tool_calls[1]["function"]["arguments"], "{\"city\": \"Paris\"}");
+        assert_eq!(tool_calls[2]["index"], 1);
+        assert_eq!(tool_calls[2]["id"], "toolu_02");
+        assert_eq!(tool_calls[3]["index"], 1);
+
+        let last = chunks.last().unwrap();
+        assert_eq!(last["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(last["usage"]["prompt_tokens"], 110);
+        assert_eq!(last["usage"]["completion_tokens"], 42);
+        assert_eq!(last["usage"]["prompt_tokens_details"]["cached_tokens"], 100);
+    }
+
+    #[test]
+    fn test_messages_stream_translator_multi_line_data() {
+        // an event's data may span several lines, and lines may end in \r\n
+        let stream = "event: message_start\r\n\
+                      data: {\"type\": \"message_start\", \"message\": {\"id\": \"msg_01\", \"type\": \"message\", \"role\": \"assistant\",\r\n\
+                      data: \"model\": \"claude-sonnet-4-20250514\", \"content\": [], \"stop_reason\": null, \"stop_sequence\": null, \"usage\": {\"input_tokens\": 10, \"output_tokens\": 1}}}\r\n\
+                      \r\n\
+                      event: content_block_delta\r\n\
+                      data: {\"type\": \"content_block_delta\", \"index\": 0,\r\n\
+                      data: \"delta\": {\"type\": \"text_delta\", \"text\": \"Hello\"}}\r\n\
+                      \r\n";
+
+        let mut translator = MessagesStreamTranslator::default();
+        let translated = String::from_utf8(translator.translate(stream.as_bytes()).unwrap()).unwrap();
+        let chunks: Vec<serde_json::Value> = translated
+            .split("\n\n")
+            .filter(|chunk| !chunk.is_empty())
+            .map(|chunk| serde_json::from_str(chunk.strip_prefix("data: ").unwrap()).unwrap())
+            .collect();
+
+        let last = chunks.last().unwrap();
+        assert_eq!(last["id"], "msg_01");
+        assert_eq!(last["choices"][0]["delta"]["content"], "Hello");
+    }
+
+    #[test]
+    fn test_messages_stream_translator_skips_untranslatable_events() {
+        let events = [
+            ("message_start", json!({"type": "message_start", "message": {"id": "msg_01", "type": "message", "role": "assistant", "model": "claude-sonnet-4-20250514", "content": [], "stop_reason": null, "stop_sequence": null, "usage": {"input_tokens": 10, "output_tokens": 1}}})),
+            ("content_block_delta", json!({"type": "content_block_delta", "index": 0, "delta": {"type": "unknown_delta"}})),
+            ("content_block_delta", json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hello"}})),
+            ("error", json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}})),
+        ];
+        let stream: String = events
+            .iter()
+            .map(|(event_type, event)| format!("event: {}\ndata: {}\n\n", event_type, event))
+            .collect();
+
+        let mut translator = MessagesStreamTranslator::default();
+        let translated = String::from_utf8(translator.translate(stream.as_bytes()).unwrap()).unwrap();
+        assert_eq!(translator.take_skipped_events(), 1);
+        assert_eq!(translator.take_skipped_events(), 0);
+
+        let chunks: Vec<serde_json::Value> = translated
+            .split("\n\n")
+            .filter(|chunk| !chunk.is_empty())
+            .map(|chunk| serde_json::from_str(chunk.strip_prefix("data: ").unwrap()).unwrap())
+            .collect();
+        // the events around the one that could not be translated are kept
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[1]["choices"][0]["delta"]["content"], "Hello");
+        assert_eq!(chunks[2]["error"]["type"], "overloaded_error");
+        assert_eq!(chunks[2]["error"]["message"], "Overloaded");
+    }
+
+    #[test]
+    fn test_translate_chat_completions_response_with_cached_usage() {
+        let body = json!({
+            "id": "chatcmpl-01",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello!"},
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": 120,
+                "completion_tokens": 5,
+                "total_tokens": 125,
+                "prompt_tokens_details": {"cached_tokens": 100}
+            }
+        });
+
+        let translated = translate_chat_completions_response(body.to_string().as_bytes()).unwrap();
+        let translated: serde_json::Value = serde_json::from_slice(&translated).unwrap();
+
+        assert_eq!(translated["type"], "message");
+        assert_eq!(translated["content"][0]["type"], "text");
+        assert_eq!(translated["content"][0]["text"], "Hello!");
+        assert_eq!(translated["stop_reason"], "end_turn");
+        assert_eq!(translated["usage"]["input_tokens"], 20);
+        assert_eq!(translated["usage"]["cache_read_input_tokens"], 100);
+        assert_eq!(translated["usage"]["output_tokens"], 5);
+    }
+
+    #[test]
+    fn test_chat_completions_stream_translator() {
+        let chunks = [
+            json!({"id": "chatcmpl-01", "object": "chat.completion.chunk", "created": 1, "model": "gpt-4o", "choices": [{"index": 0, "delta": {"role": "assistant", "content": ""}, "finish_reason": null}]}),
+            json!({"id": "chatcmpl-01", "object": "chat.completion.chunk", "created": 1, "model": "gpt-4o", "choices": [{"index": 0, "delta": {"content": "Checking"}, "finish_reason": null}]}),
+            json!({"id": "chatcmpl-01", "object": "chat.completion.chunk", "created": 1, "model": "gpt-4o", "choices": [{"index": 0, "delta": {"tool_calls": [{"index": 0, "id": "call_01", "type": "function", "function": {"name": "get_weather", "arguments": ""}}]}, "finish_reason": null}]}),
+            json!({"id": "chatcmpl-01", "object": "chat.completion.chunk", "created": 1, "model": "gpt-4o", "choices": [{"index": 0, "delta": {"tool_calls": [{"index": 0, "function": {"arguments": "{\"city\": \"Paris\"}"}}]}, "finish_reason": null}]}),
+            json!({"id": "chatcmpl-01", "object": "chat.completion.chunk", "created": 1, "model": "gpt-4o", "choices": [{"index": 0, "delta": {"tool_calls": [{"index": 1, "id": "call_02", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\": \"Rome\"}"}}]}, "finish_reason": null}]}),
+            json!({"id": "chatcmpl-01", "object": "chat.completion.chunk", "created": 1, "model": "gpt-4o", "choices": [{"index": 0, "delta": {}, "finish_reason": "tool_calls"}]}),
+            json!({"id": "chatcmpl-01", "object": "chat.completion.chunk", "created": 1, "model": "gpt-4o", "choices": [], "usage": {"prompt_tokens": 10, "completion_tokens": 30, "total_tokens": 40}}),
+        ];
+        let mut stream: String = chunks.iter().map(|chunk| format!("data: {}\n\n", chunk)).collect();
+        stream.push_str("data: [DONE]\n\n");
+
+        let (first, second) = stream.as_bytes().split_at(stream.len() / 3);
+        let mut translator = ChatCompletionsStreamTranslator::default();
+        let mut translated = translator.translate(first).unwrap();
+        translated.extend(translator.translate(second).unwrap());
+        let translated = String::from_utf8(translated).unwrap();
+
+        let events: Vec<(&str, serde_json::Value)> = translated
+            .split("\n\n")
+            .filter(|event| !event.is_empty())
+            .map(|event| {
+                let (name, data) = event.split_once('\n').unwrap();
+                let data: serde_json::Value = serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap();
+                let name = name.strip_prefix("event: ").unwrap();
+                assert_eq!(data["type"], name);
+                (name, data)
+            })
+            .collect();
+
+        let names: Vec<&str> = events.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ]
+        );
+
+        assert_eq!(events[0].1["message"]["id"], "chatcmpl-01");
+        assert_eq!(events[0].1["message"]["model"], "gpt-4o");
+        assert_eq!(events[1].1["index"], 0);
+        assert_eq!(events[1].1["content_block"]["type"], "text");
+        assert_eq!(events[2].1["delta"]["text"], "Checking");
+        assert_eq!(events[4].1["index"], 1);
+        assert_eq!(events[4].1["content_block"]["id"], "call_01");
+        assert_eq!(events[4].1["content_block"]["name"], "get_weather");
+        assert_eq!(events[5].1["delta"]["partial_json"], "{\"city\": \"Paris\"}");
+        assert_eq!(events[7].1["index"], 2);
+        assert_eq!(events[7].1["content_block"]["id"], "call_02");
+        assert_eq!(events[9].1["index"], 2);
+        assert_eq!(events[10].1["delta"]["stop_reason"], "tool_use");
+        assert_eq!(events[10].1["usage"]["input_tokens"], 10);
+        assert_eq!(events[10].1["usage"]["output_tokens"], 30);
+    }
 }
@@ -7,6 +7,7 @@ pub mod clients;
 
 
 use std::fmt::Display;
+use std::str::FromStr;
 pub enum Provider {
     Arch,
     Mistral,
@@ -16,11 +17,27 @@ pub enum Provider {
     OpenAI,
     Claude,
     Github,
+    Bedrock,
+    AzureOpenAI,
+    OpenAICompatible,
 }
 
+#[derive(Debug, thiserror::Error)]
+#[error("unknown provider: {0}")]
+pub struct UnknownProviderError(pub String);
+
+/// Names no provider is known by are taken for OpenAI compatible providers, parse the name to reject them.
 impl From<&str> for Provider {
     fn from(value: &str) -> Self {
-        match value.to_lowercase().as_str() {
+        value.parse().unwrap_or(Provider::OpenAICompatible)
+    }
+}
+
+impl FromStr for Provider {
+    type Err = UnknownProviderError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value.to_lowercase().as_str() {
             "arch" => Provider::Arch,
             "mistral" => Provider::Mistral,
             "deepseek" => Provider::Deepseek,
@@ -29,8 +46,11 @@ impl From<&str> for Provider {
             "openai" => Provider::OpenAI,
             "claude" => Provider::Claude,
             "github" => Provider::Github,
-            _ => panic!("Unknown provider: {}", value),
-        }
+            "bedrock" => Provider::Bedrock,
+            "azure_openai" => Provider::AzureOpenAI,
+            "openai_compatible" => Provider::OpenAICompatible,
+            _ => return Err(UnknownProviderError(value.to_string())),
+        })
     }
 }
 
@@ -45,12 +65,16 @@ impl Display for Provider {
             Provider::OpenAI => write!(f, "OpenAI"),
             Provider::Claude => write!(f, "Claude"),
             Provider::Github => write!(f, "Github"),
+            Provider::Bedrock => write!(f, "Bedrock"),
+            Provider::AzureOpenAI => write!(f, "AzureOpenAI"),
+            Provider::OpenAICompatible => write!(f, "OpenAICompatible"),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::Provider;
     use crate::providers::openai::types::{ChatCompletionsRequest, Message};
 
     #[test]
@@ -78,4 +102,15 @@ mod tests {
         assert_eq!(request.presence_penalty, Some(0.0));
         assert_eq!(request.frequency_penalty, Some(0.0));
     }
+
+    #[test]
+    fn provider_from_name() {
+        assert!(matches!("Groq".parse::<Provider>(), Ok(Provider::Groq)));
+        assert!(matches!("openai_compatible".parse::<Provider>(), Ok(Provider::OpenAICompatible)));
+        let error = "vllm".parse::<Provider>().err().unwrap();
+        assert_eq!(error.to_string(), "unknown provider: vllm");
+
+        assert!(matches!(Provider::from("Groq"), Provider::Groq));
+        assert!(matches!(Provider::from("vllm"), Provider::OpenAICompatible));
+    }
 }
@@ -102,6 +102,7 @@ impl OpenAIRequestBuilder {
             stream_options: self.stream_options,
             tools: self.tools,
             metadata: None,
+            ..Default::default()
         };
         Ok(request)
     }
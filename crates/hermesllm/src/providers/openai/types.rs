@@ -8,6 +8,8 @@ use std::convert::TryFrom;
 use std::str;
 use thiserror::Error;
 
+use crate::apis::{self, ConverseRequest, GenerateContentRequest, MessagesRequest, ResponsesRequest};
+use crate::clients::{SseEvent, TransformError};
 use crate::Provider;
 
 #[derive(Debug, Error)]
@@ -23,6 +25,8 @@ pub enum OpenAIError {
     },
     #[error("unsupported provider: {provider}")]
     UnsupportedProvider { provider: String },
+    #[error("transform error: {0}")]
+    Transform(#[from] TransformError),
 }
 
 type Result<T> = std::result::Result<T, OpenAIError>;
@@ -33,11 +37,18 @@ pub enum MultiPartContentType {
     Text,
     #[serde(rename = "image_url")]
     ImageUrl,
+    #[serde(rename = "input_audio")]
+    InputAudio,
+    #[serde(rename = "file")]
+    File,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ImageUrl {
     pub url: String,
+    /// Fields the gateway does not read, such as `detail`, passed through as they are
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 #[skip_serializing_none]
@@ -47,6 +58,9 @@ pub struct MultiPartContent {
     pub image_url: Option<ImageUrl>,
     #[serde(rename = "type")]
     pub content_type: MultiPartContentType,
+    /// Fields the gateway does not read, such as `input_audio` and `file`, passed through as they are
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -63,15 +77,10 @@ impl Display for ContentType {
             ContentType::MultiPart(multi_part) => {
                 let text_parts: Vec<String> = multi_part
                     .iter()
-                    .filter_map(|part| {
-                        if part.content_type == MultiPartContentType::Text {
-                            part.text.clone()
-                        } else if part.content_type == MultiPartContentType::ImageUrl {
-                            // skip image URLs or their data in text representation
-                            None
-                        } else {
-                            panic!("Unsupported content type: {:?}", part.content_type);
-                        }
+                    .filter_map(|part| match part.content_type {
+                        MultiPartContentType::Text => part.text.clone(),
+                        // skip images, audio and files in text representation
+                        _ => None,
                     })
                     .collect();
                 let combined_text = text_parts.join("\n");
@@ -82,10 +91,14 @@ impl Display for ContentType {
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Message {
     pub role: String,
     pub content: Option<ContentType>,
+    /// Fields the gateway does not read, such as `tool_calls`, `tool_call_id` and `name`, passed
+    /// through as they are
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 impl Message {
@@ -93,6 +106,7 @@ impl Message {
         Self {
             role: "user".to_string(),
             content: Some(ContentType::Text(content)),
+            ..Default::default()
         }
     }
 }
@@ -118,6 +132,10 @@ pub struct ChatCompletionsRequest {
     pub stream_options: Option<StreamOptions>,
     pub tools: Option<Vec<Value>>,
     pub metadata: Option<HashMap<String, Value>>,
+    /// Fields the gateway does not read, such as `tool_choice`, `response_format` and `seed`,
+    /// passed through as they are
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 impl TryFrom<&[u8]> for ChatCompletionsRequest {
@@ -127,12 +145,29 @@ impl TryFrom<&[u8]> for ChatCompletionsRequest {
     }
 }
 
+impl TryFrom<MessagesRequest> for ChatCompletionsRequest {
+    type Error = OpenAIError;
+    fn try_from(request: MessagesRequest) -> Result<Self> {
+        let request = apis::ChatCompletionsRequest::try_from(request)?;
+        serde_json::from_value(serde_json::to_value(request)?).map_err(OpenAIError::from)
+    }
+}
+
+impl TryFrom<ResponsesRequest> for ChatCompletionsRequest {
+    type Error = OpenAIError;
+    fn try_from(request: ResponsesRequest) -> Result<Self> {
+        let request = apis::ChatCompletionsRequest::try_from(request)?;
+        serde_json::from_value(serde_json::to_value(request)?).map_err(OpenAIError::from)
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ChatCompletionsResponse {
     pub id: String,
     pub object: String,
     pub created: u64,
+    pub model: Option<String>,
     pub choices: Vec<Choice>,
     pub usage: Option<Usage>,
 }
@@ -154,6 +189,17 @@ impl<'a> TryFrom<(&'a [u8], &'a Provider)> for ChatCompletionsResponse {
 }
 
 impl ChatCompletionsRequest {
+    // the full request type the provider transformers convert from
+    fn to_api_request(&self) -> Result<apis::ChatCompletionsRequest> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(fields) = value.as_object_mut() {
+            // openai only fields the other apis have no place for
+            fields.remove("metadata");
+            fields.remove("stream_options");
+        }
+        serde_json::from_value(value).map_err(OpenAIError::from)
+    }
+
     pub fn to_bytes(&self, provider: Provider) -> Result<Vec<u8>> {
         match provider {
             Provider::OpenAI
@@ -161,8 +207,20 @@ impl ChatCompletionsRequest {
             | Provider::Deepseek
             | Provider::Mistral
             | Provider::Groq
-            | Provider::Gemini
-            | Provider::Claude => serde_json::to_vec(self).map_err(OpenAIError::from),
+            | Provider::AzureOpenAI
+            | Provider::OpenAICompatible => serde_json::to_vec(self).map_err(OpenAIError::from),
+            Provider::Claude => {
+                let request = MessagesRequest::try_from(self.to_api_request()?)?;
+                serde_json::to_vec(&request).map_err(OpenAIError::from)
+            }
+            Provider::Gemini => {
+                let request = GenerateContentRequest::try_from(self.to_api_request()?)?;
+                serde_json::to_vec(&request).map_err(OpenAIError::from)
+            }
+            Provider::Bedrock => {
+                let request = ConverseRequest::try_from(self.to_api_request()?)?;
+                serde_json::to_vec(&request).map_err(OpenAIError::from)
+            }
             _ => Err(OpenAIError::UnsupportedProvider {
                 provider: provider.to_string(),
             }),
@@ -184,6 +242,33 @@ pub struct Usage {
     pub prompt_tokens: usize,
     pub completion_tokens: usize,
     pub total_tokens: usize,
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PromptTokensDetails {
+    pub cached_tokens: Option<usize>,
+}
+
+impl Usage {
+    pub fn cached_prompt_tokens(&self) -> usize {
+        self.prompt_tokens_details
+            .as_ref()
+            .and_then(|details| details.cached_tokens)
+            .unwrap_or(0)
+    }
+}
+
+impl From<apis::EmbeddingsUsage> for Usage {
+    fn from(usage: apis::EmbeddingsUsage) -> Self {
+        Usage {
+            prompt_tokens: usage.prompt_tokens as usize,
+            completion_tokens: 0,
+            total_tokens: usage.total_tokens as usize,
+            prompt_tokens_details: None,
+        }
+    }
 }
 
 #[skip_serializing_none]
@@ -210,6 +295,17 @@ pub struct ChatCompletionStreamResponse {
     pub usage: Option<Usage>,
 }
 
+impl TryFrom<&SseEvent> for ChatCompletionStreamResponse {
+    type Error = OpenAIError;
+
+    fn try_from(event: &SseEvent) -> Result<Self> {
+        serde_json::from_str(&event.data).map_err(|e| OpenAIError::InvalidStreamingData {
+            source: e,
+            data: event.data.clone(),
+        })
+    }
+}
+
 pub struct SseChatCompletionIter<I>
 where
     I: Iterator,
@@ -315,11 +411,13 @@ mod tests {
                 text: Some("This is a text part.".to_string()),
                 content_type: MultiPartContentType::Text,
                 image_url: None,
+                extra: HashMap::new(),
             },
             MultiPartContent {
                 text: Some("https://example.com/image.png".to_string()),
                 content_type: MultiPartContentType::ImageUrl,
                 image_url: None,
+                extra: HashMap::new(),
             },
         ]);
         assert_eq!(multi_part_content.to_string(), "This is a text part.");
@@ -421,6 +519,7 @@ mod tests {
                 multi_part_content[1].image_url,
                 Some(ImageUrl {
                     url: "data:image/jpeg;base64,/9j/4AAQSkZJRgABAQAAAQABAAD/...==".to_string(),
+                    extra: HashMap::new(),
                 })
             );
         } else {
@@ -560,4 +659,140 @@ data: [DONE]
             "Hello! How can I assist you today? Whether you have a question, need information, or just want to chat about something, I'm here to help. What would you like to talk about?"
         );
     }
+
+    #[test]
+    fn test_claude_request_to_messages_bytes() {
+        let request: ChatCompletionsRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": [
+                {"role": "system", "content": "You are helpful"},
+                {"role": "user", "content": "Hello"}
+            ],
+            "stream": true,
+            "stream_options": {"include_usage": true},
+            "metadata": {"trace": "abc"}
+        }))
+        .unwrap();
+
+        let bytes = request.to_bytes(Provider::Claude).unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(body["model"], "claude-sonnet-4-20250514");
+        assert_eq!(body["system"], "You are helpful");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert!(body["max_tokens"].as_u64().is_some());
+        assert!(body.get("stream_options").is_none());
+        assert!(body.get("metadata").is_none());
+    }
+
+    #[test]
+    fn test_bedrock_request_to_converse_bytes() {
+        let request: ChatCompletionsRequest = serde_json::from_value(serde_json::json!({
+            "model": "amazon.nova-pro-v1:0",
+            "messages": [
+                {"role": "system", "content": "You are helpful"},
+                {"role": "user", "content": "Hello"}
+            ],
+            "stream": true,
+            "stream_options": {"include_usage": true},
+            "max_tokens": 100
+        }))
+        .unwrap();
+
+        let bytes = request.to_bytes(Provider::Bedrock).unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+
+        // the model and streaming are expressed in the path
+        assert!(body.get("model").is_none());
+        assert!(body.get("stream").is_none());
+        assert_eq!(body["system"], serde_json::json!([{"text": "You are helpful"}]));
+        assert_eq!(body["messages"], serde_json::json!([{"role": "user", "content": [{"text": "Hello"}]}]));
+        assert_eq!(body["inferenceConfig"]["maxTokens"], 100);
+    }
+
+    #[test]
+    fn test_chat_completions_request_from_messages_request() {
+        let request: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4-20250514",
+            "max_tokens": 1024,
+            "system": "You are helpful",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "stream": true
+        }))
+        .unwrap();
+
+        let request = ChatCompletionsRequest::try_from(request).unwrap();
+
+        assert_eq!(request.model, "claude-sonnet-4-20250514");
+        assert_eq!(request.stream, Some(true));
+        assert_eq!(request.messages.len(), 2);
+        assert_eq!(request.messages[0].role, "system");
+        assert_eq!(request.messages[1].role, "user");
+        assert_eq!(
+            request.messages[1].content.as_ref().unwrap().to_string(),
+            "Hello"
+        );
+    }
+
+    #[test]
+    fn test_chat_completions_request_round_trips_unread_fields() {
+        let original = serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "user", "name": "alice", "content": [
+                    {"type": "text", "text": "What is in this image?"},
+                    {"type": "image_url", "image_url": {"url": "https://example.com/a.png", "detail": "high"}},
+                    {"type": "input_audio", "input_audio": {"data": "AAAA", "format": "wav"}}
+                ]},
+                {"role": "assistant", "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "describe", "arguments": "{}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "a cat"}
+            ],
+            "tools": [{"type": "function", "function": {"name": "describe", "parameters": {"type": "object"}}}],
+            "tool_choice": {"type": "function", "function": {"name": "describe"}},
+            "response_format": {"type": "json_object"},
+            "seed": 42,
+            "logprobs": true,
+            "top_logprobs": 2,
+            "user": "user-1234",
+            "parallel_tool_calls": false
+        });
+
+        let request: ChatCompletionsRequest = serde_json::from_value(original.clone()).unwrap();
+        assert_eq!(
+            request.messages[0].content.as_ref().unwrap().to_string(),
+            "What is in this image?"
+        );
+
+        let bytes = request.to_bytes(Provider::OpenAI).unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body, original);
+    }
+
+    #[test]
+    fn test_tool_calls_reach_claude() {
+        let request: ChatCompletionsRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": [
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "18C"}
+            ],
+            "tool_choice": "required"
+        }))
+        .unwrap();
+
+        let bytes = request.to_bytes(Provider::Claude).unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(body["messages"][1]["content"][0]["type"], "tool_use");
+        assert_eq!(body["messages"][1]["content"][0]["id"], "call_1");
+        assert_eq!(body["messages"][2]["content"][0]["type"], "tool_result");
+        assert_eq!(body["messages"][2]["content"][0]["tool_use_id"], "call_1");
+        assert_eq!(body["tool_choice"]["type"], "any");
+    }
 }
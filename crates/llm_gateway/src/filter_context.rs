@@ -1,7 +1,9 @@
 use crate::metrics::Metrics;
 use crate::stream_context::StreamContext;
+use common::configuration::Budget;
 use common::configuration::Configuration;
 use common::configuration::Overrides;
+use common::configuration::Tracing;
 use common::consts::OTEL_COLLECTOR_HTTP;
 use common::consts::OTEL_POST_PATH;
 use common::http::CallArgs;
@@ -9,19 +11,21 @@ use common::http::Client;
 use common::llm_providers::LlmProviders;
 use common::ratelimit;
 use common::stats::Gauge;
-use common::tracing::TraceData;
+use common::trace_export::{SpanQueue, TraceExporter};
 use log::trace;
 use log::warn;
 use proxy_wasm::traits::*;
 use proxy_wasm::types::*;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::collections::VecDeque;
 use std::rc::Rc;
-use std::time::Duration;
+use std::time::{Duration, UNIX_EPOCH};
 
 use std::sync::{Arc, Mutex};
 
+// a stuck export holds back the ones after it, so it is not waited on for long
+const OTEL_EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug)]
 pub struct CallContext {}
 
@@ -31,8 +35,11 @@ pub struct FilterContext {
     // callouts stores token_id to request mapping that we use during #on_http_call_response to match the response to the request.
     callouts: RefCell<HashMap<u32, CallContext>>,
     llm_providers: Option<Rc<LlmProviders>>,
-    traces_queue: Arc<Mutex<VecDeque<TraceData>>>,
+    traces_queue: Arc<Mutex<SpanQueue>>,
+    trace_exporter: TraceExporter,
     overrides: Rc<Option<Overrides>>,
+    tracing: Rc<Option<Tracing>>,
+    budgets: Rc<Vec<Budget>>,
 }
 
 impl FilterContext {
@@ -41,12 +48,24 @@ impl FilterContext {
             callouts: RefCell::new(HashMap::new()),
             metrics: Rc::new(Metrics::new()),
             llm_providers: None,
-            traces_queue: Arc::new(Mutex::new(VecDeque::new())),
+            traces_queue: Arc::new(Mutex::new(SpanQueue::default())),
+            trace_exporter: TraceExporter::new("egress_llm_traffic"),
             overrides: Rc::new(None),
+            tracing: Rc::new(None),
+            budgets: Rc::new(Vec::new()),
         }
     }
 }
 
+impl FilterContext {
+    fn current_time_ms(&self) -> u64 {
+        self.get_current_time()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
 impl Client for FilterContext {
     type CallContext = CallContext;
 
@@ -73,9 +92,20 @@ impl RootContext for FilterContext {
 
         ratelimit::ratelimits(Some(config.ratelimits.unwrap_or_default()));
         self.overrides = Rc::new(config.overrides);
-
-        match config.llm_providers.try_into() {
-            Ok(llm_providers) => self.llm_providers = Some(Rc::new(llm_providers)),
+        self.tracing = Rc::new(config.tracing);
+        self.budgets = Rc::new(config.budgets.unwrap_or_default());
+
+        let load_balancing = config
+            .routing
+            .as_ref()
+            .and_then(|routing| routing.load_balancing)
+            .unwrap_or_default();
+
+        match LlmProviders::try_from(config.llm_providers) {
+            Ok(llm_providers) => {
+                self.llm_providers =
+                    Some(Rc::new(llm_providers.with_load_balancing(load_balancing)))
+            }
             Err(err) => panic!("{err}"),
         }
 
@@ -98,6 +128,8 @@ impl RootContext for FilterContext {
             ),
             Arc::clone(&self.traces_queue),
             Rc::clone(&self.overrides),
+            Rc::clone(&self.tracing),
+            Rc::clone(&self.budgets),
         )))
     }
 
@@ -111,31 +143,38 @@ impl RootContext for FilterContext {
     }
 
     fn on_tick(&mut self) {
-        let _ = self.traces_queue.try_lock().map(|mut traces_queue| {
-            while let Some(trace) = traces_queue.pop_front() {
-                let trace_str = serde_json::to_string(&trace).unwrap();
-                trace!("trace details: {}", trace_str);
-                let call_args = CallArgs::new(
-                    OTEL_COLLECTOR_HTTP,
-                    OTEL_POST_PATH,
-                    vec![
-                        (":method", http::Method::POST.as_str()),
-                        (":path", OTEL_POST_PATH),
-                        (":authority", OTEL_COLLECTOR_HTTP),
-                        ("content-type", "application/json"),
-                    ],
-                    Some(trace_str.as_bytes()),
-                    vec![],
-                    Duration::from_secs(60),
-                );
-                if let Err(error) = self.http_call(call_args, CallContext {}) {
-                    warn!(
-                        "failed to schedule http call to otel-collector: {:?}",
-                        error
-                    );
-                }
-            }
-        });
+        let now_ms = self.current_time_ms();
+        let payload = match self.traces_queue.try_lock() {
+            Ok(mut traces_queue) => self.trace_exporter.next_export(&mut traces_queue, now_ms),
+            Err(_) => None,
+        };
+        let payload = match payload {
+            Some(payload) => payload,
+            None => return,
+        };
+
+        trace!("trace details: {}", payload);
+        let call_args = CallArgs::new(
+            OTEL_COLLECTOR_HTTP,
+            OTEL_POST_PATH,
+            vec![
+                (":method", http::Method::POST.as_str()),
+                (":path", OTEL_POST_PATH),
+                (":authority", OTEL_COLLECTOR_HTTP),
+                ("content-type", "application/json"),
+            ],
+            Some(payload.as_bytes()),
+            vec![],
+            OTEL_EXPORT_TIMEOUT,
+        );
+        if let Err(error) = self.http_call(call_args, CallContext {}) {
+            warn!(
+                "failed to schedule http call to otel-collector: {:?}",
+                error
+            );
+            let outcome = self.trace_exporter.export_finished(None, now_ms);
+            self.metrics.trace_export.record(&outcome);
+        }
     }
 }
 
@@ -158,8 +197,12 @@ impl Context for FilterContext {
             .remove(&token_id)
             .expect("invalid token_id");
 
-        if let Some(status) = self.get_http_call_response_header(":status") {
-            trace!("trace response status: {:?}", status);
-        };
+        let status = self.get_http_call_response_header(":status");
+        trace!("trace response status: {:?}", status);
+        let status = status.and_then(|status| status.parse::<u16>().ok());
+        let outcome = self
+            .trace_exporter
+            .export_finished(status, self.current_time_ms());
+        self.metrics.trace_export.record(&outcome);
     }
 }
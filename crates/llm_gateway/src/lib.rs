@@ -4,6 +4,7 @@ use proxy_wasm::types::*;
 
 mod filter_context;
 mod metrics;
+mod response_translator;
 mod stream_context;
 
 proxy_wasm::main! {{
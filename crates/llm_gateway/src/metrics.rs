@@ -1,28 +1,38 @@
-use common::stats::{Counter, Gauge, Histogram};
+use common::stats::{Counter, Gauge, Histogram, Tagged};
+use common::trace_export::TraceExportMetrics;
 
-#[derive(Copy, Clone, Debug)]
+/// The latency, token, cost and ratelimit metrics are tagged with provider, model and status.
+#[derive(Debug)]
 pub struct Metrics {
     pub active_http_calls: Gauge,
-    pub ratelimited_rq: Counter,
-    pub time_to_first_token: Histogram,
-    pub time_per_output_token: Histogram,
-    pub tokens_per_second: Histogram,
-    pub request_latency: Histogram,
-    pub output_sequence_length: Histogram,
-    pub input_sequence_length: Histogram,
+    pub ratelimited_rq: Tagged<Counter>,
+    /// Failed requests, tagged with the provider and the class of error instead.
+    pub request_errors: Tagged<Counter>,
+    pub time_to_first_token: Tagged<Histogram>,
+    pub time_per_output_token: Tagged<Histogram>,
+    pub tokens_per_second: Tagged<Histogram>,
+    pub request_latency: Tagged<Histogram>,
+    pub output_sequence_length: Tagged<Histogram>,
+    pub input_sequence_length: Tagged<Histogram>,
+    /// Cost of a request in micro-USD, for providers that have pricing configured.
+    pub cost: Tagged<Histogram>,
+    pub trace_export: TraceExportMetrics,
 }
 
 impl Metrics {
     pub fn new() -> Metrics {
         Metrics {
             active_http_calls: Gauge::new(String::from("active_http_calls")),
-            ratelimited_rq: Counter::new(String::from("ratelimited_rq")),
-            time_to_first_token: Histogram::new(String::from("time_to_first_token")),
-            time_per_output_token: Histogram::new(String::from("time_per_output_token")),
-            tokens_per_second: Histogram::new(String::from("tokens_per_second")),
-            request_latency: Histogram::new(String::from("request_latency")),
-            output_sequence_length: Histogram::new(String::from("output_sequence_length")),
-            input_sequence_length: Histogram::new(String::from("input_sequence_length")),
+            ratelimited_rq: Tagged::new("ratelimited_rq", Counter::new),
+            request_errors: Tagged::new("request_errors", Counter::new),
+            time_to_first_token: Tagged::new("time_to_first_token", Histogram::new),
+            time_per_output_token: Tagged::new("time_per_output_token", Histogram::new),
+            tokens_per_second: Tagged::new("tokens_per_second", Histogram::new),
+            request_latency: Tagged::new("request_latency", Histogram::new),
+            output_sequence_length: Tagged::new("output_sequence_length", Histogram::new),
+            input_sequence_length: Tagged::new("input_sequence_length", Histogram::new),
+            cost: Tagged::new("cost", Histogram::new),
+            trace_export: TraceExportMetrics::new(),
         }
     }
 }
@@ -0,0 +1,150 @@
+use common::configuration::LlmProviderType;
+use common::consts::{
+    ANTHROPIC_MESSAGES_PATH, CHAT_COMPLETIONS_PATH, EMBEDDINGS_PATH, RESPONSES_PATH,
+};
+use hermesllm::clients::{
+    translate_chat_completions_response, translate_converse_response,
+    translate_generate_content_response, translate_messages_response, translate_responses_response,
+    ChatCompletionsStreamTranslator, ConverseStreamTranslator, GenerateContentStreamTranslator,
+    MessagesStreamTranslator, ResponsesStreamTranslator, TransformError,
+};
+use serde_json::json;
+
+// The APIs the gateway accepts requests on and sends requests upstream with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LlmApi {
+    ChatCompletions,
+    Messages,
+    // only spoken by clients, requests are sent upstream as chat completions
+    Responses,
+    // passed through to openai compatible providers, responses are never translated
+    Embeddings,
+    // only spoken upstream, clients can't send gemini or bedrock requests
+    GenerateContent,
+    Converse,
+}
+
+impl LlmApi {
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path {
+            CHAT_COMPLETIONS_PATH => Some(LlmApi::ChatCompletions),
+            ANTHROPIC_MESSAGES_PATH => Some(LlmApi::Messages),
+            EMBEDDINGS_PATH => Some(LlmApi::Embeddings),
+            RESPONSES_PATH => Some(LlmApi::Responses),
+            _ => None,
+        }
+    }
+
+    // Anthropic, Gemini and Bedrock are spoken to natively, every other provider is openai compatible.
+    pub fn of_provider(provider_interface: &LlmProviderType) -> Self {
+        match provider_interface {
+            LlmProviderType::Claude => LlmApi::Messages,
+            LlmProviderType::Gemini => LlmApi::GenerateContent,
+            LlmProviderType::Bedrock => LlmApi::Converse,
+            _ => LlmApi::ChatCompletions,
+        }
+    }
+}
+
+// Translates upstream response bodies into the chat completions format that usage is accounted
+// with, and into the client's format when the client and upstream APIs differ. Streams are
+// stateful so one translator is kept per response.
+#[derive(Debug, Default)]
+pub struct ResponseTranslator {
+    messages: MessagesStreamTranslator,
+    generate_content: GenerateContentStreamTranslator,
+    converse: ConverseStreamTranslator,
+    chat_completions: ChatCompletionsStreamTranslator,
+    responses: ResponsesStreamTranslator,
+}
+
+pub struct Translated {
+    // the body in the chat completions format
+    pub chat_completions: Vec<u8>,
+    // the body to send to the client, when it is not the upstream body
+    pub client: Option<Vec<u8>>,
+}
+
+impl ResponseTranslator {
+    pub fn translate(
+        &mut self,
+        upstream_api: LlmApi,
+        client_api: LlmApi,
+        streaming: bool,
+        body: &[u8],
+    ) -> Result<Translated, TransformError> {
+        let chat_completions = match (upstream_api, streaming) {
+            (LlmApi::ChatCompletions, _) => body.to_vec(),
+            (LlmApi::Messages, true) => self.messages.translate(body)?,
+            (LlmApi::Messages, false) => translate_messages_response(body)?,
+            (LlmApi::GenerateContent, true) => self.generate_content.translate(body)?,
+            (LlmApi::GenerateContent, false) => translate_generate_content_response(body)?,
+            (LlmApi::Converse, true) => self.converse.translate(body)?,
+            (LlmApi::Converse, false) => translate_converse_response(body)?,
+            (LlmApi::Embeddings, _) => {
+                return Err(TransformError::UnsupportedConversion(
+                    "embeddings responses are not translated".to_string(),
+                ))
+            }
+            (LlmApi::Responses, _) => {
+                return Err(TransformError::UnsupportedConversion(
+                    "responses are not requested upstream".to_string(),
+                ))
+            }
+        };
+
+        let client = match (client_api, streaming) {
+            _ if client_api == upstream_api => None,
+            (LlmApi::ChatCompletions, _) => Some(chat_completions.clone()),
+            (LlmApi::Messages, true) => Some(self.chat_completions.translate(&chat_completions)?),
+            (LlmApi::Messages, false) => {
+                Some(translate_chat_completions_response(&chat_completions)?)
+            }
+            (LlmApi::Responses, true) => Some(self.responses.translate(&chat_completions)?),
+            (LlmApi::Responses, false) => Some(translate_responses_response(&chat_completions)?),
+            (LlmApi::Embeddings, _) => {
+                return Err(TransformError::UnsupportedConversion(
+                    "embeddings responses are not translated".to_string(),
+                ))
+            }
+            (LlmApi::GenerateContent | LlmApi::Converse, _) => {
+                return Err(TransformError::UnsupportedConversion(
+                    "responses are not translated to gemini or bedrock".to_string(),
+                ))
+            }
+        };
+
+        Ok(Translated {
+            chat_completions,
+            client,
+        })
+    }
+
+    /// Number of stream events left out since the last call because they could not be translated.
+    pub fn take_skipped_events(&mut self) -> usize {
+        self.messages.take_skipped_events()
+            + self.generate_content.take_skipped_events()
+            + self.converse.take_skipped_events()
+            + self.chat_completions.take_skipped_events()
+            + self.responses.take_skipped_events()
+    }
+}
+
+/// An event that ends a stream with an error, in the format of the client's API.
+pub fn stream_error_event(client_api: LlmApi, message: &str) -> Vec<u8> {
+    match client_api {
+        LlmApi::Messages => format!(
+            "event: error\ndata: {}\n\n",
+            json!({"type": "error", "error": {"type": "api_error", "message": message}})
+        ),
+        LlmApi::Responses => format!(
+            "event: error\ndata: {}\n\n",
+            json!({"type": "error", "code": "server_error", "message": message, "param": null})
+        ),
+        _ => format!(
+            "data: {}\n\n",
+            json!({"error": {"type": "server_error", "message": message}})
+        ),
+    }
+    .into_bytes()
+}
@@ -1,18 +1,32 @@
 use crate::metrics::Metrics;
-use common::configuration::{LlmProvider, LlmProviderType, Overrides};
+use crate::response_translator::{stream_error_event, LlmApi, ResponseTranslator};
+use common::budget::{self, Reservation};
+use common::configuration::{AwsConfig, Budget, LlmProvider, LlmProviderType, Overrides, Tracing};
 use common::consts::{
-    ARCH_PROVIDER_HINT_HEADER, ARCH_ROUTING_HEADER, CHAT_COMPLETIONS_PATH, HEALTHZ_PATH,
-    RATELIMIT_SELECTOR_HEADER_KEY, REQUEST_ID_HEADER, TRACE_PARENT_HEADER,
+    ANTHROPIC_API_KEY_HEADER, ANTHROPIC_MESSAGES_PATH, ANTHROPIC_VERSION, ANTHROPIC_VERSION_HEADER,
+    ARCH_COST_HEADER, ARCH_INTERNAL_CLUSTER_NAME, ARCH_PROVIDER_HINT_HEADER, ARCH_ROUTING_HEADER,
+    ARCH_UPSTREAM_HOST_HEADER, AZURE_API_KEY_HEADER, AZURE_OPENAI_API_VERSION,
+    CHAT_COMPLETIONS_PATH, EMBEDDINGS_PATH, GEMINI_API_KEY_HEADER, HEALTHZ_PATH,
+    LLM_FAILOVER_REQUEST_TIMEOUT_MS, RATELIMIT_SELECTOR_HEADER_KEY, REQUEST_ID_HEADER,
+    RESPONSES_PATH, TRACE_PARENT_HEADER,
 };
 use common::errors::ServerError;
+use common::http::{CallArgs, Client};
 use common::llm_providers::LlmProviders;
-use common::ratelimit::Header;
-use common::stats::{IncrementingMetric, RecordingMetric};
-use common::tracing::{Event, Span, TraceData, Traceparent};
-use common::{ratelimit, routing, tokenizer};
-use hermesllm::providers::openai::types::{ChatCompletionsRequest, SseChatCompletionIter};
+use common::ratelimit::{Descriptor, Header, Quota};
+use common::shared_data::HostSharedStore;
+use common::stats::{Gauge, IncrementingMetric, RecordingMetric};
+use common::trace_export::SpanQueue;
+use common::tracing::{Event, TraceContext, SPAN_KIND_CLIENT, STATUS_CODE_ERROR};
+use common::{circuit_breaker, ratelimit, routing, tokenizer};
+use hermesllm::apis::{
+    BedrockApi, EmbeddingsRequest, EmbeddingsResponse, GeminiApi, MessagesRequest, ResponsesRequest,
+};
+use hermesllm::clients::SigV4Signer;
+use hermesllm::clients::{SseDecoder, TransformError};
+use hermesllm::providers::openai::types::{ChatCompletionStreamResponse, ChatCompletionsRequest};
 use hermesllm::providers::openai::types::{
-    ChatCompletionsResponse, ContentType, Message, StreamOptions,
+    ChatCompletionsResponse, ContentType, Message, OpenAIError, StreamOptions, Usage,
 };
 use hermesllm::Provider;
 use http::StatusCode;
@@ -20,30 +34,66 @@ use log::{debug, info, warn};
 use proxy_wasm::hostcalls::get_current_time;
 use proxy_wasm::traits::*;
 use proxy_wasm::types::*;
-use std::collections::VecDeque;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::num::NonZero;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+#[derive(Debug)]
+pub struct FailoverCallContext {
+    llm_provider: String,
+}
+
 pub struct StreamContext {
     context_id: u32,
     metrics: Rc<Metrics>,
     ratelimit_selector: Option<Header>,
+    jwt_claims: BTreeMap<String, String>,
+    ratelimit_descriptor: Option<Descriptor>,
+    ratelimit_quota: Option<Quota>,
     streaming_response: bool,
     response_tokens: usize,
-    is_chat_completions_request: bool,
+    client_api: Option<LlmApi>,
     llm_providers: Rc<LlmProviders>,
     llm_provider: Option<Rc<LlmProvider>>,
     request_id: Option<String>,
     start_time: SystemTime,
     ttft_duration: Option<Duration>,
     ttft_time: Option<u128>,
-    traceparent: Option<String>,
+    trace_context: Option<TraceContext>,
     request_body_sent_time: Option<u128>,
     user_message: Option<Message>,
-    traces_queue: Arc<Mutex<VecDeque<TraceData>>>,
+    traces_queue: Arc<Mutex<SpanQueue>>,
     overrides: Rc<Option<Overrides>>,
+    tracing: Rc<Option<Tracing>>,
+    request_path: String,
+    chat_completions_request: Option<ChatCompletionsRequest>,
+    request_model: Option<String>,
+    messages_request: Option<MessagesRequest>,
+    fallback_providers: VecDeque<Rc<LlmProvider>>,
+    failed_over: bool,
+    upstream_status: Option<u16>,
+    response_status: Option<u16>,
+    response_error: Option<String>,
+    cost_usd: Option<f64>,
+    buffer_response_for_cost: bool,
+    translate_response: bool,
+    // the stream was ended with an error event, the rest of the upstream stream is dropped
+    translation_failed: bool,
+    response_translator: ResponseTranslator,
+    sse_decoder: SseDecoder,
+    cost_reported: bool,
+    input_token_count: Option<usize>,
+    usage: Option<Usage>,
+    response_id: Option<String>,
+    response_model: Option<String>,
+    finish_reasons: Vec<String>,
+    budgets: Rc<Vec<Budget>>,
+    budget_headers: HashMap<String, String>,
+    budget_reservations: Vec<Reservation>,
+    callouts: RefCell<HashMap<u32, FailoverCallContext>>,
 }
 
 impl StreamContext {
@@ -51,27 +101,58 @@ impl StreamContext {
         context_id: u32,
         metrics: Rc<Metrics>,
         llm_providers: Rc<LlmProviders>,
-        traces_queue: Arc<Mutex<VecDeque<TraceData>>>,
+        traces_queue: Arc<Mutex<SpanQueue>>,
         overrides: Rc<Option<Overrides>>,
+        tracing: Rc<Option<Tracing>>,
+        budgets: Rc<Vec<Budget>>,
     ) -> Self {
         StreamContext {
             context_id,
             metrics,
             overrides,
+            tracing,
             ratelimit_selector: None,
+            jwt_claims: BTreeMap::new(),
+            ratelimit_descriptor: None,
+            ratelimit_quota: None,
             streaming_response: false,
             response_tokens: 0,
-            is_chat_completions_request: false,
+            client_api: None,
             llm_providers,
             llm_provider: None,
             request_id: None,
             start_time: SystemTime::now(),
             ttft_duration: None,
-            traceparent: None,
+            trace_context: None,
             ttft_time: None,
             user_message: None,
             traces_queue,
             request_body_sent_time: None,
+            request_path: String::new(),
+            chat_completions_request: None,
+            request_model: None,
+            messages_request: None,
+            fallback_providers: VecDeque::new(),
+            failed_over: false,
+            upstream_status: None,
+            response_status: None,
+            response_error: None,
+            cost_usd: None,
+            buffer_response_for_cost: false,
+            translate_response: false,
+            translation_failed: false,
+            response_translator: ResponseTranslator::default(),
+            sse_decoder: SseDecoder::default(),
+            cost_reported: false,
+            input_token_count: None,
+            usage: None,
+            response_id: None,
+            response_model: None,
+            finish_reasons: Vec::new(),
+            budgets,
+            budget_headers: HashMap::new(),
+            budget_reservations: Vec::new(),
+            callouts: RefCell::new(HashMap::new()),
         }
     }
     fn llm_provider(&self) -> &LlmProvider {
@@ -85,31 +166,37 @@ impl StreamContext {
             .get_http_request_header(ARCH_PROVIDER_HINT_HEADER)
             .map(|llm_name| llm_name.into());
 
-        self.llm_provider = Some(routing::get_llm_provider(
-            &self.llm_providers,
-            provider_hint,
-        ));
-
-        match self.llm_provider.as_ref().unwrap().provider_interface {
-            LlmProviderType::Groq => {
-                if let Some(path) = self.get_http_request_header(":path") {
-                    if path.starts_with("/v1/") {
-                        let new_path = format!("/openai{}", path);
-                        self.set_http_request_header(":path", Some(new_path.as_str()));
-                    }
-                }
+        // providers whose half-open circuit let another request's trial through in the meantime
+        let mut lost_trials: Vec<String> = Vec::new();
+        let llm_provider = loop {
+            let llm_provider = routing::get_llm_provider(
+                &self.llm_providers,
+                provider_hint.clone(),
+                |llm_provider| {
+                    !lost_trials.contains(&llm_provider.name) && is_available(llm_provider)
+                },
+            );
+            // once no provider is available the selection ignores availability
+            if lost_trials.contains(&llm_provider.name) || try_acquire(&llm_provider) {
+                break llm_provider;
             }
-            LlmProviderType::Gemini => {
-                if let Some(path) = self.get_http_request_header(":path") {
-                    if path == "/v1/chat/completions" {
-                        self.set_http_request_header(
-                            ":path",
-                            Some("/v1beta/openai/chat/completions"),
-                        );
-                    }
+            lost_trials.push(llm_provider.name.clone());
+        };
+        self.llm_provider = Some(llm_provider);
+
+        let llm_provider = self.llm_provider.as_ref().unwrap();
+        if matches!(
+            llm_provider.provider_interface,
+            LlmProviderType::Claude | LlmProviderType::AzureOpenAI
+        ) || llm_provider.openai_base_path().is_some()
+            || matches!(self.client_api, Some(LlmApi::Messages | LlmApi::Responses))
+        {
+            if let Some(path) = self.get_http_request_header(":path") {
+                let new_path = provider_request_path(llm_provider, &path, None);
+                if new_path != path {
+                    self.set_http_request_header(":path", Some(new_path.as_str()));
                 }
             }
-            _ => {}
         }
 
         debug!(
@@ -121,20 +208,33 @@ impl StreamContext {
     }
 
     fn modify_auth_headers(&mut self) -> Result<(), ServerError> {
+        if self.llm_provider().aws.is_some() {
+            // the request is signed once its body is known
+            self.set_http_request_header("Authorization", None);
+            return Ok(());
+        }
+
         let llm_provider_api_key_value =
             self.llm_provider()
                 .access_key
                 .as_ref()
-                .ok_or(ServerError::BadRequest {
+                .ok_or(ServerError::MissingAccessKey {
                     why: format!(
                         "No access key configured for selected LLM Provider \"{}\"",
                         self.llm_provider()
                     ),
                 })?;
 
-        let authorization_header_value = format!("Bearer {}", llm_provider_api_key_value);
-
-        self.set_http_request_header("Authorization", Some(&authorization_header_value));
+        let auth_headers = provider_auth_headers(self.llm_provider(), llm_provider_api_key_value);
+        if !auth_headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("Authorization"))
+        {
+            self.set_http_request_header("Authorization", None);
+        }
+        for (name, value) in auth_headers {
+            self.set_http_request_header(name, Some(&value));
+        }
 
         Ok(())
     }
@@ -156,60 +256,631 @@ impl StreamContext {
             });
     }
 
-    fn send_server_error(&self, error: ServerError, override_status_code: Option<StatusCode>) {
+    fn save_jwt_claims(&mut self) {
+        let ratelimits = ratelimit::ratelimits(None).read().unwrap();
+        let jwt_claims = ratelimits.jwt_claims();
+        if jwt_claims.is_empty() {
+            return;
+        }
+        if let Some(authorization) = self.get_http_request_header("authorization") {
+            self.jwt_claims = ratelimit::jwt_claims(&authorization, &jwt_claims);
+        }
+    }
+
+    fn save_budget_headers(&mut self) {
+        let budgets = Rc::clone(&self.budgets);
+        for budget in budgets.iter() {
+            let key = &budget.selector.key;
+            if self.budget_headers.contains_key(key) {
+                continue;
+            }
+            if let Some(value) = self.get_http_request_header(key) {
+                self.budget_headers.insert(key.clone(), value);
+            }
+        }
+    }
+
+    fn enforce_budgets(&mut self, model: &str) -> Result<(), budget::Error> {
+        if self.budget_headers.is_empty() {
+            return Ok(());
+        }
+
+        self.budget_reservations = budget::reserve(
+            &HostSharedStore,
+            &self.budgets,
+            model,
+            &self.budget_headers,
+            self.input_token_count.unwrap_or_default() as u64,
+            current_time_secs(),
+        )?;
+
+        Ok(())
+    }
+
+    fn send_budget_exceeded(&mut self, error: budget::Error) {
+        let retry_after = error.retry_after(current_time_secs()).to_string();
+        let error = ServerError::ExceededBudget(error);
+        warn!("server error occurred: {}", error);
+        self.record_error(StatusCode::TOO_MANY_REQUESTS, &error);
+        self.send_http_response(
+            StatusCode::TOO_MANY_REQUESTS.as_u16().into(),
+            vec![("retry-after", retry_after.as_str())],
+            Some(format!("{error}").as_bytes()),
+        );
+    }
+
+    fn send_ratelimit_exceeded(&mut self, error: ratelimit::Error) {
+        let headers = error.headers();
+        let error = ServerError::ExceededRatelimit(error);
+        warn!("server error occurred: {}", error);
+        self.record_error(StatusCode::TOO_MANY_REQUESTS, &error);
+        self.send_http_response(
+            StatusCode::TOO_MANY_REQUESTS.as_u16().into(),
+            headers
+                .iter()
+                .map(|(name, value)| (*name, value.as_str()))
+                .collect(),
+            Some(format!("{error}").as_bytes()),
+        );
+    }
+
+    fn send_server_error(&mut self, error: ServerError, override_status_code: Option<StatusCode>) {
         warn!("server error occurred: {}", error);
+        let status = override_status_code.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        self.record_error(status, &error);
         self.send_http_response(
-            override_status_code
-                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
-                .as_u16()
-                .into(),
+            status.as_u16().into(),
             vec![],
             Some(format!("{error}").as_bytes()),
         );
     }
 
+    fn metric_tags(&self) -> [(&'static str, String); 3] {
+        let llm_provider = self.llm_provider();
+        let model = self
+            .request_model
+            .as_ref()
+            .or(llm_provider.model.as_ref())
+            .map_or("unknown", |model| model.as_str());
+        let status = self
+            .response_status
+            .map_or_else(|| "unknown".to_string(), |status| status.to_string());
+        [
+            ("provider", llm_provider.name.clone()),
+            ("model", model.to_string()),
+            ("status", status),
+        ]
+    }
+
+    fn count_error(&self, class: &str) {
+        let provider = self
+            .llm_provider
+            .as_ref()
+            .map_or("unknown", |llm_provider| llm_provider.name.as_str());
+        self.metrics
+            .request_errors
+            .with_tags(&[("provider", provider), ("class", class)])
+            .increment(1);
+    }
+
+    fn count_upstream_status(&self, status: u16) {
+        if status >= 500 {
+            self.count_error("upstream_5xx");
+        } else if status >= 400 {
+            self.count_error("upstream_4xx");
+        }
+    }
+
+    // Local replies do not pass through this filter's response callbacks, their status is kept
+    // for the egress span.
+    fn record_error(&mut self, status: StatusCode, error: &ServerError) {
+        self.count_error(error.class());
+        self.response_status = Some(status.as_u16());
+        self.response_error = Some(error.to_string());
+    }
+
     fn enforce_ratelimits(
         &mut self,
         model: &str,
-        json_string: &str,
+        token_count: usize,
     ) -> Result<(), ratelimit::Error> {
-        // Tokenize and record token count.
-        let token_count = tokenizer::token_count(model, json_string).unwrap_or(0);
-
         debug!("Recorded input token count: {}", token_count);
-        // Record the token count to metrics.
-        self.metrics
-            .input_sequence_length
-            .record(token_count as u64);
+        self.input_token_count = Some(token_count);
+
+        let descriptor = Descriptor {
+            model: model.to_owned(),
+            selector: self.ratelimit_selector.clone(),
+            path: Some(self.request_path.clone()),
+            jwt_claims: self.jwt_claims.clone(),
+        };
 
         // Check if rate limiting needs to be applied.
-        if let Some(selector) = self.ratelimit_selector.take() {
+        let ratelimits = ratelimit::ratelimits(None).read().unwrap();
+        if ratelimits.applies_to(&descriptor) {
             log::debug!("Applying ratelimit for model: {}", model);
-            ratelimit::ratelimits(None).read().unwrap().check_limit(
-                model.to_owned(),
-                selector,
-                NonZero::new(token_count as u32).unwrap(),
+            self.ratelimit_quota = ratelimits.check_limit(
+                &HostSharedStore,
+                &descriptor,
+                NonZero::new(token_count.max(1) as u32).unwrap(),
+                current_time_ns() as u64,
             )?;
+            // output tokens are debited from the same buckets once the response ends
+            self.ratelimit_descriptor = Some(descriptor);
         } else {
             debug!("No rate limit applied for model: {}", model);
         }
 
         Ok(())
     }
+
+    fn record_usage(&mut self, usage: &Usage) {
+        self.usage = Some(usage.clone());
+        self.record_cost(usage);
+
+        if let Some(descriptor) = self.ratelimit_descriptor.take() {
+            ratelimit::ratelimits(None).read().unwrap().debit_output(
+                &HostSharedStore,
+                &descriptor,
+                usage.completion_tokens as u32,
+                current_time_ns() as u64,
+            );
+        }
+
+        if !self.budget_reservations.is_empty() {
+            budget::settle(
+                &HostSharedStore,
+                &std::mem::take(&mut self.budget_reservations),
+                usage.total_tokens as u64,
+                self.cost_usd.unwrap_or_default(),
+            );
+        }
+    }
+
+    fn record_cost(&mut self, usage: &Usage) {
+        let pricing = match self.llm_provider().pricing.as_ref() {
+            Some(pricing) => pricing,
+            None => return,
+        };
+
+        let cost_usd = pricing.cost_usd(
+            usage.prompt_tokens,
+            usage.cached_prompt_tokens(),
+            usage.completion_tokens,
+        );
+        debug!("request cost: {} USD", format_cost(cost_usd));
+        self.metrics
+            .cost
+            .with_tags(&self.metric_tags())
+            .record((cost_usd * 1_000_000.0).round() as u64);
+        self.cost_usd = Some(cost_usd);
+    }
+
+    // Streamed responses have sent their headers by the time usage arrives, the cost goes in a trailer.
+    fn add_cost_trailer(&mut self) {
+        if self.cost_reported {
+            return;
+        }
+        if let Some(cost_usd) = self.cost_usd {
+            self.add_http_response_trailer(ARCH_COST_HEADER, &format_cost(cost_usd));
+            self.cost_reported = true;
+        }
+    }
+
+    // Returns whether any event was decoded, events can be split across body callbacks and the
+    // decoder carries partial ones over.
+    fn read_stream_events(&mut self, body: &[u8]) -> bool {
+        let events = self.sse_decoder.decode(body);
+        let received_event = !events.is_empty();
+
+        for event in events {
+            // ping events usually come from anthropic
+            if event.is_done() || event.data == r#"{"type": "ping"}"# {
+                continue;
+            }
+            match ChatCompletionStreamResponse::try_from(&event) {
+                Ok(chunk) => {
+                    self.response_id.get_or_insert(chunk.id);
+                    self.response_model.get_or_insert(chunk.model);
+                    self.finish_reasons.extend(
+                        chunk
+                            .choices
+                            .into_iter()
+                            .filter_map(|choice| choice.finish_reason),
+                    );
+                    if let Some(usage) = chunk.usage.as_ref() {
+                        self.response_tokens += usage.completion_tokens;
+                        self.record_usage(usage);
+                    }
+                }
+                Err(e) => {
+                    warn!("error in response event: {}", e);
+                    self.count_error("response_parse");
+                }
+            }
+        }
+
+        received_event
+    }
+
+    fn read_response(&mut self, body: &[u8]) -> Result<Option<Usage>, OpenAIError> {
+        let hermes_llm_provider = Provider::from(&self.llm_provider().provider_interface);
+        let response = ChatCompletionsResponse::try_from((body, &hermes_llm_provider))?;
+        self.response_id = Some(response.id);
+        self.response_model = response.model;
+        self.finish_reasons = response
+            .choices
+            .into_iter()
+            .filter_map(|choice| choice.finish_reason)
+            .collect();
+        Ok(response.usage)
+    }
+
+    // The fallback response arrives whole, its usage is accounted for like that of a response
+    // from the primary provider.
+    fn read_fallback_response(&mut self, chat_completions: &[u8]) {
+        if self.streaming_response {
+            self.read_stream_events(chat_completions);
+            return;
+        }
+        match self.read_response(chat_completions) {
+            Ok(Some(usage)) => {
+                self.response_tokens += usage.completion_tokens;
+                self.record_usage(&usage);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!("could not parse fallback response: {}", e);
+                self.count_error("response_parse");
+            }
+        }
+    }
+
+    // Embeddings requests are held to the same rate limits and budgets as chat completions. The
+    // model the client asks for is kept, it has to match the one its stored embeddings were made with.
+    fn on_embeddings_request_body(&mut self, body_size: usize, body_bytes: &[u8]) -> Action {
+        if LlmApi::of_provider(&self.llm_provider().provider_interface) != LlmApi::ChatCompletions {
+            self.send_server_error(
+                ServerError::BadRequest {
+                    why: format!(
+                        "LLM Provider \"{}\" does not support embeddings",
+                        self.llm_provider()
+                    ),
+                },
+                Some(StatusCode::BAD_REQUEST),
+            );
+            return Action::Pause;
+        }
+
+        let mut request = match serde_json::from_slice::<EmbeddingsRequest>(body_bytes) {
+            Ok(request) => request,
+            Err(e) => {
+                self.send_server_error(
+                    ServerError::OpenAIPError(OpenAIError::from(e)),
+                    Some(StatusCode::BAD_REQUEST),
+                );
+                return Action::Pause;
+            }
+        };
+
+        info!(
+            "on_embeddings_request_body: provider: {}, model: {}",
+            self.llm_provider().name,
+            request.model
+        );
+
+        let texts = request.input.texts();
+        let input_token_count = request.input.token_count()
+            + if texts.is_empty() {
+                0
+            } else {
+                tokenizer::token_count(&request.model, &texts.join(" ")).unwrap_or(0)
+            };
+        if let Err(e) = self.enforce_ratelimits(&request.model, input_token_count) {
+            self.send_ratelimit_exceeded(e);
+            self.metrics
+                .ratelimited_rq
+                .with_tags(&self.metric_tags())
+                .increment(1);
+            return Action::Continue;
+        }
+        if let Err(e) = self.enforce_budgets(&request.model) {
+            self.send_budget_exceeded(e);
+            self.metrics
+                .ratelimited_rq
+                .with_tags(&self.metric_tags())
+                .increment(1);
+            return Action::Continue;
+        }
+
+        request.model = upstream_model(self.llm_provider(), &request.model);
+        self.request_model = Some(request.model.clone());
+        match serde_json::to_vec(&request) {
+            Ok(body) => self.set_http_request_body(0, body_size, &body),
+            Err(e) => {
+                self.send_server_error(
+                    ServerError::OpenAIPError(OpenAIError::from(e)),
+                    Some(StatusCode::BAD_REQUEST),
+                );
+                return Action::Pause;
+            }
+        }
+
+        Action::Continue
+    }
+
+    // Translates the response body into the client's format, returns the body in the chat
+    // completions format that usage is read from.
+    fn translate_response_body(&mut self, body: &[u8]) -> Result<Vec<u8>, TransformError> {
+        let client_api = self.client_api.unwrap_or(LlmApi::ChatCompletions);
+        let upstream_api = LlmApi::of_provider(&self.llm_provider().provider_interface);
+        let translated = self.response_translator.translate(
+            upstream_api,
+            client_api,
+            self.streaming_response,
+            body,
+        );
+
+        let skipped_events = self.response_translator.take_skipped_events();
+        if skipped_events > 0 {
+            warn!(
+                "left out {} response events that could not be translated",
+                skipped_events
+            );
+            for _ in 0..skipped_events {
+                self.count_error("response_parse");
+            }
+        }
+
+        let translated = translated?;
+        if let Some(client_body) = translated.client.as_ref() {
+            self.set_http_response_body(0, body.len(), client_body);
+        }
+        Ok(translated.chat_completions)
+    }
+
+    // Whether the client speaks the upstream API, its response body is then sent on as is.
+    fn client_speaks_upstream_api(&self) -> bool {
+        self.client_api == Some(LlmApi::of_provider(&self.llm_provider().provider_interface))
+    }
+
+    // Reports the request to the tracing backend with the OpenTelemetry GenAI semantic conventions,
+    // https://opentelemetry.io/docs/specs/semconv/gen-ai/gen-ai-spans/
+    fn emit_egress_span(&self) {
+        let trace_context = match self.trace_context.as_ref() {
+            Some(trace_context) if trace_context.sampled => trace_context,
+            _ => return,
+        };
+
+        let start_time_ns = self.request_body_sent_time.unwrap_or_else(|| {
+            self.start_time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+        });
+        let mut llm_span = trace_context.span(
+            "egress_traffic".to_string(),
+            start_time_ns,
+            current_time_ns(),
+        );
+        llm_span.kind = SPAN_KIND_CLIENT;
+
+        let operation = match self.client_api {
+            Some(LlmApi::Embeddings) => "embeddings",
+            _ => "chat",
+        };
+        llm_span.add_attribute("gen_ai.operation.name".to_string(), operation.to_string());
+        if let Some(llm_provider) = self.llm_provider.as_ref() {
+            llm_span.add_attribute(
+                "gen_ai.system".to_string(),
+                gen_ai_system(&llm_provider.provider_interface),
+            );
+            // kept for dashboards built before the gen_ai attributes
+            llm_span.add_attribute("model".to_string(), llm_provider.name.to_string());
+        }
+        if let Some(request_model) = self.request_model.as_ref() {
+            llm_span.add_attribute("gen_ai.request.model".to_string(), request_model.clone());
+        }
+        if let Some(request) = self.chat_completions_request.as_ref() {
+            if let Some(temperature) = request.temperature {
+                llm_span.add_double_attribute(
+                    "gen_ai.request.temperature".to_string(),
+                    temperature as f64,
+                );
+            }
+            if let Some(max_tokens) = request.max_tokens {
+                llm_span
+                    .add_int_attribute("gen_ai.request.max_tokens".to_string(), max_tokens as i64);
+            }
+        }
+        if let Some(response_id) = self.response_id.as_ref() {
+            llm_span.add_attribute("gen_ai.response.id".to_string(), response_id.clone());
+        }
+        if let Some(response_model) = self.response_model.as_ref() {
+            llm_span.add_attribute("gen_ai.response.model".to_string(), response_model.clone());
+        }
+        if !self.finish_reasons.is_empty() {
+            llm_span.add_string_array_attribute(
+                "gen_ai.response.finish_reasons".to_string(),
+                self.finish_reasons.clone(),
+            );
+        }
+        if let Some(usage) = self.usage.as_ref() {
+            llm_span.add_int_attribute(
+                "gen_ai.usage.input_tokens".to_string(),
+                usage.prompt_tokens as i64,
+            );
+            llm_span.add_int_attribute(
+                "gen_ai.usage.output_tokens".to_string(),
+                usage.completion_tokens as i64,
+            );
+        }
+
+        if let Some(user_message) = self.user_message.as_ref() {
+            if let Some(prompt) = user_message.content.as_ref() {
+                llm_span.add_attribute("user_prompt".to_string(), prompt.to_string());
+            }
+        }
+        if let Some(cost_usd) = self.cost_usd {
+            llm_span.add_attribute("cost_usd".to_string(), format_cost(cost_usd));
+        }
+        if let Some(ttft_time) = self.ttft_time {
+            llm_span.add_event(Event::new("time_to_first_token".to_string(), ttft_time));
+        }
+
+        if let Some(status) = self.response_status {
+            llm_span.add_int_attribute("http.response.status_code".to_string(), status as i64);
+            if status >= 400 {
+                llm_span.add_attribute("error.type".to_string(), status.to_string());
+                llm_span.set_status(STATUS_CODE_ERROR, self.response_error.clone());
+            }
+        }
+
+        let dropped = self.traces_queue.lock().unwrap().push([llm_span]);
+        if dropped > 0 {
+            self.metrics
+                .trace_export
+                .dropped_spans
+                .increment(dropped as i64);
+        }
+    }
+
+    // Re-issues the transformed request to the next fallback provider that can be dispatched to.
+    // Returns false when there is no fallback left and the upstream response should be passed through.
+    fn dispatch_to_next_fallback(&mut self, status: u16) -> bool {
+        let request = match self.chat_completions_request.as_ref() {
+            Some(request) => request.clone(),
+            None => return false,
+        };
+
+        while let Some(fallback) = self.fallback_providers.pop_front() {
+            if !try_acquire(&fallback) {
+                debug!(
+                    "skipping fallback llm provider {}, its circuit is not closed",
+                    fallback
+                );
+                continue;
+            }
+            warn!(
+                "llm provider {} responded with status {}, failing over to {}",
+                self.llm_provider(),
+                status,
+                fallback
+            );
+            match self.dispatch_fallback_request(&fallback, request.clone()) {
+                Ok(_) => {
+                    self.failed_over = true;
+                    self.llm_provider = Some(fallback);
+                    return true;
+                }
+                Err(e) => {
+                    warn!("could not fail over to llm provider {}: {}", fallback, e);
+                    self.count_error(e.class());
+                }
+            }
+        }
+
+        false
+    }
+
+    fn dispatch_fallback_request(
+        &self,
+        fallback: &LlmProvider,
+        mut request: ChatCompletionsRequest,
+    ) -> Result<u32, ServerError> {
+        let mut auth_headers = match fallback.access_key.as_ref() {
+            _ if fallback.aws.is_some() => vec![],
+            Some(access_key) => provider_auth_headers(fallback, access_key),
+            None if fallback.endpoint.is_some()
+                || fallback.provider_interface == LlmProviderType::Arch =>
+            {
+                vec![]
+            }
+            None => {
+                return Err(ServerError::MissingAccessKey {
+                    why: format!(
+                        "No access key configured for fallback LLM Provider \"{}\"",
+                        fallback
+                    ),
+                })
+            }
+        };
+
+        // the request still carries the model of the provider that failed, e.g. an azure deployment
+        let Some(model) = fallback.model.as_ref() else {
+            return Err(ServerError::LogicError(format!(
+                "fallback LLM Provider \"{}\" has no model",
+                fallback
+            )));
+        };
+        request.model = upstream_model(fallback, model);
+        let hermes_llm_provider = Provider::from(&fallback.provider_interface);
+        let body = request.to_bytes(hermes_llm_provider)?;
+
+        let upstream_cluster = fallback.cluster_name();
+        let path = provider_request_path(fallback, &self.request_path, Some(&request));
+        if let Some(aws) = fallback.aws.as_ref() {
+            auth_headers.extend(aws_auth_headers(fallback, aws, &path, &body));
+        }
+        let timeout_str = LLM_FAILOVER_REQUEST_TIMEOUT_MS.to_string();
+
+        let mut headers = vec![
+            (":method", "POST"),
+            (ARCH_UPSTREAM_HOST_HEADER, upstream_cluster.as_str()),
+            (":path", path.as_str()),
+            (":authority", upstream_cluster.as_str()),
+            ("content-type", "application/json"),
+            ("x-envoy-upstream-rq-timeout-ms", timeout_str.as_str()),
+        ];
+        for (name, value) in auth_headers.iter() {
+            headers.push((name, value));
+        }
+        for (name, value) in fallback.headers.iter().flatten() {
+            headers.push((name, value));
+        }
+        if let Some(request_id) = self.request_id.as_ref() {
+            headers.push((REQUEST_ID_HEADER, request_id));
+        }
+        let traceparent = self
+            .trace_context
+            .as_ref()
+            .map(|trace_context| trace_context.traceparent().to_string());
+        if let Some(traceparent) = traceparent.as_ref() {
+            headers.push((TRACE_PARENT_HEADER, traceparent));
+        }
+
+        let call_args = CallArgs::new(
+            ARCH_INTERNAL_CLUSTER_NAME,
+            &path,
+            headers,
+            Some(&body),
+            vec![],
+            Duration::from_millis(LLM_FAILOVER_REQUEST_TIMEOUT_MS),
+        );
+        let call_context = FailoverCallContext {
+            llm_provider: fallback.name.clone(),
+        };
+
+        self.http_call(call_args, call_context)
+            .map_err(ServerError::HttpDispatch)
+    }
 }
 
 // HttpContext is the trait that allows the Rust code to interact with HTTP objects.
 impl HttpContext for StreamContext {
     // Envoy's HTTP model is event driven. The WASM ABI has given implementors events to hook onto
     // the lifecycle of the http request and response.
-    fn on_http_request_headers(&mut self, _num_headers: usize, _end_of_stream: bool) -> Action {
+    fn on_http_request_headers(&mut self, _num_headers: usize, end_of_stream: bool) -> Action {
         let request_path = self.get_http_request_header(":path").unwrap_or_default();
         if request_path == HEALTHZ_PATH {
             self.send_http_response(200, vec![], None);
             return Action::Continue;
         }
+        self.request_path = request_path.clone();
+
+        self.client_api = LlmApi::from_path(&request_path);
 
-        self.is_chat_completions_request = CHAT_COMPLETIONS_PATH == request_path;
+        // read before the authorization header is replaced with the provider's key
+        self.save_jwt_claims();
 
         let use_agent_orchestrator = match self.overrides.as_ref() {
             Some(overrides) => overrides.use_agent_orchestrator.unwrap_or_default(),
@@ -226,8 +897,12 @@ impl HttpContext for StreamContext {
                 provider_interface: LlmProviderType::OpenAI,
                 ..Default::default()
             }));
+            if matches!(self.client_api, Some(LlmApi::Messages | LlmApi::Responses)) {
+                self.set_http_request_header(":path", Some(CHAT_COMPLETIONS_PATH));
+            }
         } else {
             self.select_llm_provider();
+            self.fallback_providers = self.llm_providers.fallbacks(self.llm_provider()).into();
             if self.llm_provider().endpoint.is_some() {
                 self.add_http_request_header(
                     ARCH_ROUTING_HEADER,
@@ -249,13 +924,38 @@ impl HttpContext for StreamContext {
                     self.send_server_error(error, Some(StatusCode::BAD_REQUEST));
                 }
             }
+            for (name, value) in self.llm_provider().headers.iter().flatten() {
+                self.set_http_request_header(name, Some(value));
+            }
         }
 
         self.delete_content_length_header();
         self.save_ratelimit_header();
+        self.save_budget_headers();
 
         self.request_id = self.get_http_request_header(REQUEST_ID_HEADER);
-        self.traceparent = self.get_http_request_header(TRACE_PARENT_HEADER);
+        self.trace_context = TraceContext::new(
+            self.get_http_request_header(TRACE_PARENT_HEADER).as_deref(),
+            self.tracing.as_ref().as_ref(),
+        );
+        if let Some(trace_context) = self.trace_context.as_ref() {
+            // the upstream's spans are children of the egress span
+            self.set_http_request_header(
+                TRACE_PARENT_HEADER,
+                Some(&trace_context.traceparent().to_string()),
+            );
+        }
+
+        let has_model_in_path = self.llm_provider.as_ref().is_some_and(|llm_provider| {
+            matches!(
+                LlmApi::of_provider(&llm_provider.provider_interface),
+                LlmApi::GenerateContent | LlmApi::Converse
+            )
+        });
+        if has_model_in_path && self.client_api.is_some() && !end_of_stream {
+            // hold the headers, the path is rewritten once the model is read from the body
+            return Action::Pause;
+        }
 
         Action::Continue
     }
@@ -295,7 +995,23 @@ impl HttpContext for StreamContext {
             }
         };
 
-        let mut deserialized_body = match ChatCompletionsRequest::try_from(body_bytes.as_slice()) {
+        if self.client_api == Some(LlmApi::Embeddings) {
+            return self.on_embeddings_request_body(body_size, &body_bytes);
+        }
+
+        let deserialized_body = match self.client_api {
+            Some(LlmApi::Messages) => serde_json::from_slice::<MessagesRequest>(&body_bytes)
+                .map_err(OpenAIError::from)
+                .and_then(|messages_request| {
+                    self.messages_request = Some(messages_request.clone());
+                    ChatCompletionsRequest::try_from(messages_request)
+                }),
+            Some(LlmApi::Responses) => serde_json::from_slice::<ResponsesRequest>(&body_bytes)
+                .map_err(OpenAIError::from)
+                .and_then(ChatCompletionsRequest::try_from),
+            _ => ChatCompletionsRequest::try_from(body_bytes.as_slice()),
+        };
+        let mut deserialized_body = match deserialized_body {
             Ok(deserialized) => deserialized,
             Err(e) => {
                 debug!(
@@ -372,22 +1088,55 @@ impl HttpContext for StreamContext {
                         .to_string()
                         .as_str()
             });
+        let input_token_count =
+            tokenizer::token_count(&deserialized_body.model, &input_tokens_str).unwrap_or(0);
         // enforce ratelimits on ingress
-        if let Err(e) = self.enforce_ratelimits(&deserialized_body.model, input_tokens_str.as_str())
-        {
-            self.send_server_error(
-                ServerError::ExceededRatelimit(e),
-                Some(StatusCode::TOO_MANY_REQUESTS),
-            );
-            self.metrics.ratelimited_rq.increment(1);
+        if let Err(e) = self.enforce_ratelimits(&deserialized_body.model, input_token_count) {
+            self.send_ratelimit_exceeded(e);
+            self.metrics
+                .ratelimited_rq
+                .with_tags(&self.metric_tags())
+                .increment(1);
             return Action::Continue;
         }
 
-        let llm_provider_str = self.llm_provider().provider_interface.to_string();
-        let hermes_llm_provider = Provider::from(llm_provider_str.as_str());
+        // enforce budgets on ingress, they are settled with the actual usage once the response ends
+        if let Err(e) = self.enforce_budgets(&deserialized_body.model) {
+            self.send_budget_exceeded(e);
+            self.metrics
+                .ratelimited_rq
+                .with_tags(&self.metric_tags())
+                .increment(1);
+            return Action::Continue;
+        }
 
-        // convert chat completion request to llm provider specific request
-        let deserialized_body_bytes = match deserialized_body.to_bytes(hermes_llm_provider) {
+        deserialized_body.model = upstream_model(self.llm_provider(), &deserialized_body.model);
+        self.request_model = Some(deserialized_body.model.clone());
+
+        let hermes_llm_provider = Provider::from(&self.llm_provider().provider_interface);
+
+        // convert chat completion request to llm provider specific request, anthropic requests are
+        // passed through to anthropic as they are
+        let upstream_api = LlmApi::of_provider(&self.llm_provider().provider_interface);
+        let upstream_path = matches!(upstream_api, LlmApi::GenerateContent | LlmApi::Converse)
+            .then(|| {
+                provider_request_path(
+                    self.llm_provider(),
+                    &self.request_path,
+                    Some(&deserialized_body),
+                )
+            });
+        if let Some(path) = upstream_path.as_ref() {
+            self.set_http_request_header(":path", Some(path));
+        }
+        let deserialized_body_bytes = match self.messages_request.as_mut() {
+            Some(messages_request) if upstream_api == LlmApi::Messages => {
+                messages_request.model = deserialized_body.model.clone();
+                serde_json::to_vec(messages_request).map_err(OpenAIError::from)
+            }
+            _ => deserialized_body.to_bytes(hermes_llm_provider),
+        };
+        let deserialized_body_bytes = match deserialized_body_bytes {
             Ok(bytes) => bytes,
             Err(e) => {
                 warn!("Failed to serialize request body: {}", e);
@@ -396,7 +1145,16 @@ impl HttpContext for StreamContext {
             }
         };
 
+        if let (Some(aws), Some(path)) = (self.llm_provider().aws.as_ref(), upstream_path) {
+            for (name, value) in
+                aws_auth_headers(self.llm_provider(), aws, &path, &deserialized_body_bytes)
+            {
+                self.set_http_request_header(name, Some(&value));
+            }
+        }
+
         self.set_http_request_body(0, body_size, &deserialized_body_bytes);
+        self.chat_completions_request = Some(deserialized_body);
 
         Action::Continue
     }
@@ -412,6 +1170,83 @@ impl HttpContext for StreamContext {
             Some("hello world from filter".as_bytes()),
         );
 
+        let status = self
+            .get_http_response_header(":status")
+            .and_then(|status| status.parse::<u16>().ok());
+        self.response_status = status;
+        if let Some(status) = status {
+            self.count_upstream_status(status);
+        }
+
+        let has_circuit_breaker = self
+            .llm_provider
+            .as_ref()
+            .is_some_and(|llm_provider| llm_provider.circuit_breaker.is_some());
+        if has_circuit_breaker || !self.fallback_providers.is_empty() {
+            if let Some(status) = status {
+                self.upstream_status = Some(status);
+                if is_upstream_failure(status) {
+                    // upstream timeouts surface here as 504 responses generated by envoy
+                    circuit_breaker::record_failure(
+                        &HostSharedStore,
+                        self.llm_provider(),
+                        current_time_ms(),
+                    );
+                    if self.dispatch_to_next_fallback(status) {
+                        return Action::Pause;
+                    }
+                } else if end_of_stream {
+                    circuit_breaker::record_success(&HostSharedStore, self.llm_provider());
+                }
+            }
+        }
+
+        let upstream_api = self
+            .llm_provider
+            .as_ref()
+            .map(|llm_provider| LlmApi::of_provider(&llm_provider.provider_interface));
+        if let (Some(client_api), Some(upstream_api)) = (self.client_api, upstream_api) {
+            if client_api != LlmApi::Embeddings
+                && (client_api != LlmApi::ChatCompletions
+                    || upstream_api != LlmApi::ChatCompletions)
+            {
+                let is_success = status.is_some_and(is_success_status);
+                if is_success {
+                    self.translate_response = true;
+                }
+                if is_success && client_api != upstream_api {
+                    // the body is rewritten to the client's format
+                    self.set_http_response_header("content-length", None);
+                    if upstream_api == LlmApi::Converse && self.streaming_response {
+                        // bedrock streams are binary eventstreams, clients receive server-sent events
+                        self.set_http_response_header("content-type", Some("text/event-stream"));
+                    }
+                }
+            }
+        }
+
+        if let Some(quota) = self.ratelimit_quota {
+            // the gateway's limits are the ones the client is held to, not the provider's
+            for (name, value) in quota.headers() {
+                self.set_http_response_header(name, Some(&value));
+            }
+        }
+
+        let has_pricing = self
+            .llm_provider
+            .as_ref()
+            .is_some_and(|llm_provider| llm_provider.pricing.is_some());
+        if has_pricing
+            && self.client_api.is_some()
+            && !self.streaming_response
+            && !end_of_stream
+            && status.is_some_and(is_success_status)
+        {
+            // hold the headers until usage is known so that the cost can be returned as a header
+            self.buffer_response_for_cost = true;
+            return Action::Pause;
+        }
+
         Action::Continue
     }
 
@@ -421,12 +1256,25 @@ impl HttpContext for StreamContext {
             self.context_id, body_size, end_of_stream
         );
 
+        if self.failed_over {
+            // the failed upstream response is discarded, the fallback response is sent once it arrives
+            return Action::Pause;
+        }
+
+        if end_of_stream {
+            if let Some(status) = self.upstream_status.take() {
+                if !is_upstream_failure(status) {
+                    circuit_breaker::record_success(&HostSharedStore, self.llm_provider());
+                }
+            }
+        }
+
         if self.request_body_sent_time.is_none() {
             debug!("on_http_response_body: request body not sent, not doing any processing in llm filter");
             return Action::Continue;
         }
 
-        if !self.is_chat_completions_request {
+        if self.client_api.is_none() {
             info!("on_http_response_body: non-chatcompletion request");
             return Action::Continue;
         }
@@ -441,14 +1289,20 @@ impl HttpContext for StreamContext {
                     let duration_ms = duration.as_millis();
                     info!("on_http_response_body: request latency: {}ms", duration_ms);
                     // Record the latency to the latency histogram
-                    self.metrics.request_latency.record(duration_ms as u64);
+                    self.metrics
+                        .request_latency
+                        .with_tags(&self.metric_tags())
+                        .record(duration_ms as u64);
 
                     if self.response_tokens > 0 {
                         // Compute the time per output token
                         let tpot = duration_ms as u64 / self.response_tokens as u64;
 
                         // Record the time per output token
-                        self.metrics.time_per_output_token.record(tpot);
+                        self.metrics
+                            .time_per_output_token
+                            .with_tags(&self.metric_tags())
+                            .record(tpot);
 
                         debug!(
                             "time per token: {}ms, tokens per second: {}",
@@ -456,7 +1310,10 @@ impl HttpContext for StreamContext {
                             1000 / tpot
                         );
                         // Record the tokens per second
-                        self.metrics.tokens_per_second.record(1000 / tpot);
+                        self.metrics
+                            .tokens_per_second
+                            .with_tags(&self.metric_tags())
+                            .record(1000 / tpot);
                     }
                 }
                 Err(e) => {
@@ -466,52 +1323,26 @@ impl HttpContext for StreamContext {
             // Record the output sequence length
             self.metrics
                 .output_sequence_length
+                .with_tags(&self.metric_tags())
                 .record(self.response_tokens as u64);
 
-            if let Some(traceparent) = self.traceparent.as_ref() {
-                let current_time_ns = current_time_ns();
-
-                match Traceparent::try_from(traceparent.to_string()) {
-                    Err(e) => {
-                        warn!("traceparent header is invalid: {}", e);
-                    }
-                    Ok(traceparent) => {
-                        let mut trace_data = common::tracing::TraceData::new();
-                        let mut llm_span = Span::new(
-                            "egress_traffic".to_string(),
-                            Some(traceparent.trace_id),
-                            Some(traceparent.parent_id),
-                            self.request_body_sent_time.unwrap(),
-                            current_time_ns,
-                        );
-                        if let Some(user_message) = self.user_message.as_ref() {
-                            if let Some(prompt) = user_message.content.as_ref() {
-                                llm_span
-                                    .add_attribute("user_prompt".to_string(), prompt.to_string());
-                            }
-                        }
-                        llm_span.add_attribute(
-                            "model".to_string(),
-                            self.llm_provider().name.to_string(),
-                        );
-
-                        if self.ttft_time.is_some() {
-                            llm_span.add_event(Event::new(
-                                "time_to_first_token".to_string(),
-                                self.ttft_time.unwrap(),
-                            ));
-                            trace_data.add_span(llm_span);
-                        }
-
-                        self.traces_queue.lock().unwrap().push_back(trace_data);
-                    }
-                };
-            }
+            self.add_cost_trailer();
+            return Action::Continue;
+        }
 
+        if self
+            .response_status
+            .is_some_and(|status| !is_success_status(status))
+        {
+            // error responses carry no usage, the client receives the upstream status and body as is
             return Action::Continue;
         }
 
         let body = if self.streaming_response {
+            if self.translation_failed {
+                self.set_http_response_body(0, body_size, &[]);
+                return Action::Continue;
+            }
             let chunk_start = 0;
             let chunk_size = body_size;
             debug!(
@@ -536,18 +1367,61 @@ impl HttpContext for StreamContext {
                     chunk_size
                 );
             }
-            streaming_chunk
+            if self.translate_response {
+                match self.translate_response_body(&streaming_chunk) {
+                    Ok(chat_completions) => chat_completions,
+                    Err(e) => {
+                        warn!("could not translate response: {}", e);
+                        self.count_error("response_parse");
+                        if !self.client_speaks_upstream_api() {
+                            // the upstream events are never sent on in a format the client does not speak
+                            let error_event = stream_error_event(
+                                self.client_api.unwrap_or(LlmApi::ChatCompletions),
+                                &format!("could not translate upstream response: {}", e),
+                            );
+                            self.set_http_response_body(0, body_size, &error_event);
+                            self.translation_failed = true;
+                        }
+                        return Action::Continue;
+                    }
+                }
+            } else {
+                streaming_chunk
+            }
         } else {
+            if (self.buffer_response_for_cost || self.translate_response) && !end_of_stream {
+                return Action::Pause;
+            }
             if body_size == 0 {
                 return Action::Continue;
             }
             debug!("non streaming response bytes read: 0:{}", body_size);
-            match self.get_http_response_body(0, body_size) {
+            let body = match self.get_http_response_body(0, body_size) {
                 Some(body) => body,
                 None => {
                     warn!("non streaming response body empty");
                     return Action::Continue;
                 }
+            };
+            if self.translate_response {
+                match self.translate_response_body(&body) {
+                    Ok(chat_completions) => chat_completions,
+                    Err(e) if self.client_speaks_upstream_api() => {
+                        // the client receives the upstream body as is, only its usage is lost
+                        warn!("could not translate response: {}", e);
+                        self.count_error("response_parse");
+                        return Action::Continue;
+                    }
+                    Err(e) => {
+                        self.send_server_error(
+                            ServerError::ResponseTranslation(e),
+                            Some(StatusCode::BAD_GATEWAY),
+                        );
+                        return Action::Continue;
+                    }
+                }
+            } else {
+                body
             }
         };
 
@@ -558,40 +1432,15 @@ impl HttpContext for StreamContext {
             );
         }
 
-        let llm_provider_str = self.llm_provider().provider_interface.to_string();
-        let hermes_llm_provider = Provider::from(llm_provider_str.as_str());
-
         if self.streaming_response {
-            let chat_completions_chunk_response_events =
-                match SseChatCompletionIter::try_from((body.as_slice(), &hermes_llm_provider)) {
-                    Ok(events) => events,
-                    Err(e) => {
-                        warn!(
-                            "could not parse response: {}, body str: {}",
-                            e,
-                            String::from_utf8_lossy(&body)
-                        );
-                        return Action::Continue;
-                    }
-                };
+            let received_first_event = self.read_stream_events(&body);
 
-            for event in chat_completions_chunk_response_events {
-                match event {
-                    Ok(event) => {
-                        if let Some(usage) = event.usage.as_ref() {
-                            self.response_tokens += usage.completion_tokens;
-                        }
-                    }
-                    Err(e) => {
-                        warn!("error in response event: {}", e);
-                        continue;
-                    }
-                }
+            if end_of_stream {
+                self.add_cost_trailer();
             }
 
             // Compute TTFT if not already recorded
-            if self.ttft_duration.is_none() {
-                // if let Some(start_time) = self.start_time {
+            if self.ttft_duration.is_none() && received_first_event {
                 let current_time = get_current_time().unwrap();
                 self.ttft_time = Some(current_time_ns());
                 match current_time.duration_since(self.start_time) {
@@ -602,7 +1451,10 @@ impl HttpContext for StreamContext {
                             duration_ms
                         );
                         self.ttft_duration = Some(duration);
-                        self.metrics.time_to_first_token.record(duration_ms as u64);
+                        self.metrics
+                            .time_to_first_token
+                            .with_tags(&self.metric_tags())
+                            .record(duration_ms as u64);
                     }
                     Err(e) => {
                         warn!("SystemTime error: {:?}", e);
@@ -611,9 +1463,25 @@ impl HttpContext for StreamContext {
             }
         } else {
             debug!("non streaming response");
-            let chat_completions_response =
-                match ChatCompletionsResponse::try_from((body.as_slice(), &hermes_llm_provider)) {
-                    Ok(de) => de,
+            let usage = if self.client_api == Some(LlmApi::Embeddings) {
+                match serde_json::from_slice::<EmbeddingsResponse>(&body) {
+                    Ok(response) => {
+                        self.response_model = Some(response.model);
+                        Some(Usage::from(response.usage))
+                    }
+                    Err(e) => {
+                        warn!(
+                            "could not parse embeddings response: {}, body str: {}",
+                            e,
+                            String::from_utf8_lossy(&body)
+                        );
+                        self.count_error("response_parse");
+                        return Action::Continue;
+                    }
+                }
+            } else {
+                match self.read_response(&body) {
+                    Ok(usage) => usage,
                     Err(e) => {
                         warn!(
                             "could not parse response: {}, body str: {}",
@@ -626,15 +1494,24 @@ impl HttpContext for StreamContext {
                             String::from_utf8_lossy(&body)
                         );
                         self.send_server_error(
-                            ServerError::OpenAIPError(e),
+                            ServerError::UpstreamResponse(e),
                             Some(StatusCode::BAD_REQUEST),
                         );
                         return Action::Continue;
                     }
-                };
+                }
+            };
 
-            if let Some(usage) = chat_completions_response.usage {
+            if let Some(usage) = usage.as_ref() {
                 self.response_tokens += usage.completion_tokens;
+                self.record_usage(usage);
+            }
+
+            if self.buffer_response_for_cost {
+                if let Some(cost_usd) = self.cost_usd {
+                    self.set_http_response_header(ARCH_COST_HEADER, Some(&format_cost(cost_usd)));
+                    self.cost_reported = true;
+                }
             }
         }
 
@@ -645,6 +1522,37 @@ impl HttpContext for StreamContext {
 
         Action::Continue
     }
+
+    fn on_log(&mut self) {
+        // the request ended without usage e.g. it failed upstream or locally, or its stream had no
+        // usage chunk, hand back the tokens reserved on ingress
+        if !self.budget_reservations.is_empty() {
+            budget::settle(
+                &HostSharedStore,
+                &std::mem::take(&mut self.budget_reservations),
+                0,
+                0.0,
+            );
+        }
+
+        let response_code_details = self
+            .get_property(vec!["response", "code_details"])
+            .map(|details| String::from_utf8_lossy(&details).into_owned());
+        if response_code_details.as_deref() == Some("downstream_remote_disconnect") {
+            self.count_error("client_disconnect");
+        }
+
+        if self.client_api.is_some() {
+            // recorded once the request is done, to be tagged with the status it ended with
+            if let Some(input_token_count) = self.input_token_count {
+                self.metrics
+                    .input_sequence_length
+                    .with_tags(&self.metric_tags())
+                    .record(input_token_count as u64);
+            }
+            self.emit_egress_span();
+        }
+    }
 }
 
 fn current_time_ns() -> u128 {
@@ -654,4 +1562,271 @@ fn current_time_ns() -> u128 {
         .as_nanos()
 }
 
-impl Context for StreamContext {}
+fn format_cost(cost_usd: f64) -> String {
+    format!("{:.6}", cost_usd)
+}
+
+fn current_time_secs() -> u64 {
+    (current_time_ns() / 1_000_000_000) as u64
+}
+
+fn current_time_ms() -> u64 {
+    (current_time_ns() / 1_000_000) as u64
+}
+
+fn is_available(llm_provider: &LlmProvider) -> bool {
+    circuit_breaker::is_available(&HostSharedStore, llm_provider, current_time_ms())
+}
+
+fn try_acquire(llm_provider: &LlmProvider) -> bool {
+    circuit_breaker::try_acquire(&HostSharedStore, llm_provider, current_time_ms())
+}
+
+// Headers that authenticate a request with the provider's access key, a configured auth header or
+// prefix takes precedence over the provider's own scheme.
+fn provider_auth_headers<'a>(
+    llm_provider: &'a LlmProvider,
+    access_key: &str,
+) -> Vec<(&'a str, String)> {
+    if llm_provider.auth_header.is_some() || llm_provider.auth_prefix.is_some() {
+        let name = llm_provider
+            .auth_header
+            .as_deref()
+            .unwrap_or("Authorization");
+        let value = match llm_provider.auth_prefix.as_deref() {
+            Some("") => access_key.to_string(),
+            Some(prefix) => format!("{} {}", prefix, access_key),
+            None if name.eq_ignore_ascii_case("Authorization") => format!("Bearer {}", access_key),
+            None => access_key.to_string(),
+        };
+        return vec![(name, value)];
+    }
+    match llm_provider.provider_interface {
+        LlmProviderType::Claude => vec![
+            (ANTHROPIC_API_KEY_HEADER, access_key.to_string()),
+            (ANTHROPIC_VERSION_HEADER, ANTHROPIC_VERSION.to_string()),
+        ],
+        LlmProviderType::Gemini => vec![(GEMINI_API_KEY_HEADER, access_key.to_string())],
+        LlmProviderType::AzureOpenAI => vec![(AZURE_API_KEY_HEADER, access_key.to_string())],
+        _ => vec![("Authorization", format!("Bearer {}", access_key))],
+    }
+}
+
+// Headers that sign a request with AWS SigV4, the signature covers the path and the body.
+fn aws_auth_headers(
+    llm_provider: &LlmProvider,
+    aws: &AwsConfig,
+    path: &str,
+    body: &[u8],
+) -> Vec<(&'static str, String)> {
+    // envoy rewrites the host to the endpoint's
+    let host = llm_provider
+        .endpoint
+        .clone()
+        .unwrap_or_else(|| aws.bedrock_host());
+    let credentials = aws.credentials();
+    SigV4Signer::new(&credentials, &aws.region, "bedrock").sign(
+        "POST",
+        path,
+        &[("host", &host)],
+        body,
+        current_time_secs(),
+    )
+}
+
+// https://opentelemetry.io/docs/specs/semconv/attributes-registry/gen-ai/#gen-ai-system
+fn gen_ai_system(provider_interface: &LlmProviderType) -> String {
+    match provider_interface {
+        LlmProviderType::Claude => "anthropic".to_string(),
+        LlmProviderType::Mistral => "mistral_ai".to_string(),
+        LlmProviderType::Gemini => "gcp.gemini".to_string(),
+        LlmProviderType::Bedrock => "aws.bedrock".to_string(),
+        LlmProviderType::AzureOpenAI => "az.ai.openai".to_string(),
+        _ => provider_interface.to_string(),
+    }
+}
+
+fn is_success_status(status: u16) -> bool {
+    StatusCode::from_u16(status).is_ok_and(|status| status.is_success())
+}
+
+fn is_upstream_failure(status: u16) -> bool {
+    StatusCode::from_u16(status)
+        .map(|status| status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+        .unwrap_or(false)
+}
+
+// The model to name in the request body, azure names the deployment that serves the model.
+fn upstream_model(llm_provider: &LlmProvider, model: &str) -> String {
+    match llm_provider.provider_interface {
+        LlmProviderType::AzureOpenAI => llm_provider.azure_deployment().unwrap_or(model),
+        _ => model,
+    }
+    .to_string()
+}
+
+// Rewrites an openai compatible request path to the path the provider serves it on. Gemini and
+// Bedrock carry the model in the path, so their paths are only known once the request body is read.
+fn provider_request_path(
+    llm_provider: &LlmProvider,
+    path: &str,
+    request: Option<&ChatCompletionsRequest>,
+) -> String {
+    let provider_interface = &llm_provider.provider_interface;
+    // anthropic requests are converted to chat completions for every provider but anthropic,
+    // responses requests for every provider
+    let path = if path == RESPONSES_PATH
+        || path == ANTHROPIC_MESSAGES_PATH
+            && LlmApi::of_provider(provider_interface) != LlmApi::Messages
+    {
+        CHAT_COMPLETIONS_PATH
+    } else {
+        path
+    };
+    match provider_interface {
+        LlmProviderType::Claude if path == CHAT_COMPLETIONS_PATH => {
+            ANTHROPIC_MESSAGES_PATH.to_string()
+        }
+        LlmProviderType::Gemini if path == CHAT_COMPLETIONS_PATH => match request {
+            Some(request) => {
+                GeminiApi::for_stream(request.stream.unwrap_or_default()).path(&request.model)
+            }
+            None => path.to_string(),
+        },
+        LlmProviderType::AzureOpenAI
+            if path == CHAT_COMPLETIONS_PATH || path == EMBEDDINGS_PATH =>
+        {
+            match llm_provider.azure_deployment() {
+                Some(deployment) => format!(
+                    "/openai/deployments/{}{}?api-version={}",
+                    deployment,
+                    &path[3..],
+                    llm_provider
+                        .api_version
+                        .as_deref()
+                        .unwrap_or(AZURE_OPENAI_API_VERSION)
+                ),
+                None => path.to_string(),
+            }
+        }
+        LlmProviderType::Bedrock if path == CHAT_COMPLETIONS_PATH => match request {
+            Some(request) => {
+                BedrockApi::for_stream(request.stream.unwrap_or_default()).path(&request.model)
+            }
+            None => path.to_string(),
+        },
+        _ if path.starts_with("/v1/") => match llm_provider.openai_base_path() {
+            Some(base_path) => format!("{}{}", base_path.trim_end_matches('/'), &path[3..]),
+            None => path.to_string(),
+        },
+        _ => path.to_string(),
+    }
+}
+
+impl Client for StreamContext {
+    type CallContext = FailoverCallContext;
+
+    fn callouts(&self) -> &RefCell<HashMap<u32, Self::CallContext>> {
+        &self.callouts
+    }
+
+    fn active_http_calls(&self) -> &Gauge {
+        &self.metrics.active_http_calls
+    }
+}
+
+impl Context for StreamContext {
+    fn on_http_call_response(
+        &mut self,
+        token_id: u32,
+        _num_headers: usize,
+        body_size: usize,
+        _num_trailers: usize,
+    ) {
+        let callout_context = self
+            .callouts
+            .get_mut()
+            .remove(&token_id)
+            .expect("invalid token_id");
+        self.metrics.active_http_calls.increment(-1);
+
+        // a missing status means the call itself failed e.g. it was reset or timed out
+        let status = self
+            .get_http_call_response_header(":status")
+            .and_then(|status| status.parse::<u16>().ok())
+            .unwrap_or(StatusCode::SERVICE_UNAVAILABLE.as_u16());
+
+        info!(
+            "on_http_call_response: fallback llm provider {} responded with status {}",
+            callout_context.llm_provider, status
+        );
+        self.count_upstream_status(status);
+
+        if is_upstream_failure(status) {
+            circuit_breaker::record_failure(
+                &HostSharedStore,
+                self.llm_provider(),
+                current_time_ms(),
+            );
+            if self.dispatch_to_next_fallback(status) {
+                return;
+            }
+        } else {
+            circuit_breaker::record_success(&HostSharedStore, self.llm_provider());
+        }
+
+        let mut body = self
+            .get_http_call_response_body(0, body_size)
+            .unwrap_or_default();
+        if let Some(client_api) = self.client_api.filter(|_| is_success_status(status)) {
+            // The fallback response arrives whole through a callout, so a streamed one is also
+            // translated in one go and reaches the client as a single block of events.
+            let upstream_api = LlmApi::of_provider(&self.llm_provider().provider_interface);
+            match ResponseTranslator::default().translate(
+                upstream_api,
+                client_api,
+                self.streaming_response,
+                &body,
+            ) {
+                Ok(translated) => {
+                    self.read_fallback_response(&translated.chat_completions);
+                    body = translated.client.unwrap_or(body);
+                }
+                Err(e) if client_api == upstream_api => {
+                    // the client receives the upstream body as is, only its usage is lost
+                    warn!("could not translate response: {}", e);
+                    self.count_error("response_parse");
+                }
+                Err(e) => {
+                    self.send_server_error(
+                        ServerError::ResponseTranslation(e),
+                        Some(StatusCode::BAD_GATEWAY),
+                    );
+                    return;
+                }
+            }
+        }
+        let response_headers = self.get_http_call_response_headers();
+        let quota_headers = self.ratelimit_quota.map(|quota| quota.headers());
+        let cost = self.cost_usd.map(format_cost);
+        let headers = response_headers
+            .iter()
+            .filter(|(name, _)| {
+                !name.starts_with(':') && name != "content-length" && name != "transfer-encoding"
+            })
+            .filter(|(name, _)| quota_headers.is_none() || !name.starts_with("x-ratelimit-"))
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .chain(
+                quota_headers
+                    .iter()
+                    .flatten()
+                    .map(|(name, value)| (*name, value.as_str())),
+            )
+            .chain(cost.iter().map(|cost| (ARCH_COST_HEADER, cost.as_str())))
+            .collect();
+
+        self.cost_reported = self.cost_usd.is_some();
+        self.response_status = Some(status);
+        self.send_http_response(status.into(), headers, Some(&body));
+    }
+}
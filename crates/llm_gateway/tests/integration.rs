@@ -81,13 +81,9 @@ fn setup_filter(module: &mut Tester, config: &str) -> i32 {
     module
         .call_proxy_on_context_create(filter_context, 0)
         .expect_metric_creation(MetricType::Gauge, "active_http_calls")
-        .expect_metric_creation(MetricType::Counter, "ratelimited_rq")
-        .expect_metric_creation(MetricType::Histogram, "time_to_first_token")
-        .expect_metric_creation(MetricType::Histogram, "time_per_output_token")
-        .expect_metric_creation(MetricType::Histogram, "tokens_per_second")
-        .expect_metric_creation(MetricType::Histogram, "request_latency")
-        .expect_metric_creation(MetricType::Histogram, "output_sequence_length")
-        .expect_metric_creation(MetricType::Histogram, "input_sequence_length")
+        .expect_metric_creation(MetricType::Counter, "trace_exports_succeeded")
+        .expect_metric_creation(MetricType::Counter, "trace_exports_failed")
+        .expect_metric_creation(MetricType::Counter, "trace_spans_dropped")
         .execute_and_expect(ReturnType::None)
         .unwrap();
 
@@ -217,7 +213,6 @@ fn llm_gateway_successful_request_to_open_ai_chat_completions() {
         .expect_log(Some(LogLevel::Info), None)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
-        .expect_metric_record("input_sequence_length", 21)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_set_buffer_bytes(Some(BufferType::HttpRequestBody), None)
@@ -276,7 +271,6 @@ fn llm_gateway_bad_request_to_open_ai_chat_completions() {
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
-        .expect_metric_record("input_sequence_length", 13)
         .expect_log(Some(LogLevel::Debug), None)
         .execute_and_expect(ReturnType::Action(Action::Continue))
         .unwrap();
@@ -334,17 +328,28 @@ fn llm_gateway_request_ratelimited() {
         .expect_log(Some(LogLevel::Info), None)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
-        .expect_metric_record("input_sequence_length", 107)
         .expect_log(Some(LogLevel::Debug), Some("Applying ratelimit for model: gpt-4"))
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Warn), Some(r#"server error occurred: exceeded limit provider=gpt-4, selector=Header { key: "selector-key", value: "selector-value" }, tokens_used=107"#))
+        .expect_metric_creation(
+            MetricType::Counter,
+            "request_errors;provider=open-ai-gpt-4;class=ratelimited",
+        )
+        .expect_metric_increment("request_errors;provider=open-ai-gpt-4;class=ratelimited", 1)
         .expect_send_local_response(
             Some(StatusCode::TOO_MANY_REQUESTS.as_u16().into()),
             None,
             None,
             None,
         )
-        .expect_metric_increment("ratelimited_rq", 1)
+        .expect_metric_creation(
+            MetricType::Counter,
+            "ratelimited_rq;provider=open-ai-gpt-4;model=gpt-4;status=429",
+        )
+        .expect_metric_increment(
+            "ratelimited_rq;provider=open-ai-gpt-4;model=gpt-4;status=429",
+            1,
+        )
         .execute_and_expect(ReturnType::Action(Action::Continue))
         .unwrap();
 }
@@ -388,7 +393,6 @@ fn llm_gateway_request_not_ratelimited() {
         .expect_log(Some(LogLevel::Info), None)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
-        .expect_metric_record("input_sequence_length", 29)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_set_buffer_bytes(Some(BufferType::HttpRequestBody), None)
@@ -435,7 +439,6 @@ fn llm_gateway_override_model_name() {
         .expect_log(Some(LogLevel::Info), Some("on_http_request_body: provider: open-ai-gpt-4, model requested (in body): gpt-1, model selected: gpt-4"))
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
-        .expect_metric_record("input_sequence_length", 29)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_set_buffer_bytes(Some(BufferType::HttpRequestBody), None)
@@ -485,7 +488,6 @@ fn llm_gateway_override_use_default_model() {
         )
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
-        .expect_metric_record("input_sequence_length", 29)
         .expect_log(Some(LogLevel::Debug), Some("Applying ratelimit for model: gpt-4"))
         .expect_log(Some(LogLevel::Debug), Some(r#"Checking limit for provider=gpt-4, with selector=Header { key: "selector-key", value: "selector-value" }, consuming tokens=29"#))
         .expect_set_buffer_bytes(Some(BufferType::HttpRequestBody), None)
@@ -532,10 +534,144 @@ fn llm_gateway_override_use_model_name_none() {
         .expect_log(Some(LogLevel::Info), Some("on_http_request_body: provider: open-ai-gpt-4, model requested (in body): none, model selected: gpt-4"))
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
-        .expect_metric_record("input_sequence_length", 29)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_log(Some(LogLevel::Debug), None)
         .expect_set_buffer_bytes(Some(BufferType::HttpRequestBody), None)
         .execute_and_expect(ReturnType::Action(Action::Continue))
         .unwrap();
 }
+
+fn fallback_config() -> &'static str {
+    r#"
+version: "0.1-beta"
+
+listener:
+  address: 0.0.0.0
+  port: 10000
+  message_format: huggingface
+  connect_timeout: 0.005s
+
+llm_providers:
+  - name: open-ai-gpt-4
+    provider_interface: openai
+    access_key: secret_key
+    model: gpt-4
+    default: true
+    fallbacks:
+      - open-ai-gpt-4o
+  - name: open-ai-gpt-4o
+    provider_interface: openai
+    access_key: secret_key
+    model: gpt-4o
+    pricing:
+      input: 10
+      output: 30
+
+ratelimits:
+  - model: gpt-4
+    selector:
+      key: selector-key
+      value: selector-value
+    limit:
+      tokens: 100
+      unit: minute
+"#
+}
+
+#[test]
+#[serial]
+fn llm_gateway_fallback_response_is_accounted_for() {
+    let args = tester::MockSettings {
+        wasm_path: wasm_module(),
+        quiet: false,
+        // only the calls that show the failover and its accounting are asserted
+        allow_unexpected: true,
+    };
+    let mut module = tester::mock(args).unwrap();
+
+    module
+        .call_start()
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+
+    let filter_context = setup_filter(&mut module, fallback_config());
+    let http_context = 2;
+
+    normal_flow(&mut module, filter_context, http_context);
+
+    let chat_completions_request_body = r#"{"model":"gpt-1","messages":[{"role":"system","content":"You are a poetic assistant, skilled in explaining complex programming concepts with creative flair."},{"role":"user","content":"Compose a poem that explains the concept of recursion in programming."}]}"#;
+
+    module
+        .call_proxy_on_request_body(
+            http_context,
+            chat_completions_request_body.len() as i32,
+            true,
+        )
+        .expect_get_buffer_bytes(Some(BufferType::HttpRequestBody))
+        .returning(Some(chat_completions_request_body))
+        .expect_set_buffer_bytes(Some(BufferType::HttpRequestBody), None)
+        .execute_and_expect(ReturnType::Action(Action::Continue))
+        .unwrap();
+
+    // the primary provider fails, the request is re-issued to its fallback
+    module
+        .call_proxy_on_response_headers(http_context, 0, false)
+        .expect_get_header_map_value(Some(MapType::HttpResponseHeaders), Some(":status"))
+        .returning(Some("503"))
+        .expect_http_call(
+            Some("arch_internal"),
+            Some(vec![
+                (":method", "POST"),
+                ("x-arch-upstream", "openai"),
+                (":path", "/v1/chat/completions"),
+                (":authority", "openai"),
+                ("content-type", "application/json"),
+                ("x-envoy-upstream-rq-timeout-ms", "60000"),
+                ("Authorization", "Bearer secret_key"),
+            ]),
+            None,
+            None,
+            Some(60000),
+        )
+        .returning(Some(1))
+        .expect_metric_increment("active_http_calls", 1)
+        .execute_and_expect(ReturnType::Action(Action::Pause))
+        .unwrap();
+
+    let chat_completions_response = r#"{"id":"chatcmpl-1","object":"chat.completion","created":1,"model":"gpt-4o","choices":[{"index":0,"message":{"role":"assistant","content":"Recursion is a function calling itself."},"finish_reason":"stop"}],"usage":{"prompt_tokens":20,"completion_tokens":10,"total_tokens":30}}"#;
+
+    // 20 prompt tokens at 10 USD and 10 completion tokens at 30 USD per million tokens, the
+    // quota is the one the input tokens were checked against
+    module
+        .call_proxy_on_http_call_response(
+            http_context,
+            1,
+            0,
+            chat_completions_response.len() as i32,
+            0,
+        )
+        .expect_metric_increment("active_http_calls", -1)
+        .expect_get_header_map_value(Some(MapType::HttpCallResponseHeaders), Some(":status"))
+        .returning(Some("200"))
+        .expect_get_buffer_bytes(Some(BufferType::HttpCallResponseBody))
+        .returning(Some(chat_completions_response))
+        .expect_get_header_map_pairs(Some(MapType::HttpCallResponseHeaders))
+        .returning(Some(vec![
+            (":status", "200"),
+            ("content-type", "application/json"),
+        ]))
+        .expect_send_local_response(
+            Some(StatusCode::OK.as_u16().into()),
+            Some(chat_completions_response),
+            Some(vec![
+                ("content-type", "application/json"),
+                ("x-ratelimit-limit-tokens", "100"),
+                ("x-ratelimit-remaining-tokens", "71"),
+                ("x-ratelimit-reset-tokens", "17.4s"),
+                ("x-arch-cost-usd", "0.000500"),
+            ]),
+            None,
+        )
+        .execute_and_expect(ReturnType::None)
+        .unwrap();
+}
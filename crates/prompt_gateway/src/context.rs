@@ -6,7 +6,7 @@ use http::StatusCode;
 use log::warn;
 use proxy_wasm::traits::Context;
 
-use crate::stream_context::{ResponseHandlerType, StreamContext};
+use crate::stream_context::{ResponseHandlerType, StreamCallContext, StreamContext};
 
 impl Context for StreamContext {
     fn on_http_call_response(
@@ -23,6 +23,17 @@ impl Context for StreamContext {
             .expect("invalid token_id");
         self.metrics.active_http_calls.increment(-1);
 
+        let span = self.callout_span(&callout_context);
+        let response_handler_type = callout_context.response_handler_type.clone();
+        self.handle_http_call_response(callout_context, body_size);
+        if let Some(span) = span {
+            self.record_callout_span(span, response_handler_type);
+        }
+    }
+}
+
+impl StreamContext {
+    fn handle_http_call_response(&mut self, callout_context: StreamCallContext, body_size: usize) {
         let body = self
             .get_http_call_response_body(0, body_size)
             .unwrap_or_default();
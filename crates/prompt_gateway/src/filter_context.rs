@@ -3,14 +3,21 @@ use crate::stream_context::StreamContext;
 use common::configuration::{
     Configuration, Endpoint, Overrides, PromptGuards, PromptTarget, Tracing,
 };
-use common::http::Client;
+use common::consts::{OTEL_COLLECTOR_HTTP, OTEL_POST_PATH};
+use common::http::{CallArgs, Client};
 use common::stats::Gauge;
-use log::trace;
+use common::trace_export::{SpanQueue, TraceExporter};
+use log::{trace, warn};
 use proxy_wasm::traits::*;
 use proxy_wasm::types::*;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+// a stuck export holds back the ones after it, so it is not waited on for long
+const OTEL_EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug)]
 pub struct FilterCallContext {}
@@ -26,6 +33,8 @@ pub struct FilterContext {
     endpoints: Rc<Option<HashMap<String, Endpoint>>>,
     prompt_guards: Rc<PromptGuards>,
     tracing: Rc<Option<Tracing>>,
+    traces_queue: Arc<Mutex<SpanQueue>>,
+    trace_exporter: TraceExporter,
 }
 
 impl FilterContext {
@@ -39,8 +48,17 @@ impl FilterContext {
             prompt_guards: Rc::new(PromptGuards::default()),
             endpoints: Rc::new(None),
             tracing: Rc::new(None),
+            traces_queue: Arc::new(Mutex::new(SpanQueue::default())),
+            trace_exporter: TraceExporter::new("ingress_prompt_traffic"),
         }
     }
+
+    fn current_time_ms(&self) -> u64 {
+        self.get_current_time()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
 }
 
 impl Client for FilterContext {
@@ -55,7 +73,34 @@ impl Client for FilterContext {
     }
 }
 
-impl Context for FilterContext {}
+impl Context for FilterContext {
+    fn on_http_call_response(
+        &mut self,
+        token_id: u32,
+        _num_headers: usize,
+        _body_size: usize,
+        _num_trailers: usize,
+    ) {
+        trace!(
+            "||| on_http_call_response called with token_id: {:?} |||",
+            token_id
+        );
+
+        let _callout_data = self
+            .callouts
+            .borrow_mut()
+            .remove(&token_id)
+            .expect("invalid token_id");
+
+        let status = self.get_http_call_response_header(":status");
+        trace!("trace response status: {:?}", status);
+        let status = status.and_then(|status| status.parse::<u16>().ok());
+        let outcome = self
+            .trace_exporter
+            .export_finished(status, self.current_time_ms());
+        self.metrics.trace_export.record(&outcome);
+    }
+}
 
 // RootContext allows the Rust code to reach into the Envoy Config
 impl RootContext for FilterContext {
@@ -102,6 +147,7 @@ impl RootContext for FilterContext {
             Rc::clone(&self.endpoints),
             Rc::clone(&self.overrides),
             Rc::clone(&self.tracing),
+            Arc::clone(&self.traces_queue),
         )))
     }
 
@@ -110,6 +156,42 @@ impl RootContext for FilterContext {
     }
 
     fn on_vm_start(&mut self, _: usize) -> bool {
+        self.set_tick_period(Duration::from_secs(1));
         true
     }
+
+    fn on_tick(&mut self) {
+        let now_ms = self.current_time_ms();
+        let payload = match self.traces_queue.try_lock() {
+            Ok(mut traces_queue) => self.trace_exporter.next_export(&mut traces_queue, now_ms),
+            Err(_) => None,
+        };
+        let payload = match payload {
+            Some(payload) => payload,
+            None => return,
+        };
+
+        trace!("trace details: {}", payload);
+        let call_args = CallArgs::new(
+            OTEL_COLLECTOR_HTTP,
+            OTEL_POST_PATH,
+            vec![
+                (":method", http::Method::POST.as_str()),
+                (":path", OTEL_POST_PATH),
+                (":authority", OTEL_COLLECTOR_HTTP),
+                ("content-type", "application/json"),
+            ],
+            Some(payload.as_bytes()),
+            vec![],
+            OTEL_EXPORT_TIMEOUT,
+        );
+        if let Err(error) = self.http_call(call_args, FilterCallContext {}) {
+            warn!(
+                "failed to schedule http call to otel-collector: {:?}",
+                error
+            );
+            let outcome = self.trace_exporter.export_finished(None, now_ms);
+            self.metrics.trace_export.record(&outcome);
+        }
+    }
 }
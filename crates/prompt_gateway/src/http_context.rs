@@ -1,4 +1,6 @@
-use crate::stream_context::{ResponseHandlerType, StreamCallContext, StreamContext};
+use crate::stream_context::{
+    current_time_ns, ResponseHandlerType, StreamCallContext, StreamContext,
+};
 use common::{
     api::open_ai::{
         self, ArchState, ChatCompletionStreamResponse, ChatCompletionTool, ChatCompletionsRequest,
@@ -13,6 +15,7 @@ use common::{
     errors::ServerError,
     http::{CallArgs, Client},
     pii::obfuscate_auth_header,
+    tracing::TraceContext,
 };
 use http::StatusCode;
 use log::{debug, info, warn};
@@ -71,7 +74,17 @@ impl HttpContext for StreamContext {
         );
 
         self.request_id = self.get_http_request_header(REQUEST_ID_HEADER);
-        self.traceparent = self.get_http_request_header(TRACE_PARENT_HEADER);
+        self.request_start_time = current_time_ns();
+        self.trace_context = TraceContext::new(
+            self.get_http_request_header(TRACE_PARENT_HEADER).as_deref(),
+            self.tracing.as_ref().as_ref(),
+        );
+        if let Some(trace_context) = self.trace_context.as_ref() {
+            self.set_http_request_header(
+                TRACE_PARENT_HEADER,
+                Some(&trace_context.traceparent().to_string()),
+            );
+        }
 
         Action::Continue
     }
@@ -224,8 +237,12 @@ impl HttpContext for StreamContext {
             headers.push((REQUEST_ID_HEADER, self.request_id.as_ref().unwrap()));
         }
 
-        if self.traceparent.is_some() {
-            headers.push((TRACE_PARENT_HEADER, self.traceparent.as_ref().unwrap()));
+        let traceparent = self
+            .trace_context
+            .as_ref()
+            .map(|trace_context| trace_context.traceparent().to_string());
+        if let Some(traceparent) = traceparent.as_ref() {
+            headers.push((TRACE_PARENT_HEADER, traceparent));
         }
 
         let call_args = CallArgs::new(
@@ -246,6 +263,7 @@ impl HttpContext for StreamContext {
                 similarity_scores: None,
                 upstream_cluster: Some(ARCH_INTERNAL_CLUSTER_NAME.to_string()),
                 upstream_cluster_path: Some("/function_calling".to_string()),
+                start_time: current_time_ns(),
             };
 
             if let Err(e) = self.http_call(call_args, call_context) {
@@ -268,6 +286,9 @@ impl HttpContext for StreamContext {
             self.context_id,
             self.get_http_response_headers()
         );
+        self.response_status = self
+            .get_http_response_header(":status")
+            .and_then(|status| status.parse::<u16>().ok());
         // delete content-lenght header let envoy calculate it, because we modify the response body
         // that would result in a different content-length
         self.set_http_response_header("content-length", None);
@@ -433,4 +454,10 @@ impl HttpContext for StreamContext {
 
         Action::Continue
     }
+
+    fn on_log(&mut self) {
+        if self.is_chat_completions_request {
+            self.emit_spans();
+        }
+    }
 }
@@ -1,14 +1,17 @@
 use common::stats::Gauge;
+use common::trace_export::TraceExportMetrics;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Metrics {
     pub active_http_calls: Gauge,
+    pub trace_export: TraceExportMetrics,
 }
 
 impl Metrics {
     pub fn new() -> Metrics {
         Metrics {
             active_http_calls: Gauge::new(String::from("active_http_calls")),
+            trace_export: TraceExportMetrics::new(),
         }
     }
 }
@@ -14,6 +14,11 @@ use common::consts::{
 use common::errors::ServerError;
 use common::http::{CallArgs, Client};
 use common::stats::Gauge;
+use common::stats::IncrementingMetric;
+use common::trace_export::SpanQueue;
+use common::tracing::{
+    Event, Span, TraceContext, SPAN_KIND_CLIENT, SPAN_KIND_SERVER, STATUS_CODE_ERROR,
+};
 use derivative::Derivative;
 use http::StatusCode;
 use log::{debug, info, warn};
@@ -22,6 +27,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone)]
@@ -42,6 +48,7 @@ pub struct StreamCallContext {
     pub similarity_scores: Option<Vec<(String, f64)>>,
     pub upstream_cluster: Option<String>,
     pub upstream_cluster_path: Option<String>,
+    pub start_time: u128,
 }
 
 pub struct StreamContext {
@@ -63,12 +70,19 @@ pub struct StreamContext {
     pub request_id: Option<String>,
     pub start_upstream_llm_request_time: u128,
     pub time_to_first_token: Option<u128>,
-    pub traceparent: Option<String>,
-    pub _tracing: Rc<Option<Tracing>>,
+    pub trace_context: Option<TraceContext>,
+    pub tracing: Rc<Option<Tracing>>,
+    traces_queue: Arc<Mutex<SpanQueue>>,
+    spans: Vec<Span>,
+    pub request_start_time: u128,
+    pub response_status: Option<u16>,
+    response_error: Option<String>,
+    llm_prompt_target_name: Option<String>,
     pub arch_fc_response: Option<String>,
 }
 
 impl StreamContext {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         context_id: u32,
         metrics: Rc<Metrics>,
@@ -77,6 +91,7 @@ impl StreamContext {
         endpoints: Rc<Option<HashMap<String, Endpoint>>>,
         overrides: Rc<Option<Overrides>>,
         tracing: Rc<Option<Tracing>>,
+        traces_queue: Arc<Mutex<SpanQueue>>,
     ) -> Self {
         StreamContext {
             context_id,
@@ -95,27 +110,37 @@ impl StreamContext {
             is_chat_completions_request: false,
             overrides,
             request_id: None,
-            traceparent: None,
-            _tracing: tracing,
+            trace_context: None,
+            tracing,
+            traces_queue,
+            spans: Vec::new(),
+            request_start_time: 0,
+            response_status: None,
+            response_error: None,
+            llm_prompt_target_name: None,
             start_upstream_llm_request_time: 0,
             time_to_first_token: None,
             arch_fc_response: None,
         }
     }
 
-    pub fn send_server_error(&self, error: ServerError, override_status_code: Option<StatusCode>) {
+    pub fn send_server_error(
+        &mut self,
+        error: ServerError,
+        override_status_code: Option<StatusCode>,
+    ) {
+        let status = override_status_code.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        self.response_status = Some(status.as_u16());
+        self.response_error = Some(error.to_string());
         self.send_http_response(
-            override_status_code
-                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
-                .as_u16()
-                .into(),
+            status.as_u16().into(),
             vec![],
             Some(format!("{error}").as_bytes()),
         );
     }
 
-    fn _trace_arch_internal(&self) -> bool {
-        match self._tracing.as_ref() {
+    fn trace_arch_internal(&self) -> bool {
+        match self.tracing.as_ref() {
             Some(tracing) => match tracing.trace_arch_internal.as_ref() {
                 Some(trace_arch_internal) => *trace_arch_internal,
                 None => false,
@@ -124,6 +149,168 @@ impl StreamContext {
         }
     }
 
+    // Spans of the hops made while serving a request are children of the gateway's span, they
+    // are only recorded when trace_arch_internal is set.
+    fn internal_span(&self, name: &str, start_time: u128) -> Option<Span> {
+        let trace_context = self.trace_context.as_ref()?;
+        if !trace_context.sampled || !self.trace_arch_internal() {
+            return None;
+        }
+        let mut span = Span::new(
+            name.to_string(),
+            Some(trace_context.trace_id.clone()),
+            Some(trace_context.span_id.clone()),
+            start_time,
+            current_time_ns(),
+        );
+        span.kind = SPAN_KIND_CLIENT;
+        Some(span)
+    }
+
+    // Called with the response of a callout, before its handler runs.
+    pub fn callout_span(&self, callout_context: &StreamCallContext) -> Option<Span> {
+        let name = match callout_context.response_handler_type {
+            ResponseHandlerType::ArchFC => "arch_function_calling",
+            ResponseHandlerType::FunctionCall => "api_call",
+            ResponseHandlerType::DefaultTarget => "default_target",
+        };
+        let mut span = self.internal_span(name, callout_context.start_time)?;
+
+        if let Some(upstream_cluster) = callout_context.upstream_cluster.as_ref() {
+            span.add_attribute("server.address".to_string(), upstream_cluster.clone());
+        }
+        if let Some(path) = callout_context.upstream_cluster_path.as_ref() {
+            span.add_attribute("url.path".to_string(), path.clone());
+        }
+        if let Some(prompt_target_name) = callout_context.prompt_target_name.as_ref() {
+            span.add_attribute("prompt_target.name".to_string(), prompt_target_name.clone());
+        }
+        if let ResponseHandlerType::FunctionCall = callout_context.response_handler_type {
+            if let Some(arguments) = self.tool_call_arguments() {
+                span.add_attribute("tool.arguments".to_string(), arguments);
+            }
+        }
+
+        // a missing status means the call itself failed e.g. it was reset or timed out
+        let status = self
+            .get_http_call_response_header(":status")
+            .and_then(|status| status.parse::<u16>().ok());
+        match status {
+            Some(status) => {
+                span.add_int_attribute("http.response.status_code".to_string(), status as i64);
+                if status >= 400 {
+                    span.add_attribute("error.type".to_string(), status.to_string());
+                    span.set_status(STATUS_CODE_ERROR, None);
+                }
+            }
+            None => span.set_status(STATUS_CODE_ERROR, Some("no response".to_string())),
+        }
+        Some(span)
+    }
+
+    // The Arch-Function response decides the prompt target and its arguments, they are added to
+    // its span once the response is handled.
+    pub fn record_callout_span(
+        &mut self,
+        mut span: Span,
+        response_handler_type: ResponseHandlerType,
+    ) {
+        if let ResponseHandlerType::ArchFC = response_handler_type {
+            if let Some(tool_call) = self
+                .tool_calls
+                .as_ref()
+                .and_then(|tool_calls| tool_calls.first())
+            {
+                span.add_attribute(
+                    "prompt_target.name".to_string(),
+                    tool_call.function.name.clone(),
+                );
+            }
+            if let Some(arguments) = self.tool_call_arguments() {
+                span.add_attribute("tool.arguments".to_string(), arguments);
+            }
+        }
+        self.spans.push(span);
+    }
+
+    fn tool_call_arguments(&self) -> Option<String> {
+        let tool_call = self.tool_calls.as_ref()?.first()?;
+        serde_json::to_string(tool_call.function.arguments.as_ref()?).ok()
+    }
+
+    // Sends the request on to the upstream llm, the rewritten body replaces the client's.
+    fn send_to_upstream_llm(&mut self, body: &[u8], prompt_target_name: Option<String>) {
+        self.start_upstream_llm_request_time = current_time_ns();
+        self.llm_prompt_target_name = prompt_target_name;
+        self.set_http_request_body(0, self.request_body_size, body);
+        self.resume_http_request();
+    }
+
+    pub fn emit_spans(&mut self) {
+        let trace_context = match self.trace_context.as_ref() {
+            Some(trace_context) if trace_context.sampled => trace_context,
+            _ => return,
+        };
+        let mut gateway_span = trace_context.span(
+            "prompt_gateway".to_string(),
+            self.request_start_time,
+            current_time_ns(),
+        );
+        gateway_span.kind = SPAN_KIND_SERVER;
+        if let Some(status) = self.response_status {
+            gateway_span.add_int_attribute("http.response.status_code".to_string(), status as i64);
+            if status >= 400 {
+                gateway_span.add_attribute("error.type".to_string(), status.to_string());
+                gateway_span.set_status(STATUS_CODE_ERROR, self.response_error.clone());
+            }
+        }
+        self.spans.push(gateway_span);
+
+        if self.start_upstream_llm_request_time > 0 {
+            if let Some(mut llm_span) =
+                self.internal_span("upstream_llm", self.start_upstream_llm_request_time)
+            {
+                if let Some(request) = self.chat_completions_request.as_ref() {
+                    llm_span
+                        .add_attribute("gen_ai.request.model".to_string(), request.model.clone());
+                }
+                if let Some(prompt_target_name) = self.llm_prompt_target_name.as_ref() {
+                    llm_span.add_attribute(
+                        "prompt_target.name".to_string(),
+                        prompt_target_name.clone(),
+                    );
+                }
+                if let Some(time_to_first_token) = self.time_to_first_token {
+                    llm_span.add_event(Event::new(
+                        "time_to_first_token".to_string(),
+                        time_to_first_token,
+                    ));
+                }
+                if let Some(status) = self.response_status {
+                    llm_span
+                        .add_int_attribute("http.response.status_code".to_string(), status as i64);
+                    if status >= 400 {
+                        llm_span.add_attribute("error.type".to_string(), status.to_string());
+                        llm_span.set_status(STATUS_CODE_ERROR, self.response_error.clone());
+                    }
+                }
+                self.spans.push(llm_span);
+            }
+        }
+
+        let dropped = self
+            .traces_queue
+            .lock()
+            .unwrap()
+            .push(std::mem::take(&mut self.spans));
+        if dropped > 0 {
+            self.metrics
+                .trace_export
+                .dropped_spans
+                .increment(dropped as i64);
+        }
+    }
+
     pub fn arch_fc_response_handler(
         &mut self,
         body: Vec<u8>,
@@ -187,6 +374,14 @@ impl StreamContext {
                     headers.push((REQUEST_ID_HEADER, self.request_id.as_ref().unwrap()));
                 }
 
+                let traceparent = self
+                    .trace_context
+                    .as_ref()
+                    .map(|trace_context| trace_context.traceparent().to_string());
+                if let Some(traceparent) = traceparent.as_ref() {
+                    headers.push((TRACE_PARENT_HEADER, traceparent));
+                }
+
                 let call_args = CallArgs::new(
                     ARCH_INTERNAL_CLUSTER_NAME,
                     &upstream_path,
@@ -197,6 +392,7 @@ impl StreamContext {
                 );
                 callout_context.response_handler_type = ResponseHandlerType::DefaultTarget;
                 callout_context.prompt_target_name = Some(default_prompt_target.name.clone());
+                callout_context.start_time = current_time_ns();
 
                 if let Err(e) = self.http_call(call_args, callout_context) {
                     warn!("error dispatching default prompt target request: {}", e);
@@ -249,12 +445,7 @@ impl StreamContext {
                     "archgw => upstream llm request: {}",
                     chat_completion_request_json
                 );
-                self.set_http_request_body(
-                    0,
-                    self.request_body_size,
-                    chat_completion_request_json.as_bytes(),
-                );
-                self.resume_http_request();
+                self.send_to_upstream_llm(chat_completion_request_json.as_bytes(), None);
                 return;
             }
         }
@@ -357,8 +548,10 @@ impl StreamContext {
 
                 let body_str = serde_json::to_string(&chat_completion_request).unwrap();
                 info!("sending request to llm agent: {}", body_str);
-                self.set_http_request_body(0, self.request_body_size, body_str.as_bytes());
-                self.resume_http_request();
+                self.send_to_upstream_llm(
+                    body_str.as_bytes(),
+                    callout_context.prompt_target_name.clone(),
+                );
                 return;
             }
         }
@@ -420,8 +613,12 @@ impl StreamContext {
             headers.insert(REQUEST_ID_HEADER, self.request_id.as_ref().unwrap());
         }
 
-        if self.traceparent.is_some() {
-            headers.insert(TRACE_PARENT_HEADER, self.traceparent.as_ref().unwrap());
+        let traceparent = self
+            .trace_context
+            .as_ref()
+            .map(|trace_context| trace_context.traceparent().to_string());
+        if let Some(traceparent) = traceparent.as_ref() {
+            headers.insert(TRACE_PARENT_HEADER, traceparent);
         }
 
         // override http headers that are set in the prompt target
@@ -447,6 +644,7 @@ impl StreamContext {
         callout_context.upstream_cluster = Some(endpoint_details.name.to_owned());
         callout_context.upstream_cluster_path = Some(path.to_owned());
         callout_context.response_handler_type = ResponseHandlerType::FunctionCall;
+        callout_context.start_time = current_time_ns();
 
         if let Err(e) = self.http_call(call_args, callout_context) {
             self.send_server_error(ServerError::HttpDispatch(e), Some(StatusCode::BAD_REQUEST));
@@ -567,13 +765,10 @@ impl StreamContext {
         info!("on_http_call_response: sending request to upstream llm");
         debug!("request body: {}", llm_request_str);
 
-        self.start_upstream_llm_request_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-
-        self.set_http_request_body(0, self.request_body_size, &llm_request_str.into_bytes());
-        self.resume_http_request();
+        self.send_to_upstream_llm(
+            llm_request_str.as_bytes(),
+            callout_context.prompt_target_name,
+        );
     }
 
     fn get_system_prompt(&self, prompt_target: Option<PromptTarget>) -> Option<String> {
@@ -661,7 +856,11 @@ impl StreamContext {
         }
     }
 
-    pub fn default_target_handler(&self, body: Vec<u8>, mut callout_context: StreamCallContext) {
+    pub fn default_target_handler(
+        &mut self,
+        body: Vec<u8>,
+        mut callout_context: StreamCallContext,
+    ) {
         let prompt_target = self
             .prompt_targets
             .get(callout_context.prompt_target_name.as_ref().unwrap())
@@ -781,11 +980,17 @@ impl StreamContext {
 
         let json_resp = serde_json::to_string(&chat_completion_request).unwrap();
         info!("archgw => (default target) llm request: {}", json_resp);
-        self.set_http_request_body(0, self.request_body_size, json_resp.as_bytes());
-        self.resume_http_request();
+        self.send_to_upstream_llm(json_resp.as_bytes(), callout_context.prompt_target_name);
     }
 }
 
+pub fn current_time_ns() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos()
+}
+
 fn check_intent_matched(model_server_response: &ChatCompletionsResponse) -> bool {
     let content = model_server_response
         .choices
@@ -104,6 +104,9 @@ fn setup_filter(module: &mut Tester, config: &str) -> i32 {
     module
         .call_proxy_on_context_create(filter_context, 0)
         .expect_metric_creation(MetricType::Gauge, "active_http_calls")
+        .expect_metric_creation(MetricType::Counter, "trace_exports_succeeded")
+        .expect_metric_creation(MetricType::Counter, "trace_exports_failed")
+        .expect_metric_creation(MetricType::Counter, "trace_spans_dropped")
         .execute_and_expect(ReturnType::None)
         .unwrap();
 
@@ -329,7 +332,11 @@ fn prompt_gateway_request_to_llm_gateway() {
 
     // Setup Filter
     let mut config: Configuration = serde_yaml::from_str(default_config()).unwrap();
-    config.ratelimits.as_mut().unwrap()[0].limit.tokens += 1000;
+    config.ratelimits.as_mut().unwrap()[0]
+        .limit
+        .as_mut()
+        .unwrap()
+        .tokens += 1000;
     let config_str = serde_json::to_string(&config).unwrap();
 
     let filter_context = setup_filter(&mut module, &config_str);
@@ -480,7 +487,11 @@ fn prompt_gateway_request_no_intent_match() {
 
     // Setup Filter
     let mut config: Configuration = serde_yaml::from_str(default_config()).unwrap();
-    config.ratelimits.as_mut().unwrap()[0].limit.tokens += 1000;
+    config.ratelimits.as_mut().unwrap()[0]
+        .limit
+        .as_mut()
+        .unwrap()
+        .tokens += 1000;
     let config_str = serde_json::to_string(&config).unwrap();
 
     let filter_context = setup_filter(&mut module, &config_str);
@@ -627,7 +638,11 @@ fn prompt_gateway_request_no_intent_match_default_target() {
 
     // Setup Filter
     let mut config: Configuration = serde_yaml::from_str(arch_config_default_target()).unwrap();
-    config.ratelimits.as_mut().unwrap()[0].limit.tokens += 1000;
+    config.ratelimits.as_mut().unwrap()[0]
+        .limit
+        .as_mut()
+        .unwrap()
+        .tokens += 1000;
     let config_str = serde_json::to_string(&config).unwrap();
 
     let filter_context = setup_filter(&mut module, &config_str);